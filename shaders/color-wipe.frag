@@ -9,6 +9,7 @@ layout(set = 0, binding = 0) uniform Uniforms {
     float u_time_delta;
     uint u_frame_num;
     uint u_num_textures;
+    vec4 u_tile_offset;
 };
 
 layout(set = 0, binding = 1) uniform CustomUniforms { bool vertical_wipe; };
@@ -20,8 +21,10 @@ void main() {
     float window_wiper = sin(0.5 * u_time);
     window_wiper *= window_wiper;
 
-    bool color_condition = (vertical_wipe) ? tex_coords.y < window_wiper
-                                           : tex_coords.x < window_wiper;
+    // Pixel coordinates within the whole painting, so tiled paintings line up.
+    vec2 uv = (gl_FragCoord.xy + u_tile_offset.xy) / u_resolution.xy;
+    bool color_condition = (vertical_wipe) ? uv.y < window_wiper
+                                           : uv.x < window_wiper;
 
     if (color_condition) {
         f_color = vec4(0.f, 0.5f, 0.5f, 1.f);
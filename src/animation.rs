@@ -0,0 +1,225 @@
+use crate::vector::UIntVector2;
+use color_quant::NeuQuant;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Sampling factor for palette quantisation, from 1 (best, slowest) to 30. 10 is the usual trade-off.
+static QUANTIZER_SAMPLE_FACTOR: i32 = 10;
+
+/// File formats for short looping animations, written without FFmpeg.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationFormat {
+    /// Animated GIF with a 256 colour palette per frame. Transparency is not kept.
+    Gif,
+    /// Animated PNG, 8 bits per component with alpha.
+    Apng,
+}
+
+impl AnimationFormat {
+    /// Short name used to select the format on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "apng",
+        }
+    }
+
+    /// File extension for the format, without the leading dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+        }
+    }
+
+    /// Human-readable description of the format, as shown in the GUI.
+    pub fn description(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "Animated GIF",
+            AnimationFormat::Apng => "Animated PNG",
+        }
+    }
+}
+
+/// Options for writing a looping animation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AnimationOptions {
+    /// Apply Floyd-Steinberg dithering when reducing GIF frames to their palette. Ignored for APNG.
+    pub dither: bool,
+    /// Play the frames forwards then backwards, so the loop has no jump back to the first frame.
+    pub ping_pong: bool,
+}
+
+/// Private helper that returns the order to play `count` frames in.
+/// Ping-pong loops skip the first and last frame on the way back, so neither is shown twice in a row.
+fn frame_order(count: usize, ping_pong: bool) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..count).collect();
+    if ping_pong && count > 2 {
+        order.extend((1..count - 1).rev());
+    }
+    order
+}
+
+/// Write frames as an endlessly looping animation.
+/// Returns an error message if the file could not be written.
+/// * `frames` - Tightly packed 8-bit sRGB RGBA pixels of each frame, in order.
+/// * `resolution` - Width and height of every frame.
+/// * `framerate` - Frames per second to play the animation at.
+/// * `format` - File format to write.
+/// * `options` - Dithering and ping-pong playback.
+/// * `filename` - File to write, relative to the working directory.
+pub fn write_animation(
+    frames: &[Vec<u8>],
+    resolution: UIntVector2,
+    framerate: u32,
+    format: AnimationFormat,
+    options: AnimationOptions,
+    filename: &str,
+) -> Result<(), String> {
+    if frames.is_empty() {
+        return Err(String::from("No frames were recorded"));
+    }
+    let order = frame_order(frames.len(), options.ping_pong);
+    let file = File::create(Path::new(filename)).map_err(|e| e.to_string())?;
+    match format {
+        AnimationFormat::Gif => {
+            write_gif(frames, &order, resolution, framerate, options.dither, file)
+        }
+        AnimationFormat::Apng => write_apng(frames, &order, resolution, framerate, file),
+    }
+}
+
+/// Private helper that writes an animated GIF, quantising each frame to its own palette.
+fn write_gif(
+    frames: &[Vec<u8>],
+    order: &[usize],
+    resolution: UIntVector2,
+    framerate: u32,
+    dither: bool,
+    file: File,
+) -> Result<(), String> {
+    if resolution.x > u16::MAX as u32 || resolution.y > u16::MAX as u32 {
+        return Err(format!(
+            "GIFs can be at most {}x{} pixels",
+            u16::MAX,
+            u16::MAX
+        ));
+    }
+    let width = resolution.x as u16;
+    let height = resolution.y as u16;
+    let mut encoder =
+        gif::Encoder::new(BufWriter::new(file), width, height, &[]).map_err(|e| e.to_string())?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(|e| e.to_string())?;
+
+    // Quantise each source frame once, even if ping-pong plays it twice.
+    let quantized: Vec<(Vec<u8>, Vec<u8>)> = frames
+        .iter()
+        .map(|frame| quantize_frame(frame, resolution.x as usize, dither))
+        .collect();
+    // GIF delays are in hundredths of a second. Round the running time rather than each delay so the loop doesn't drift.
+    let centiseconds = |frame: usize| (frame as f64 * 100.0 / framerate as f64).round() as u16;
+    for (i, &frame_idx) in order.iter().enumerate() {
+        let (palette, indices) = &quantized[frame_idx];
+        let frame = gif::Frame {
+            width,
+            height,
+            delay: centiseconds(i + 1) - centiseconds(i),
+            palette: Some(palette.clone()),
+            buffer: std::borrow::Cow::Borrowed(indices),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Private helper that reduces a frame to a palette of 256 colours.
+/// Returns the RGB palette and the palette index of every pixel.
+fn quantize_frame(rgba: &[u8], width: usize, dither: bool) -> (Vec<u8>, Vec<u8>) {
+    // GIF has no partial transparency, so frames are quantised as opaque.
+    let mut opaque = rgba.to_vec();
+    for pixel in opaque.chunks_exact_mut(4) {
+        pixel[3] = 255;
+    }
+    let quantizer = NeuQuant::new(QUANTIZER_SAMPLE_FACTOR, 256, &opaque);
+    let indices = match dither {
+        false => opaque
+            .chunks_exact(4)
+            .map(|pixel| quantizer.index_of(pixel) as u8)
+            .collect(),
+        true => dither_frame(&quantizer, &opaque, width),
+    };
+    (quantizer.color_map_rgb(), indices)
+}
+
+/// Private helper that maps a frame to palette indices with Floyd-Steinberg error diffusion.
+fn dither_frame(quantizer: &NeuQuant, rgba: &[u8], width: usize) -> Vec<u8> {
+    let mut pixels: Vec<[f32; 3]> = rgba
+        .chunks_exact(4)
+        .map(|p| [p[0] as f32, p[1] as f32, p[2] as f32])
+        .collect();
+    let height = pixels.len() / width;
+    let mut indices = Vec::with_capacity(pixels.len());
+    for y in 0..height {
+        for x in 0..width {
+            let wanted = pixels[y * width + x];
+            let mut pixel = [255u8; 4];
+            for c in 0..3 {
+                pixel[c] = wanted[c].round().clamp(0.0, 255.0) as u8;
+            }
+            let index = quantizer.index_of(&pixel);
+            indices.push(index as u8);
+
+            let chosen = quantizer.lookup(index).unwrap();
+            let mut error = [0.0; 3];
+            for c in 0..3 {
+                error[c] = wanted[c] - chosen[c] as f32;
+            }
+            // Spread the error to the neighbours not yet visited.
+            let neighbours = [
+                (x + 1, y, 7.0 / 16.0),
+                (x.wrapping_sub(1), y + 1, 3.0 / 16.0),
+                (x, y + 1, 5.0 / 16.0),
+                (x + 1, y + 1, 1.0 / 16.0),
+            ];
+            for &(nx, ny, weight) in &neighbours {
+                if nx < width && ny < height {
+                    let neighbour = &mut pixels[ny * width + nx];
+                    for c in 0..3 {
+                        neighbour[c] += error[c] * weight;
+                    }
+                }
+            }
+        }
+    }
+    indices
+}
+
+/// Private helper that writes an animated PNG.
+fn write_apng(
+    frames: &[Vec<u8>],
+    order: &[usize],
+    resolution: UIntVector2,
+    framerate: u32,
+    file: File,
+) -> Result<(), String> {
+    let mut encoder = png::Encoder::new(BufWriter::new(file), resolution.x, resolution.y);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(order.len() as u32, 0)
+        .map_err(|e| e.to_string())?;
+    encoder
+        .set_frame_delay(1, framerate.min(u16::MAX as u32) as u16)
+        .map_err(|e| e.to_string())?;
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    for &frame_idx in order {
+        writer
+            .write_image_data(&frames[frame_idx])
+            .map_err(|e| e.to_string())?;
+    }
+    writer.finish().map_err(|e| e.to_string())
+}
@@ -1,10 +1,10 @@
-use crate::uniforms::load_uniforms_from_json;
+use easel_rs::keyframes::{keyframes_to_json, load_keyframes_from_json};
+use easel_rs::lfo::{lfos_to_json, load_lfos_from_json};
+use easel_rs::uniforms::{load_uniforms_from_json, store_uniforms_in_json};
 use std::sync::mpsc::channel;
 
 use super::message::CanvasMessage;
-use super::{Canvas, PAINTING_TEXTURE_FORMAT, RENDER_TEXTURE_FORMAT, VS_MODULE_BYTES};
-use crate::postprocessing::PostProcess;
-use crate::recording::MOVIE_TEXTURE_FORMAT;
+use super::Canvas;
 use log::{error, info, warn};
 use notify::{DebouncedEvent, Watcher};
 
@@ -15,67 +15,18 @@ impl Canvas {
         match event {
             DebouncedEvent::Create(path_buf) | DebouncedEvent::Write(path_buf) => {
                 let file = path_buf.to_str().unwrap();
-                let fs_spirv_data = match crate::utils::load_shader(file) {
-                    Ok(data) => data,
-                    Err(e) => {
-                        error!("Error compiling shader: {}", e);
-                        self.transmitter
-                            .send(CanvasMessage::ShaderCompilationFailed(e.to_string()))
-                            .unwrap();
-                        return;
-                    }
-                };
-                let fs_module = self
-                    .device
-                    .create_shader_module(&wgpu::ShaderModuleDescriptor {
-                        label: Some("Vertex Shader"),
-                        source: wgpu::util::make_spirv(&fs_spirv_data),
-                        flags: wgpu::ShaderFlags::VALIDATION,
-                    });
-                let vs_module = self
-                    .device
-                    .create_shader_module(&wgpu::ShaderModuleDescriptor {
-                        label: Some("Vertex Shader"),
-                        source: wgpu::util::make_spirv(VS_MODULE_BYTES),
-                        flags: wgpu::ShaderFlags::VALIDATION,
-                    });
-
-                let layouts = [&self.bind_group_layouts[0], &self.bind_group_layouts[1]];
-                // let mut constants_for_pipeline = vec![];
-                // if let Some(constants) = self.push_constants.as_ref() {
-                //     let mut size = 0;
-                //     for a_constant in constants {
-                //         size += a_constant.size();
-                //     }
-                //     constants_for_pipeline.push(wgpu::PushConstantRange {
-                //         stages: wgpu::ShaderStage::FRAGMENT,
-                //         range: 0..(size as u32),
-                //     });
-                // }
-                let render_pipeline_layout =
-                    self.device
-                        .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                            label: Some("Canvas Pipeline Layout"),
-                            bind_group_layouts: &layouts,
-                            // push_constant_ranges: &constants_for_pipeline,
-                            push_constant_ranges: &[],
-                        });
-                let (render_pipeline, painting_pipeline, movie_pipeline) =
-                    crate::utils::create_pipelines(
-                        &self.device,
-                        &render_pipeline_layout,
-                        &vs_module,
-                        &fs_module,
-                        (
-                            RENDER_TEXTURE_FORMAT,
-                            PAINTING_TEXTURE_FORMAT,
-                            MOVIE_TEXTURE_FORMAT,
-                        ),
-                    );
-
-                self.render_pipeline = render_pipeline;
-                self.painting_pipeline = painting_pipeline;
-                self.movie_pipeline = movie_pipeline;
+                let fs_spirv_data =
+                    match easel_rs::utils::load_shader(file, &self.renderer.user_uniforms) {
+                        Ok(data) => data,
+                        Err(e) => {
+                            error!("Error compiling shader: {}", e);
+                            self.transmitter
+                                .send(CanvasMessage::ShaderCompilationFailed(e.to_string()))
+                                .unwrap();
+                            return;
+                        }
+                    };
+                self.renderer.update_shader(&fs_spirv_data);
 
                 self.transmitter
                     .send(CanvasMessage::ShaderCompilationSucceeded)
@@ -114,15 +65,7 @@ impl Canvas {
     }
 
     pub fn add_post_processing_shader(&mut self, shader_data: Vec<u8>) {
-        let postprocess = PostProcess::new(
-            &self.device,
-            shader_data,
-            self.user_uniforms_buffer.is_some(),
-        );
-        // We have a default included post-processing stage that is run in the painting pipeline
-        // for doing sRGB conversion. That must always run last.
-        self.postprocess_ops
-            .insert(self.postprocess_ops.len() - 1, postprocess);
+        self.renderer.add_post_processing_shader(shader_data);
     }
 
     /// Use to trigger automatic reload when shader is changed on disk.
@@ -152,18 +95,101 @@ impl Canvas {
         self.json_file_watcher_receiver = Some(rx);
     }
 
+    /// Load user uniforms, keyframes and LFOs from a uniforms file, replacing the current ones.
+    /// Logs an error and leaves the uniforms unchanged if the file can't be read,
+    /// or if its uniforms don't have the same types in the same order as the current ones.
+    pub fn load_uniforms_file(&mut self, file: &str) {
+        let json_data = match std::fs::read_to_string(file) {
+            Ok(text) => match json::parse(&text) {
+                Ok(data) => data,
+                Err(e) => {
+                    error!("Error parsing uniforms file {}: {}", file, e);
+                    return;
+                }
+            },
+            Err(e) => {
+                error!("Error reading uniforms file {}: {}", file, e);
+                return;
+            }
+        };
+        if let Err(e) = self
+            .renderer
+            .set_user_uniforms(load_uniforms_from_json(&json_data))
+        {
+            error!("Error reloading uniforms: {}", e);
+        }
+        self.set_keyframe_tracks(load_keyframes_from_json(&json_data));
+        self.set_lfos(load_lfos_from_json(&json_data));
+        // self.push_constants = Some(load_push_constants_from_json(&json_data));
+    }
+
+    /// Write the current values of the user uniforms, and the keyframes and LFOs, back into [Self::uniforms_file].
+    /// Everything else in the file is kept, including uniform metadata and keys Easel doesn't use.
+    /// Uniforms driven by keyframes or LFOs are saved with their value at the current time.
+    pub fn save_uniforms_file(&mut self) {
+        let file = match &self.uniforms_file {
+            Some(file) => file,
+            None => {
+                error!("No uniforms file to save to.");
+                return;
+            }
+        };
+        // Start from the file as it is now, so edits made to it since loading are kept too.
+        // Never overwrite a file we can't read or parse, as that would lose its metadata and other keys.
+        let mut json_data = match std::fs::read_to_string(file) {
+            Ok(text) => match json::parse(&text) {
+                Ok(data) => data,
+                Err(e) => {
+                    error!("Error parsing uniforms file {}, not saving: {}", file, e);
+                    return;
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => json::JsonValue::new_object(),
+            Err(e) => {
+                error!("Error reading uniforms file {}, not saving: {}", file, e);
+                return;
+            }
+        };
+        store_uniforms_in_json(&mut json_data, &self.renderer.user_uniforms);
+        if self.keyframe_tracks.is_empty() {
+            json_data.remove("keyframes");
+        } else {
+            json_data["keyframes"] = keyframes_to_json(&self.keyframe_tracks);
+        }
+        if self.lfos.is_empty() {
+            json_data.remove("lfos");
+        } else {
+            json_data["lfos"] = lfos_to_json(&self.lfos);
+        }
+        let text = json_data.pretty(2);
+        match std::fs::write(file, &text) {
+            Ok(_) => {
+                info!("Saved uniforms to {}", file);
+                self.saved_uniforms_text = Some(text);
+            }
+            Err(e) => error!("Error saving uniforms to {}: {}", file, e),
+        }
+    }
+
     /// Reload uniforms file from disk and update render pipelines.
     pub fn update_custom_uniforms_from_file(&mut self, event: DebouncedEvent) {
         let mut disable = false;
         match event {
             DebouncedEvent::Create(path_buf) | DebouncedEvent::Write(path_buf) => {
                 let file = path_buf.to_str().unwrap();
+                let text = match std::fs::read_to_string(file) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        error!("Error reading uniforms file {}: {}", file, e);
+                        return;
+                    }
+                };
+                // Saving from the Dashboard already applied these values.
+                if self.saved_uniforms_text.as_ref() == Some(&text) {
+                    return;
+                }
                 info!("Detected uniforms JSON file changed, reloading {}", file);
-                let text =
-                    std::fs::read_to_string(file).expect("Error reading uniforms from file.");
-                let json_data = json::parse(&text).expect("Error parsing JSON");
-                self.user_uniforms = load_uniforms_from_json(&json_data);
-                // self.push_constants = Some(load_push_constants_from_json(&json_data));
+                self.load_uniforms_file(file);
             }
             DebouncedEvent::Remove(path_buf) => {
                 info!(
@@ -1,5 +1,12 @@
-use crate::uniforms::UserUniform;
-use crate::vector::{IntVector2, UIntVector2, Vector2};
+use easel_rs::keyframes::KeyframeTrack;
+use easel_rs::lfo::Lfo;
+use easel_rs::painting::WriteFinished;
+use easel_rs::recipe::PaintingRecipe;
+use easel_rs::recording::FrameData;
+use easel_rs::uniforms::UserUniform;
+use easel_rs::vector::{IntVector2, UIntVector2, Vector2};
+use std::collections::HashMap;
+use std::sync::mpsc::Receiver;
 
 /// Message Enums used by [crate::canvas::Canvas] to send messages to interested parties.
 pub enum CanvasMessage {
@@ -15,22 +22,44 @@ pub enum CanvasMessage {
     WindowResized(IntVector2),
     /// A painting render operation has been dispatched.
     /// The buffer will contain the painting data once rendering finishes.
-    /// The IntVector2 is the resolution of the painting.
+    /// The PaintingRecipe describes how the painting was rendered, including its resolution and format.
     /// The Instant is the time point at which this render operation started.
-    PaintingStarted(wgpu::Buffer, UIntVector2, std::time::Instant),
+    PaintingStarted(wgpu::Buffer, Box<PaintingRecipe>, std::time::Instant),
+    /// A tiled painting render operation has started, streaming into a writer created by the canvas.
+    /// The Receiver reports when the writer has finished.
+    /// The Instant is the time point at which this render operation started.
+    TiledPaintingStarted(Receiver<WriteFinished>, std::time::Instant),
     /// A movie frame render operation has been dispatched.
     /// The buffer will contain the frame data once rendering finishes.
     /// The IntVector2 is the resolution of the frame.
     /// The Instant is the time point at which this render operation started.
     MovieFrameStarted(wgpu::Buffer, UIntVector2, std::time::Instant),
+    /// A frame of an offline recording has been rendered and read back.
+    /// Frames are sent in order and must be added to the recorder in the same order.
+    MovieFrameRendered(FrameData),
+    /// The offline recording has rendered all of its frames.
+    OfflineRecordingFinished,
     /// Signifies shader reloaded from disk, recompiled, and render pipeline has been updated.
     ShaderCompilationSucceeded,
     /// Error reloading shader, contains error message.
     ShaderCompilationFailed(String),
+    /// Contains the current time in seconds and frame number in the uniforms.
+    PlaybackTimeUpdated(f32, u32),
     /// Indication pause play state changed from canvas window.
     PausePlayChanged,
     /// Used by Canvas to tell Dashboard how to build the editor GUI for a given custom uniform.
     UniformForGUI(UserUniform),
+    /// Every keyframe track animating user uniforms, by uniform name. Sent when the tracks are loaded or reloaded from file.
+    KeyframesForGUI(HashMap<String, KeyframeTrack>),
+    /// Every LFO driving user uniforms, by uniform name. Sent when the LFOs are loaded or reloaded from file.
+    LfosForGUI(HashMap<String, Lfo>),
     /// Change the resolution of the painting in the GUI.
     UpdatePaintingResolutioninGUI(IntVector2),
+    /// Undo the last uniform edit, requested with Ctrl+Z in the canvas window.
+    UndoRequested,
+    /// Redo the last undone uniform edit, requested with Ctrl+Shift+Z in the canvas window.
+    RedoRequested,
+    /// A thumbnail requested by the Dashboard has been rendered.
+    /// Contains the ID of the gallery entry it is for, its resolution, and its pixels as tightly packed 8-bit sRGB RGBA.
+    ThumbnailRendered(u64, UIntVector2, Vec<u8>),
 }
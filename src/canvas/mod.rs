@@ -1,21 +1,18 @@
 // use crate::drawable::Drawable;
-use crate::texture::{default_color_sampler, AssetTexture};
-use crate::uniforms::{Uniforms, UserUniform};
-use crate::vector::{IntVector2, IntVector4, UIntVector2, Vector2, Vector4};
-use crate::{dashboard::DashboardMessage, recording::MOVIE_TEXTURE_FORMAT};
+use crate::dashboard::DashboardMessage;
 use chrono::Datelike;
-use std::collections::HashSet;
+use easel_rs::keyframes::{apply_keyframes, KeyframeTrack};
+use easel_rs::lfo::{apply_lfos, Lfo};
+use easel_rs::painting::PaintingFormat;
+use easel_rs::recipe::ShaderSources;
+use easel_rs::recording::FrameFormat;
+use easel_rs::renderer::{request_device, Renderer, VS_MODULE_BYTES};
+use easel_rs::uniforms::{replace_user_uniform, UserUniform, UserUniformType, LOOP_PHASE_UNIFORM};
+use easel_rs::vector::{IntVector2, IntVector4, UIntVector2, Vector2};
+use log::error;
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, Sender};
 use std::vec::Vec;
-use std::{
-    num::NonZeroU64,
-    sync::mpsc::{Receiver, Sender},
-};
-use stopwatch::Stopwatch;
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
-use wgpu::{
-    BindGroupEntry, BindGroupLayoutEntry, BindingResource, BufferBinding, PowerPreference,
-    RequestAdapterOptions,
-};
 use winit::{event::*, window::Window};
 
 mod message;
@@ -25,23 +22,26 @@ pub use self::rendering::*;
 mod file_loading;
 pub use self::file_loading::*;
 
-use crate::postprocessing::PostProcess;
 use notify::{DebouncedEvent, RecommendedWatcher};
 
-/// Pre-compile vertex shader that renders a full-screen quad.
-pub static VS_MODULE_BYTES: &[u8] = include_bytes!("../../shaders/vert.spv");
-/// The [wgpu::TextureFormat] used when rendering to screen.
-/// We render to linear color as so that post-process ops are correctly applied in linear space.
-/// A final render pass is done before presenting to screen to convert to sRGB.
-pub static RENDER_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
-/// The [wgpu::TextureFormat] used when rendering off-screen painting to write to disk.
-pub static PAINTING_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
-/// Built-in shader used as a post-processing effect to apply gamma sRGB conversion for painting.
-/// This is needed as the [PAINTING_TEXTURE_FORMAT] does not perform automatic sRGB conversion for us.
-static POST_PROCESS_SRGB_SHADER_BYTES: &[u8] =
-    include_bytes!("../../shaders/post-process-srgb.spv");
+/// A movie being recorded offline, where time advances by a fixed step every frame instead of with the wall clock.
+struct OfflineRecording {
+    /// Resolution of the movie frames.
+    resolution: UIntVector2,
+    /// Format the recorder expects frames in.
+    frame_format: FrameFormat,
+    /// Seconds of shader time between frames, one over the framerate.
+    time_step: f32,
+    /// Frames left to render, or None to record until stopped.
+    frames_remaining: Option<u32>,
+    /// Frames rendered so far.
+    frames_rendered: u32,
+    /// Length of a seamless loop in frames. Loops start at t = 0 instead of the current time.
+    loop_frames: Option<u32>,
+}
 
 /// Central class for the painting on the Easel.
+/// Presents the output of its [Renderer] to a window.
 /// Sends & receives messages to/from Dashboard.
 /// Provides file watching capabilities for shader and/or custom uniforms.
 pub struct Canvas {
@@ -53,56 +53,31 @@ pub struct Canvas {
     pub surface: wgpu::Surface,
     /// Handle to WebGPU Adapter
     pub adapter: wgpu::Adapter,
-    /// Handle to WebGPU Device. Attempts to use highest performance GPU on system.
-    pub device: wgpu::Device,
-    /// Handle to command dispatch queue on device.
-    pub queue: wgpu::Queue,
+    /// Renders the shader, paintings and movie frames. Owns the device and queue.
+    pub renderer: Renderer,
     /// Descriptor is kept around for window resizing events.
     sc_desc: wgpu::SwapChainDescriptor,
     /// Handle to swap chain for on-screen rendering.
     swap_chain: wgpu::SwapChain,
-    /// Render pipeline used for on-screen rendering. May include post-processing effects, if provided.
-    render_pipeline: wgpu::RenderPipeline,
-    /// Render pipeline used for off-screen rendering. Will always include sRGB conversion post-processing effect.
-    /// May also include other post-processing effects, if provided.
-    painting_pipeline: wgpu::RenderPipeline,
-    /// Render pipeline use for off-screen rendering of movie frames.
-    /// May also include other post-processing effects, if provided.
-    movie_pipeline: wgpu::RenderPipeline,
-    /// The pipeline use to render output of [Self::render_pipeline] to screen.
+    /// The pipeline use to render output of the [Renderer] to screen.
     swap_chain_pipeline: wgpu::RenderPipeline,
-    /// Color with which to [wgpu::LoadOp::Clear] attachments to render passes.
-    clear_color: wgpu::Color,
     /// Resolution of render canvas.
     /// **Note:** Distinct from the painting render resolution.
     size: winit::dpi::PhysicalSize<u32>,
-    /// Uniforms provided by Canvas to all shaders.
-    uniforms: Uniforms,
-    /// Handle to device buffer where [Self::uniforms] are copied over.
-    uniforms_device_buffer: wgpu::Buffer,
-    /// Optional device buffer of user-provided uniforms.
-    user_uniforms_buffer: Option<wgpu::Buffer>,
-    /// Optional size of device buffer holding user-provided uniforms.
-    user_uniforms_buffer_size: Option<usize>,
-    /// Optional list of user-provided uniforms from JSON file.
-    user_uniforms: HashSet<UserUniform>,
     /// Optional list of user-provided push constants from JSON file.
     // push_constants: Option<Vec<Box<dyn PushConstant>>>,
-    bind_groups: [wgpu::BindGroup; 2],
-    bind_group_layouts: [wgpu::BindGroupLayout; 2],
-
-    /// List of texture handles and their destination binding locations in the shader.
-    #[allow(dead_code)]
-    textures: Vec<AssetTexture>,
-    /// List of post-processing shaders.
-    postprocess_ops: Vec<PostProcess>,
-    /// Shader to apply sRGB Gamma for paintings.
-    srgb_postprocess: PostProcess,
-    /// Stopwatch used for calculating time elapsed and other uniforms.
-    stop_watch: Stopwatch,
-    /// Pause/Play state. Also pauses [Self::stop_watch], which sets time data in [Self::uniforms].
+    /// Pause/Play state. Time in the uniforms only advances while playing.
     paused: bool,
-    /// Time of last update. Use to calculate time deltas in [Self::uniforms].
+    /// Multiplier applied to the wall clock time between updates before adding it to the uniforms.
+    /// Negative values play time backwards.
+    playback_speed: f32,
+    /// Render the next frame even if paused, such as after seeking to a new time.
+    /// Time does not advance in the update that follows a seek.
+    redraw_requested: bool,
+    /// Whether the last update advanced time. Offline recordings only capture frames that did,
+    /// so redraws requested while recording, such as by mouse moves, don't record the same frame twice.
+    time_advanced: bool,
+    /// Time of last update. Use to calculate time deltas in the uniforms.
     last_update: std::time::Instant,
 
     /// Used to send messages to Dashboard.
@@ -122,6 +97,24 @@ pub struct Canvas {
     json_file_watcher_receiver: Option<Receiver<DebouncedEvent>>,
     /// Painting Resolution
     painting_resolution: UIntVector2,
+    /// Format paintings are written in. Linear formats are rendered without the sRGB conversion.
+    painting_format: PaintingFormat,
+    /// The files the shader was loaded from, recorded in the recipe of every painting.
+    pub shader_sources: ShaderSources,
+    /// Offline movie recording in progress, if any.
+    offline_recording: Option<OfflineRecording>,
+    /// Keyframe tracks animating user uniforms, by uniform name. Evaluated against the time in the uniforms every update.
+    keyframe_tracks: HashMap<String, KeyframeTrack>,
+    /// Low-frequency oscillators driving user uniforms, by uniform name. Enabled LFOs override any keyframes.
+    lfos: HashMap<String, Lfo>,
+    /// The JSON file the user uniforms were loaded from, if any. Saved to and reverted from via the Dashboard.
+    pub uniforms_file: Option<String>,
+    /// Contents of the uniforms file as last saved by us, so the file watcher can ignore our own write.
+    saved_uniforms_text: Option<String>,
+    /// Modifier keys currently held in the canvas window.
+    modifiers: ModifiersState,
+    /// Thumbnails requested by the Dashboard, by gallery entry ID, rendered once the uniforms are next written.
+    thumbnail_requests: Vec<(u64, UIntVector2)>,
 }
 
 impl Canvas {
@@ -137,7 +130,7 @@ impl Canvas {
         window: Window,
         fs_spirv_data: Vec<u8>,
         images: Option<Vec<image::DynamicImage>>,
-        user_uniforms: Option<HashSet<UserUniform>>,
+        user_uniforms: Option<Vec<UserUniform>>,
         // push_constants: Option<Vec<Box<dyn PushConstant>>>,
         transmitter: Sender<CanvasMessage>,
         receiver: Receiver<DashboardMessage>,
@@ -150,80 +143,7 @@ impl Canvas {
             surface = instance.create_surface(&window);
         }
 
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                compatible_surface: Some(&surface),
-                power_preference: PowerPreference::HighPerformance,
-            })
-            .await
-            .unwrap();
-        // From: https://docs.rs/wgpu/0.6.2/wgpu/struct.Limits.html#structfield.max_push_constant_size
-        let max_push_constant_size = match wgpu::BackendBit::PRIMARY {
-            wgpu::BackendBit::VULKAN => 256,
-            wgpu::BackendBit::DX12 => 256,
-            wgpu::BackendBit::METAL => 4096,
-            _ => 128,
-        };
-        let limits = wgpu::Limits {
-            max_push_constant_size,
-            ..Default::default()
-        };
-        let device_desc = wgpu::DeviceDescriptor {
-            label: None,
-            features: adapter.features(),
-            limits,
-        };
-
-        let (device, queue) = adapter.request_device(&device_desc, None).await.unwrap();
-
-        //------------------------------------------------------------------------------------------
-        // Create uniforms, device buffer, and bindings.
-        let mut uniforms = Uniforms::new();
-        uniforms.resolution = Vector4::new(size.width as f32, size.height as f32, 0.0, 0.0);
-        uniforms.num_textures = match &images {
-            Some(vec) => vec.len() as u32,
-            None => 0,
-        };
-        let descriptor = BufferInitDescriptor {
-            label: Some("Uniforms Buffer"),
-            contents: bytemuck::bytes_of(&uniforms),
-            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-        };
-        let u_buffer = device.create_buffer_init(&descriptor);
-
-        //------------------------------------------------------------------------------------------
-        // Bind custom uniforms, if provided
-        let mut custom_uniforms_buffer = None;
-        let mut custom_uniforms_buffer_size = 0;
-        if let Some(dem_uniforms) = &user_uniforms {
-            let mut total_size = 0;
-            for a_uniform in dem_uniforms {
-                total_size += a_uniform.bytes.len();
-            }
-
-            custom_uniforms_buffer_size = total_size;
-            let mut bytes = Vec::with_capacity(total_size);
-            for a_uniform in dem_uniforms {
-                bytes.extend_from_slice(&a_uniform.bytes);
-            }
-
-            let desc = BufferInitDescriptor {
-                label: Some("Custom Uniforms Buffer"),
-                contents: &bytes,
-                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-            };
-
-            custom_uniforms_buffer = Some(device.create_buffer_init(&desc));
-        }
-
-        //------------------------------------------------------------------------------------------
-        // Load textures.
-        let mut asset_textures = Vec::<AssetTexture>::new();
-        if let Some(vec) = images {
-            for an_image in &vec {
-                asset_textures.push(AssetTexture::new_with_image(an_image, &device, &queue));
-            }
-        }
+        let (adapter, device, queue) = request_device(&instance, Some(&surface)).await;
 
         //------------------------------------------------------------------------------------------
         // Setup swap chain
@@ -236,189 +156,23 @@ impl Canvas {
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
 
-        //------------------------------------------------------------------------------------------
-        // Load shaders.
+        // Swap chain pipeline will never change and is separate from others.
         let vs_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
             label: Some("Vertex Shader"),
             source: wgpu::util::make_spirv(VS_MODULE_BYTES),
             flags: wgpu::ShaderFlags::VALIDATION,
         });
-        let fs_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
-            label: Some("Painting Fragment Shader"),
-            source: wgpu::util::make_spirv(&fs_spirv_data),
-            flags: wgpu::ShaderFlags::VALIDATION,
-        });
-
-        //------------------------------------------------------------------------------------------
-        // Create the bind group layout and entries.
-        // Uniforms and our generated textures are set 0
-        let primary_bind_group_layout: wgpu::BindGroupLayout;
-        {
-            let mut bind_group_layout_entries = Vec::<wgpu::BindGroupLayoutEntry>::new();
-            // Uniforms are first.
-            bind_group_layout_entries.push(wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStage::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            });
-            if let Some(_) = custom_uniforms_buffer {
-                bind_group_layout_entries.push(wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStage::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                });
-            }
-            primary_bind_group_layout =
-                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: None,
-                    entries: &bind_group_layout_entries,
-                });
-        }
-
-        // In set 1, bind provided textures.
-        let secondary_bind_group_layout: wgpu::BindGroupLayout;
-        {
-            let mut bind_group_layout_entries = Vec::<wgpu::BindGroupLayoutEntry>::new();
-            // For now, we only have 1 sampler per set
-            bind_group_layout_entries.push(BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStage::FRAGMENT,
-                ty: wgpu::BindingType::Sampler {
-                    filtering: true,
-                    comparison: false,
-                },
-                count: None,
-            });
-            for i in 1..=asset_textures.len() {
-                bind_group_layout_entries.push(BindGroupLayoutEntry {
-                    binding: i as u32,
-                    visibility: wgpu::ShaderStage::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                });
-            }
-            // Create the Bind Group Layout.
-            secondary_bind_group_layout =
-                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: None,
-                    entries: &bind_group_layout_entries,
-                });
-        }
-
-        //------------------------------------------------------------------------------------------
-        // Create Bind Groups from layouts.
-        let primary_bind_group: wgpu::BindGroup;
-        {
-            let mut primary_bind_group_entries: Vec<BindGroupEntry> = Vec::new();
-            // Provided Uniforms first.
-            primary_bind_group_entries.push(wgpu::BindGroupEntry {
-                binding: 0,
-                resource: BindingResource::Buffer(BufferBinding {
-                    buffer: &u_buffer,
-                    offset: 0,
-                    size: Some(NonZeroU64::new(std::mem::size_of_val(&uniforms) as u64).unwrap()),
-                }),
-            });
-            // Custom Uniforms next, if enabled.
-            if let Some(cu_buffer) = &custom_uniforms_buffer {
-                primary_bind_group_entries.push(wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Buffer(BufferBinding {
-                        buffer: &cu_buffer,
-                        offset: 0,
-                        size: Some(NonZeroU64::new(custom_uniforms_buffer_size as u64).unwrap()),
-                    }),
-                });
-            }
-
-            // Finally create the bind group.
-            primary_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Primary Bind Group"),
-                layout: &primary_bind_group_layout,
-                entries: &primary_bind_group_entries,
-            });
-        }
-
-        let secondary_bind_group: wgpu::BindGroup;
-        {
-            let mut secondary_bind_group_entries: Vec<BindGroupEntry> = Vec::new();
-            let default_sampler = default_color_sampler(&device);
-            secondary_bind_group_entries.push(BindGroupEntry {
-                binding: 0,
-                resource: BindingResource::Sampler(&default_sampler),
-            });
-            // Create texture views.
-            let mut tex_views = Vec::<wgpu::TextureView>::new();
-            for tex in &asset_textures {
-                let texture_view = tex.get_view(0);
-                tex_views.push(texture_view);
-            }
-            // Add texture view bindings.
-            for tex_bind_idx in 1..=tex_views.len() {
-                secondary_bind_group_entries.push(BindGroupEntry {
-                    binding: tex_bind_idx as u32,
-                    resource: BindingResource::TextureView(&tex_views[tex_bind_idx - 1]),
-                });
-            }
-            secondary_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Secondary Bind Group"),
-                layout: &secondary_bind_group_layout,
-                entries: &secondary_bind_group_entries,
-            });
-        }
+        let swap_chain_pipeline =
+            easel_rs::utils::create_swap_chain_pipeline(&device, &vs_module, sc_desc.format);
 
-        //------------------------------------------------------------------------------------------
-        // Create render pipeline.
-        // let mut constants_for_pipeline = vec![];
-        // if let Some(constants) = push_constants.as_ref() {
-        //     let mut size = 0;
-        //     for a_constant in constants {
-        //         size += a_constant.size();
-        //     }
-        //     constants_for_pipeline.push(wgpu::PushConstantRange {
-        //         stages: wgpu::ShaderStage::FRAGMENT,
-        //         range: 0..(size as u32),
-        //     });
-        // }
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Canvas Pipeline Layout"),
-                bind_group_layouts: &[&primary_bind_group_layout, &secondary_bind_group_layout],
-                // push_constant_ranges: &constants_for_pipeline,
-                push_constant_ranges: &[],
-            });
-        let (render_pipeline, painting_pipeline, movie_pipeline) = crate::utils::create_pipelines(
-            &device,
-            &render_pipeline_layout,
-            &vs_module,
-            &fs_module,
-            (
-                RENDER_TEXTURE_FORMAT,
-                PAINTING_TEXTURE_FORMAT,
-                MOVIE_TEXTURE_FORMAT,
-            ),
+        let renderer = Renderer::with_device(
+            device,
+            queue,
+            &fs_spirv_data,
+            images,
+            user_uniforms,
+            UIntVector2::new(size.width, size.height),
         );
-        // Swap chain pipeline will never change and is separate from others.
-        let swap_chain_pipeline =
-            crate::utils::create_swap_chain_pipeline(&device, &vs_module, sc_desc.format);
-        let mut custom_size = None;
-        if custom_uniforms_buffer_size > 0 {
-            custom_size = Some(custom_uniforms_buffer_size);
-        }
 
         // Inform dashboard of our window size so that it has a sensible default for painting res.
         transmitter
@@ -427,46 +181,20 @@ impl Canvas {
             ))
             .unwrap();
         Self {
-            srgb_postprocess: PostProcess::new(
-                &device,
-                Vec::from(POST_PROCESS_SRGB_SHADER_BYTES),
-                custom_uniforms_buffer.is_some(),
-            ),
             window,
             instance,
             surface,
             adapter,
-            device,
-            queue,
+            renderer,
             sc_desc,
             swap_chain,
-            render_pipeline,
-            painting_pipeline,
-            movie_pipeline,
             swap_chain_pipeline,
-            clear_color: wgpu::Color {
-                r: 0.1,
-                g: 0.2,
-                b: 0.3,
-                a: 1.0,
-            },
             size,
-            uniforms,
-            user_uniforms_buffer: custom_uniforms_buffer,
-            user_uniforms_buffer_size: custom_size,
-            user_uniforms: match user_uniforms {
-                Some(uni) => uni,
-                None => HashSet::new(),
-            },
             // push_constants,
-            uniforms_device_buffer: u_buffer,
-            bind_groups: [primary_bind_group, secondary_bind_group],
-            bind_group_layouts: [primary_bind_group_layout, secondary_bind_group_layout],
-            textures: asset_textures,
-            postprocess_ops: vec![],
-
-            stop_watch: Stopwatch::start_new(),
             paused: false,
+            playback_speed: 1.0,
+            redraw_requested: false,
+            time_advanced: false,
             last_update: std::time::Instant::now(),
             transmitter,
             receiver,
@@ -476,6 +204,15 @@ impl Canvas {
             json_file_watcher: None,
             json_file_watcher_receiver: None,
             painting_resolution: UIntVector2::zero(),
+            painting_format: PaintingFormat::Tiff16,
+            shader_sources: ShaderSources::default(),
+            offline_recording: None,
+            keyframe_tracks: HashMap::new(),
+            lfos: HashMap::new(),
+            uniforms_file: None,
+            saved_uniforms_text: None,
+            modifiers: ModifiersState::empty(),
+            thumbnail_requests: Vec::new(),
         }
     }
 
@@ -484,9 +221,12 @@ impl Canvas {
         self.size = new_size;
         self.sc_desc.width = new_size.width;
         self.sc_desc.height = new_size.height;
-        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
-        self.uniforms.resolution.x = new_size.width as f32;
-        self.uniforms.resolution.y = new_size.height as f32;
+        self.swap_chain = self
+            .renderer
+            .device
+            .create_swap_chain(&self.surface, &self.sc_desc);
+        self.renderer.uniforms.resolution.x = new_size.width as f32;
+        self.renderer.uniforms.resolution.y = new_size.height as f32;
         self.transmitter
             .send(CanvasMessage::WindowResized(IntVector2::new(
                 new_size.width as i32,
@@ -498,22 +238,22 @@ impl Canvas {
     /// Used to parse messages received from Dashboard and act accordingly.
     fn dashboard_signal_received(&mut self, message: DashboardMessage) {
         match message {
-            DashboardMessage::PausePlayChanged => {
-                self.paused = !self.paused;
-                if self.paused {
-                    self.stop_watch.stop();
-                } else {
-                    self.stop_watch.start();
-                }
-            }
-            DashboardMessage::Pause => {
-                self.paused = true;
-                self.stop_watch.stop();
+            DashboardMessage::PausePlayChanged => self.paused = !self.paused,
+            DashboardMessage::Pause => self.paused = true,
+            DashboardMessage::Play => self.paused = false,
+            DashboardMessage::TimeSeekRequested(time, frame_num) => {
+                self.renderer.uniforms.time = time;
+                self.renderer.uniforms.frame_num = frame_num;
+                self.redraw_requested = true;
             }
-            DashboardMessage::Play => {
-                self.paused = false;
-                self.stop_watch.start();
+            DashboardMessage::FrameStepRequested(frames, seconds_per_frame) => {
+                let uniforms = &mut self.renderer.uniforms;
+                uniforms.time += frames as f32 * seconds_per_frame;
+                uniforms.time_delta = frames as f32 * seconds_per_frame;
+                uniforms.frame_num = (uniforms.frame_num as i64 + frames as i64).max(0) as u32;
+                self.redraw_requested = true;
             }
+            DashboardMessage::PlaybackSpeedUpdated(speed) => self.playback_speed = speed,
             DashboardMessage::TitlebarStatusChanged => {
                 self.show_titlebar = !self.show_titlebar;
                 self.window.set_decorations(self.show_titlebar);
@@ -521,15 +261,63 @@ impl Canvas {
             DashboardMessage::PaintingRenderRequested(resolution) => {
                 self.create_painting(resolution)
             }
+            DashboardMessage::TiledPaintingRenderRequested(
+                resolution,
+                tile_size,
+                filename,
+                open_externally,
+            ) => self.create_tiled_painting(resolution, tile_size, filename, open_externally),
             DashboardMessage::UniformUpdatedViaGUI(modified_uniform) => {
-                self.user_uniforms.insert(modified_uniform);
+                replace_user_uniform(&mut self.renderer.user_uniforms, modified_uniform);
             }
-            DashboardMessage::MovieRenderRequested(resolution) => {
-                self.create_movie_frame(resolution);
+            DashboardMessage::KeyframeTrackUpdatedViaGUI(name, track) => {
+                match track.keyframes.is_empty() {
+                    true => self.keyframe_tracks.remove(&name),
+                    false => self.keyframe_tracks.insert(name, track),
+                };
             }
+            DashboardMessage::LfoUpdatedViaGUI(name, lfo) => {
+                self.lfos.insert(name, lfo);
+            }
+            DashboardMessage::ThumbnailRequested(id, resolution) => {
+                self.thumbnail_requests.push((id, resolution));
+            }
+            DashboardMessage::MouseMovedViaGUI(position) => {
+                self.move_mouse(position);
+                self.redraw_requested = true;
+            }
+            DashboardMessage::UniformsSaveRequested => self.save_uniforms_file(),
+            DashboardMessage::UniformsRevertRequested => match self.uniforms_file.clone() {
+                Some(file) => self.load_uniforms_file(&file),
+                None => error!("No uniforms file to revert to."),
+            },
+            DashboardMessage::MovieRenderRequested(resolution, frame_format) => {
+                self.create_movie_frame(resolution, frame_format);
+            }
+            DashboardMessage::OfflineRecordingStarted(
+                resolution,
+                frame_format,
+                framerate,
+                frame_count,
+                seamless_loop,
+            ) => {
+                self.offline_recording = Some(OfflineRecording {
+                    resolution,
+                    frame_format,
+                    time_step: 1.0 / framerate as f32,
+                    frames_remaining: frame_count,
+                    frames_rendered: 0,
+                    loop_frames: match seamless_loop {
+                        true => frame_count,
+                        false => None,
+                    },
+                });
+            }
+            DashboardMessage::OfflineRecordingStopped => self.offline_recording = None,
             DashboardMessage::PaintingResolutionUpdated(resolution) => {
                 self.painting_resolution = resolution
             }
+            DashboardMessage::PaintingFormatUpdated(format) => self.painting_format = format,
         }
     }
 
@@ -579,53 +367,88 @@ impl Canvas {
                 self.update_custom_uniforms_from_file(an_event);
             }
         }
-        // Referesh user uniforms buffer
-        if let Some(buffer) = &self.user_uniforms_buffer {
-            let mut total_size = 0;
-            for a_uniform in &self.user_uniforms {
-                total_size += a_uniform.bytes.len();
-            }
-            let mut bytes = Vec::with_capacity(total_size);
-            for a_uniform in &self.user_uniforms {
-                bytes.extend_from_slice(&a_uniform.bytes);
+        let now = std::time::Instant::now();
+        let seconds_elapsed = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+        // Only actually update uniforms if not paused or just seeked, but we always update buffer.
+        self.time_advanced = !self.paused && !self.redraw_requested;
+        if self.time_advanced {
+            let uniforms = &mut self.renderer.uniforms;
+            uniforms.frame_num += 1;
+            let mut loop_phase = None;
+            match &self.offline_recording {
+                // Offline recordings step time exactly one frame at a time, however long frames take to render.
+                Some(recording) => {
+                    match recording.loop_frames {
+                        // Seamless loops sample [0, period) exactly, so the frame after the last is t = 0 again.
+                        Some(loop_frames) => {
+                            let frame = recording.frames_rendered as f32;
+                            uniforms.time = frame * recording.time_step;
+                            loop_phase = Some(frame / loop_frames as f32);
+                        }
+                        None => uniforms.time += recording.time_step,
+                    }
+                    uniforms.time_delta = recording.time_step;
+                }
+                None => {
+                    uniforms.time_delta = seconds_elapsed * self.playback_speed;
+                    uniforms.time += uniforms.time_delta;
+                }
             }
-            self.queue.write_buffer(&buffer, 0, &bytes);
-        }
-
-        // Only actually update uniforms if not paused, but we always update buffer.
-        if !self.paused {
-            self.uniforms.frame_num += 1;
-            self.uniforms.time = self.stop_watch.elapsed().as_secs_f32();
-            let now = std::time::Instant::now();
-            let delta_duration = now.duration_since(self.last_update);
-            self.uniforms.time_delta = delta_duration.as_secs_f32();
             let today = chrono::Local::now();
-            self.uniforms.date =
+            uniforms.date =
                 IntVector4::new(today.year(), today.month() as i32, today.day() as i32, 0);
-            self.last_update = now;
-        }
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Update Uniforms Encoder"),
-            });
-        // Copy uniforms from CPU to staging buffer, then copy from staging buffer to main buf.
-        let descriptor = BufferInitDescriptor {
-            label: Some("Uniforms Buffer"),
-            contents: bytemuck::bytes_of(&self.uniforms),
-            usage: wgpu::BufferUsage::COPY_SRC,
-        };
-        let staging_buffer = self.device.create_buffer_init(&descriptor);
 
-        encoder.copy_buffer_to_buffer(
-            &staging_buffer,
-            0,
-            &self.uniforms_device_buffer,
-            0,
-            std::mem::size_of::<Uniforms>() as u64,
+            if let Some(phase) = loop_phase {
+                let phase_uniform = self
+                    .renderer
+                    .user_uniforms
+                    .iter_mut()
+                    .find(|u| u.name == LOOP_PHASE_UNIFORM);
+                if let Some(uniform) = phase_uniform {
+                    if let UserUniformType::Float32 = uniform.inherent_type {
+                        uniform.set_from_f64(phase as f64);
+                    }
+                }
+            }
+        }
+        // Keyframed and modulated uniforms follow the time even while paused, so seeking shows their value at the new time.
+        apply_keyframes(
+            &self.keyframe_tracks,
+            &mut self.renderer.user_uniforms,
+            self.renderer.uniforms.time,
         );
-        let command_buffer = encoder.finish();
-        self.queue.submit(Some(command_buffer));
+        apply_lfos(
+            &self.lfos,
+            &mut self.renderer.user_uniforms,
+            self.renderer.uniforms.time,
+        );
+        self.renderer.write_uniforms();
+        // Rendered now, so thumbnails show the uniforms sent with their request.
+        for (id, resolution) in std::mem::take(&mut self.thumbnail_requests) {
+            let pixels = self.renderer.render_thumbnail(resolution);
+            self.transmitter
+                .send(CanvasMessage::ThumbnailRendered(id, resolution, pixels))
+                .unwrap();
+        }
+    }
+
+    /// Replace the keyframe tracks animating user uniforms, and show them in the Dashboard.
+    /// * `tracks` - Keyframe tracks by uniform name, see [easel_rs::keyframes::load_keyframes_from_json()].
+    pub fn set_keyframe_tracks(&mut self, tracks: HashMap<String, KeyframeTrack>) {
+        self.transmitter
+            .send(CanvasMessage::KeyframesForGUI(tracks.clone()))
+            .unwrap();
+        self.keyframe_tracks = tracks;
+    }
+
+    /// Replace the LFOs driving user uniforms, and show them in the Dashboard.
+    /// * `lfos` - LFOs by uniform name, see [easel_rs::lfo::load_lfos_from_json()].
+    pub fn set_lfos(&mut self, lfos: HashMap<String, Lfo>) {
+        self.transmitter
+            .send(CanvasMessage::LfosForGUI(lfos.clone()))
+            .unwrap();
+        self.lfos = lfos;
     }
 
     /// Time to exit, cleanup resources.
@@ -644,11 +467,6 @@ impl Canvas {
                 ..
             } => {
                 self.paused = !self.paused;
-                if self.paused {
-                    self.stop_watch.stop();
-                } else {
-                    self.stop_watch.start();
-                }
                 self.transmitter
                     .send(CanvasMessage::PausePlayChanged)
                     .unwrap();
@@ -658,7 +476,19 @@ impl Canvas {
                 virtual_keycode: Some(VirtualKeyCode::P),
                 ..
             } => {
-                self.create_painting(self.painting_resolution.clone());
+                self.create_painting(self.painting_resolution);
+            }
+            KeyboardInput {
+                state: ElementState::Pressed,
+                virtual_keycode: Some(VirtualKeyCode::Z),
+                ..
+            } if self.modifiers.ctrl() || self.modifiers.logo() => {
+                // The uniform edit history lives in the Dashboard.
+                let message = match self.modifiers.shift() {
+                    true => CanvasMessage::RedoRequested,
+                    false => CanvasMessage::UndoRequested,
+                };
+                self.transmitter.send(message).unwrap();
             }
             KeyboardInput {
                 state: ElementState::Pressed,
@@ -669,36 +499,40 @@ impl Canvas {
         }
     }
 
+    /// Move the mouse position in the uniforms, keeping the last position, and tell the Dashboard.
+    /// * `position` - New position in pixels.
+    fn move_mouse(&mut self, position: Vector2) {
+        let mouse_position = &mut self.renderer.uniforms.mouse_position;
+        mouse_position.z = mouse_position.x;
+        mouse_position.w = mouse_position.y;
+        mouse_position.x = position.x;
+        mouse_position.y = position.y;
+        self.transmitter
+            .send(CanvasMessage::MouseMoved(position))
+            .unwrap();
+    }
+
     /// Expected to be called from main thread to handle IO events.
     /// This fn assumes the incoming events are from the Canvas' window.
     pub fn input(&mut self, incoming_event: winit::event::WindowEvent<'_>) {
         match incoming_event {
             WindowEvent::KeyboardInput { input, .. } => self.handle_keyoard_input(&input),
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers,
             WindowEvent::CursorMoved { position, .. } => {
-                self.uniforms.mouse_position.z = self.uniforms.mouse_position.x;
-                self.uniforms.mouse_position.w = self.uniforms.mouse_position.y;
-                self.uniforms.mouse_position.x = position.x as f32;
-                self.uniforms.mouse_position.y = position.y as f32;
-                // Send message.
-                self.transmitter
-                    .send(CanvasMessage::MouseMoved(Vector2::new(
-                        self.uniforms.mouse_position.x,
-                        self.uniforms.mouse_position.y,
-                    )))
-                    .unwrap();
+                self.move_mouse(Vector2::new(position.x as f32, position.y as f32))
             }
             WindowEvent::MouseInput { button, state, .. } => match button {
                 MouseButton::Left => {
-                    self.uniforms.mouse_button.x = (state == ElementState::Pressed) as i32
+                    self.renderer.uniforms.mouse_button.x = (state == ElementState::Pressed) as i32
                 }
                 MouseButton::Right => {
-                    self.uniforms.mouse_button.y = (state == ElementState::Pressed) as i32
+                    self.renderer.uniforms.mouse_button.y = (state == ElementState::Pressed) as i32
                 }
                 MouseButton::Middle => {
-                    self.uniforms.mouse_button.z = (state == ElementState::Pressed) as i32
+                    self.renderer.uniforms.mouse_button.z = (state == ElementState::Pressed) as i32
                 }
                 MouseButton::Other(_) => {
-                    self.uniforms.mouse_button.w = (state == ElementState::Pressed) as i32
+                    self.renderer.uniforms.mouse_button.w = (state == ElementState::Pressed) as i32
                 }
             },
             WindowEvent::Resized(physical_size) => {
@@ -1,24 +1,23 @@
-use std::num::NonZeroU32;
-
-use crate::texture::default_color_sampler;
-use crate::vector::UIntVector2;
-use crate::{postprocessing, recording::MOVIE_TEXTURE_FORMAT};
-use log::info;
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use easel_rs::painting::AsyncPaintingWriter;
+use easel_rs::recipe::PaintingRecipe;
+use easel_rs::recording::FrameFormat;
+use easel_rs::texture::default_color_sampler;
+use easel_rs::vector::UIntVector2;
+use log::error;
 use wgpu::{
     BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
-    BindingResource, Extent3d, LoadOp, Operations, Origin3d,
+    BindingResource, LoadOp, Operations,
 };
 
 use super::message::CanvasMessage;
-use super::{Canvas, PAINTING_TEXTURE_FORMAT, RENDER_TEXTURE_FORMAT};
-use crate::uniforms::Uniforms;
+use super::Canvas;
 impl Canvas {
     /// Render the shader on the canvas.
     pub fn render_canvas(&mut self) {
-        if self.paused {
+        if self.paused && !self.redraw_requested {
             return;
         }
+        self.redraw_requested = false;
         let frame = match self.swap_chain.get_current_frame() {
             Ok(frame) => frame,
             Err(frame_err) => {
@@ -28,137 +27,57 @@ impl Canvas {
                 return;
             }
         };
-        // Create the texture to render to.
-        let tex_desc = wgpu::TextureDescriptor {
-            size: Extent3d {
-                width: self.size.width,
-                height: self.size.height,
-                depth_or_array_layers: 1,
-            },
-            format: RENDER_TEXTURE_FORMAT,
-            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
-            label: Some("Canvas Render"),
-            dimension: wgpu::TextureDimension::D2,
-            mip_level_count: 1,
-            sample_count: 1,
-        };
-        let render_tex = self.device.create_texture(&tex_desc);
-        let render_tex_view = render_tex.create_view(&wgpu::TextureViewDescriptor::default());
-
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
-
-        // First, render using the shader.
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view: &render_tex_view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(self.clear_color),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
+        let device = &self.renderer.device;
 
-            for i in 0..self.bind_groups.len() {
-                render_pass.set_bind_group(i as u32, &self.bind_groups[i], &[]);
-            }
-            render_pass.set_pipeline(&self.render_pipeline);
-            // Set push constants, if any.
-            // if let Some(constants) = self.push_constants.as_ref() {
-            //     let mut offset: usize = 0;
-            //     for a_constant in constants {
-            //         let bytes = a_constant.bytes();
-            //         render_pass.set_push_constants(
-            //             wgpu::ShaderStage::FRAGMENT,
-            //             offset as u32,
-            //             &bytes,
-            //         );
-            //         offset += a_constant.size();
-            //     }
-            // }
-            render_pass.draw(0..3, 0..1);
-        }
-
-        // We can't create bind groups with swap chain textures, so have to create another temp tex.
-        let postprocessing_tex = self.device.create_texture(&tex_desc);
-        let postprocessing_tex_view =
-            postprocessing_tex.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
+        });
 
-        // Then render any post-processing effects.
-        let mut stage_in = &render_tex_view;
-        let mut stage_out = &postprocessing_tex_view;
-        for i in 0..self.postprocess_ops.len() {
-            let postprocess_op = &self.postprocess_ops[i];
-            // If user has provided custom uniforms, pass them to the post-processing stage as well.
-            let mut custom_data = None;
-            if let Some(custom_buffer) = self.user_uniforms_buffer.as_ref() {
-                custom_data = Some((custom_buffer, self.user_uniforms_buffer_size.unwrap()));
-            }
-            postprocess_op.post_process(
-                stage_in,
-                stage_out,
-                (
-                    &self.uniforms_device_buffer,
-                    std::mem::size_of_val(&self.uniforms),
-                ),
-                custom_data,
-                &self.device,
-                &mut encoder,
-                self.clear_color,
-                postprocessing::PipelineType::Render,
-            );
-            // Swap input and output textures handles
-            std::mem::swap(&mut stage_in, &mut stage_out);
-        }
-        // Swap one more time to get final output tex (undo last swap).
-        std::mem::swap(&mut stage_in, &mut stage_out);
+        // First, render using the shader and any post-processing effects.
+        // We can't create bind groups with swap chain textures, so the renderer outputs to a temp tex.
+        let output_tex = self.renderer.encode_render(
+            &mut encoder,
+            UIntVector2::new(self.size.width, self.size.height),
+        );
+        let output_tex_view = output_tex.create_view(&wgpu::TextureViewDescriptor::default());
 
         // Render back to swap chain texture.
         // Build new specialized bind groups for this render pass.
-        let sc_layout = self
-            .device
-            .create_bind_group_layout(&BindGroupLayoutDescriptor {
-                label: None,
-                entries: &[
-                    BindGroupLayoutEntry {
-                        binding: 0,
-                        count: None,
-                        visibility: wgpu::ShaderStage::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler {
-                            filtering: true,
-                            comparison: false,
-                        },
+        let sc_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    count: None,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        filtering: true,
+                        comparison: false,
                     },
-                    BindGroupLayoutEntry {
-                        binding: 1,
-                        count: None,
-                        visibility: wgpu::ShaderStage::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    count: None,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
                     },
-                ],
-            });
-        let sc_bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+                },
+            ],
+        });
+        let sc_bind_group = device.create_bind_group(&BindGroupDescriptor {
             label: Some("Swap Chain Render Pass Bind Group"),
             layout: &sc_layout,
             entries: &[
                 BindGroupEntry {
                     binding: 0,
-                    resource: BindingResource::Sampler(&default_color_sampler(&self.device)),
+                    resource: BindingResource::Sampler(&default_color_sampler(device)),
                 },
                 BindGroupEntry {
                     binding: 1,
-                    resource: BindingResource::TextureView(stage_out),
+                    resource: BindingResource::TextureView(&output_tex_view),
                 },
             ],
         });
@@ -169,7 +88,7 @@ impl Canvas {
                     view: &frame.output.view,
                     resolve_target: None,
                     ops: Operations {
-                        load: LoadOp::Clear(self.clear_color),
+                        load: LoadOp::Clear(self.renderer.clear_color),
                         store: true,
                     },
                 }],
@@ -183,210 +102,114 @@ impl Canvas {
         }
 
         let command_buffer = encoder.finish();
-        self.queue.submit(Some(command_buffer));
+        self.renderer.queue.submit(Some(command_buffer));
 
         self.transmitter
             .send(CanvasMessage::RenderPassSubmitted)
             .unwrap();
         self.transmitter.send(CanvasMessage::FrameStep).unwrap();
-    }
-
-    /// Similar to [Self::render_canvas()], but renders to a very high bit-depth texture and writes output to file.
-    /// **Note:** File is written to disk asynchronously.
-    pub fn create_painting(&mut self, resolution: UIntVector2) {
-        let painting_tex_desc = wgpu::TextureDescriptor {
-            size: Extent3d {
-                width: resolution.x as u32,
-                height: resolution.y as u32,
-                depth_or_array_layers: 1,
-            },
-            format: PAINTING_TEXTURE_FORMAT,
-            usage: wgpu::TextureUsage::RENDER_ATTACHMENT
-                | wgpu::TextureUsage::COPY_SRC
-                | wgpu::TextureUsage::SAMPLED,
-            label: Some("Painting"),
-            dimension: wgpu::TextureDimension::D2,
-            mip_level_count: 1,
-            sample_count: 1,
-        };
-
-        // Texture to render the painting too.
-        let painting = self.device.create_texture(&painting_tex_desc);
-        // Create the output texture for post-processing.
-        let post_process_tex = self.device.create_texture(&painting_tex_desc);
-
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Painting Encoder"),
-            });
-
-        // Modify Uniforms as necessary for painting render
-        {
-            let mut painting_uniforms = self.uniforms.clone();
-            let width_ratio = resolution.x as f32 / self.uniforms.resolution.x as f32;
-            let height_ratio = resolution.y as f32 / self.uniforms.resolution.y as f32;
-            painting_uniforms.mouse_position.x *= width_ratio;
-            painting_uniforms.mouse_position.z *= width_ratio;
-            painting_uniforms.mouse_position.y *= height_ratio;
-            painting_uniforms.mouse_position.w *= height_ratio;
-            painting_uniforms.resolution.x = resolution.x as f32;
-            painting_uniforms.resolution.y = resolution.y as f32;
-
-            // Copy uniforms from CPU to staging buffer, then copy from staging buffer to main buf.
-            let descriptor = BufferInitDescriptor {
-                label: Some("Uniforms Buffer"),
-                contents: bytemuck::bytes_of(&painting_uniforms),
-                usage: wgpu::BufferUsage::COPY_SRC,
-            };
-            let staging_buffer = self.device.create_buffer_init(&descriptor);
-
-            encoder.copy_buffer_to_buffer(
-                &staging_buffer,
-                0,
-                &self.uniforms_device_buffer,
-                0,
-                std::mem::size_of::<Uniforms>() as u64,
-            );
-        }
-
-        // Buffer to copy texture into after all rendering finishes.
-        let buffer_desc = wgpu::BufferDescriptor {
-            label: Some("Painting Staging Buffer"),
-            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
-            size: ((resolution.x * resolution.y) as usize * std::mem::size_of::<half::f16>() * 4)
-                as u64,
-            mapped_at_creation: false,
-        };
-        let buffer = self.device.create_buffer(&buffer_desc);
-
-        let painting_start_time = std::time::Instant::now();
-        // First run the pipeline.
-        {
-            let painting_view = painting.create_view(&wgpu::TextureViewDescriptor::default());
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view: &painting_view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(self.clear_color),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-
-            for i in 0..self.bind_groups.len() {
-                render_pass.set_bind_group(i as u32, &self.bind_groups[i], &[]);
-            }
-            render_pass.set_pipeline(&self.painting_pipeline);
-            // Set push constants, if any.
-            // if let Some(constants) = self.push_constants.as_ref() {
-            //     let mut offset: usize = 0;
-            //     for a_constant in constants {
-            //         let bytes = a_constant.bytes();
-            //         render_pass.set_push_constants(
-            //             wgpu::ShaderStage::FRAGMENT,
-            //             offset as u32,
-            //             &bytes,
-            //         );
-            //         offset += a_constant.size();
-            //     }
-            // }
-            render_pass.draw(0..3, 0..1);
-        }
 
-        // Then run all post-processing steps, in order.
-        let mut stage_in = &painting;
-        let mut stage_out = &post_process_tex;
-        let mut custom_data = None;
-        if let Some(custom_buffer) = self.user_uniforms_buffer.as_ref() {
-            custom_data = Some((custom_buffer, self.user_uniforms_buffer_size.unwrap()));
-        }
-        for postprocess_op in &mut self.postprocess_ops {
-            let input_view = stage_in.create_view(&wgpu::TextureViewDescriptor::default());
-            let output_view = stage_out.create_view(&wgpu::TextureViewDescriptor::default());
-            postprocess_op.post_process(
-                &input_view,
-                &output_view,
-                (
-                    &self.uniforms_device_buffer,
-                    std::mem::size_of_val(&self.uniforms),
-                ),
-                custom_data,
-                &self.device,
-                &mut encoder,
-                self.clear_color,
-                postprocessing::PipelineType::Painting,
-            );
-            // Swap input and output textures handles
-            std::mem::swap(&mut stage_in, &mut stage_out);
+        // Frames redrawn without advancing time, such as after seeking, are not part of the recording.
+        if self.offline_recording.is_some() && self.time_advanced {
+            self.time_advanced = false;
+            self.render_offline_movie_frame();
         }
+    }
 
-        // Run one more post-process op, the sRGB conversion.
-        {
-            let input_view = stage_in.create_view(&wgpu::TextureViewDescriptor::default());
-            let output_view = stage_out.create_view(&wgpu::TextureViewDescriptor::default());
-            self.srgb_postprocess.post_process(
-                &input_view,
-                &output_view,
-                (
-                    &self.uniforms_device_buffer,
-                    std::mem::size_of_val(&self.uniforms),
-                ),
-                custom_data,
-                &self.device,
-                &mut encoder,
-                self.clear_color,
-                postprocessing::PipelineType::Painting,
-            );
+    /// Render the next frame of an offline recording in the recorder's frame format and send it to the Dashboard.
+    /// Blocks until the frame has been read back, so frames reach the recorder in order and none are skipped.
+    fn render_offline_movie_frame(&mut self) {
+        let recording = self.offline_recording.as_mut().unwrap();
+        let resolution = recording.resolution;
+        let frame_format = recording.frame_format;
+        if let Some(frames) = recording.frames_remaining.as_mut() {
+            *frames -= 1;
         }
+        recording.frames_rendered += 1;
+        let finished = recording.frames_remaining == Some(0);
 
-        // Then encode a copy of the texture to the buffer.
-        {
-            let tex_copy_view = wgpu::ImageCopyTexture {
-                mip_level: 0,
-                origin: Origin3d::ZERO,
-                texture: stage_out,
-            };
-            let buf_copy_view = wgpu::ImageCopyBuffer {
-                buffer: &buffer,
-                layout: wgpu::ImageDataLayout {
-                    bytes_per_row: NonZeroU32::new(
-                        ((resolution.x * 4) as usize * std::mem::size_of::<half::f16>()) as u32,
-                    ),
-                    offset: 0,
-                    rows_per_image: NonZeroU32::new(resolution.y),
-                },
-            };
-            encoder.copy_texture_to_buffer(
-                tex_copy_view,
-                buf_copy_view,
-                Extent3d {
-                    width: resolution.x as u32,
-                    height: resolution.y as u32,
-                    depth_or_array_layers: 1,
-                },
-            );
+        let frame_data = self.renderer.render_frame(resolution, frame_format);
+        self.transmitter
+            .send(CanvasMessage::MovieFrameRendered(frame_data))
+            .unwrap();
+        if finished {
+            self.offline_recording = None;
+            self.transmitter
+                .send(CanvasMessage::OfflineRecordingFinished)
+                .unwrap();
         }
+    }
 
-        let command_buffer = encoder.finish();
-        self.queue.submit(Some(command_buffer));
+    /// Similar to [Self::render_canvas()], but renders to a very high bit-depth texture and writes output to file.
+    /// **Note:** File is written to disk asynchronously.
+    pub fn create_painting(&mut self, resolution: UIntVector2) {
+        let painting_start_time = std::time::Instant::now();
+        let recipe = PaintingRecipe::new(
+            self.shader_sources.clone(),
+            &self.renderer,
+            resolution,
+            self.painting_format,
+        );
+        let buffer = self
+            .renderer
+            .encode_painting(resolution, !self.painting_format.is_linear());
 
         self.transmitter
             .send(CanvasMessage::PaintingStarted(
                 buffer,
-                resolution,
+                Box::new(recipe),
+                painting_start_time,
+            ))
+            .unwrap();
+    }
+
+    /// Render a painting in tiles and stream each band of tiles to a painting writer as soon as it is read back.
+    /// Blocks the canvas until every tile has been rendered.
+    /// * `resolution` - Resolution of the full painting.
+    /// * `tile_size` - Width and height of each tile, in pixels.
+    /// * `filename` - File to write the painting to, see [easel_rs::painting::AsyncPaintingWriter::write_bands()].
+    /// * `open_externally` - Whether to open the painting in an external app once written.
+    pub fn create_tiled_painting(
+        &mut self,
+        resolution: UIntVector2,
+        tile_size: u32,
+        filename: String,
+        open_externally: bool,
+    ) {
+        let painting_start_time = std::time::Instant::now();
+        let recipe = PaintingRecipe::new(
+            self.shader_sources.clone(),
+            &self.renderer,
+            resolution,
+            self.painting_format,
+        );
+        let (bands, progress) = AsyncPaintingWriter::write_bands(recipe, filename, open_externally);
+        self.transmitter
+            .send(CanvasMessage::TiledPaintingStarted(
+                progress,
                 painting_start_time,
             ))
             .unwrap();
+        let srgb = !self.painting_format.is_linear();
+        self.renderer
+            .render_painting_tiled(resolution, tile_size, srgb, |band| {
+                if bands.send(band).is_err() {
+                    error!("Painting writer hung up before all tiles were rendered.");
+                }
+            });
     }
 
     /// Expected to be called immediately after the render() function.
     pub fn post_render(&mut self) {
+        // Keep the Dashboard timeline in sync, including after seeks and while recording offline.
+        self.transmitter
+            .send(CanvasMessage::PlaybackTimeUpdated(
+                self.renderer.uniforms.time,
+                self.renderer.uniforms.frame_num,
+            ))
+            .unwrap();
         // Inform Dashboard of each of our user-provided uniforms.
-        for a_uniform in &self.user_uniforms {
+        for a_uniform in &self.renderer.user_uniforms {
             let uni = a_uniform.clone();
             self.transmitter
                 .send(CanvasMessage::UniformForGUI(uni))
@@ -397,143 +220,10 @@ impl Canvas {
     }
 
     /// Called when Dashboard requests a movie render frame.
-    pub fn create_movie_frame(&mut self, resolution: UIntVector2) {
-        let painting_tex_desc = wgpu::TextureDescriptor {
-            size: Extent3d {
-                width: resolution.x as u32,
-                height: resolution.y as u32,
-                depth_or_array_layers: 1,
-            },
-            format: MOVIE_TEXTURE_FORMAT,
-            usage: wgpu::TextureUsage::RENDER_ATTACHMENT
-                | wgpu::TextureUsage::COPY_SRC
-                | wgpu::TextureUsage::SAMPLED,
-            label: Some("Movie Frame"),
-            dimension: wgpu::TextureDimension::D2,
-            mip_level_count: 1,
-            sample_count: 1,
-        };
-
-        // Texture to render the painting too.
-        let movie_frame = self.device.create_texture(&painting_tex_desc);
-        // Create the output texture for post-processing.
-        let post_process_tex = self.device.create_texture(&painting_tex_desc);
-
-        // Buffer to copy texture into after all rendering finishes.
-        let buffer_desc = wgpu::BufferDescriptor {
-            label: Some("Painting Staging Buffer"),
-            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
-            size: ((resolution.x * resolution.y) as usize * std::mem::size_of::<u8>() * 4) as u64,
-            mapped_at_creation: false,
-        };
-        let buffer = self.device.create_buffer(&buffer_desc);
-
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Movie Frame Encoder"),
-            });
-
+    /// * `frame_format` - Format the recorder expects frames in.
+    pub fn create_movie_frame(&mut self, resolution: UIntVector2, frame_format: FrameFormat) {
         let frame_start_time = std::time::Instant::now();
-        // First run the pipeline.
-        {
-            let movie_frame_view = movie_frame.create_view(&wgpu::TextureViewDescriptor::default());
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view: &movie_frame_view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(self.clear_color),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-
-            for i in 0..self.bind_groups.len() {
-                render_pass.set_bind_group(i as u32, &self.bind_groups[i], &[]);
-            }
-            render_pass.set_pipeline(&self.movie_pipeline);
-            // Set push constants, if any.
-            // if let Some(constants) = self.push_constants.as_ref() {
-            //     let mut offset: usize = 0;
-            //     for a_constant in constants {
-            //         let bytes = a_constant.bytes();
-            //         render_pass.set_push_constants(
-            //             wgpu::ShaderStage::FRAGMENT,
-            //             offset as u32,
-            //             &bytes,
-            //         );
-            //         offset += a_constant.size();
-            //     }
-            // }
-            render_pass.draw(0..3, 0..1);
-        }
-
-        // Then run all post-processing steps, in order.
-        let mut stage_in = &movie_frame;
-        let mut stage_out = &post_process_tex;
-        // If user has provided custom uniforms, pass them to the post-processing stage as well.
-        let mut custom_data = None;
-        if let Some(custom_buffer) = self.user_uniforms_buffer.as_ref() {
-            custom_data = Some((custom_buffer, self.user_uniforms_buffer_size.unwrap()));
-        }
-        for i in 0..self.postprocess_ops.len() {
-            info!("Postprocess op {}", i);
-            let postprocess_op = &self.postprocess_ops[i];
-            let input_view = stage_in.create_view(&wgpu::TextureViewDescriptor::default());
-            let output_view = stage_out.create_view(&wgpu::TextureViewDescriptor::default());
-            postprocess_op.post_process(
-                &input_view,
-                &output_view,
-                (
-                    &self.uniforms_device_buffer,
-                    std::mem::size_of_val(&self.uniforms),
-                ),
-                custom_data,
-                &self.device,
-                &mut encoder,
-                self.clear_color,
-                postprocessing::PipelineType::Movie,
-            );
-            // Swap input and output textures handles
-            std::mem::swap(&mut stage_in, &mut stage_out);
-        }
-        if self.postprocess_ops.len() == 0 {
-            std::mem::swap(&mut stage_in, &mut stage_out);
-        }
-
-        // Then encode a copy of the texture to the buffer.
-        {
-            let tex_copy_view = wgpu::ImageCopyTexture {
-                mip_level: 0,
-                origin: Origin3d::ZERO,
-                texture: stage_out,
-            };
-            let buf_copy_view = wgpu::ImageCopyBuffer {
-                buffer: &buffer,
-                layout: wgpu::ImageDataLayout {
-                    bytes_per_row: NonZeroU32::new(
-                        ((resolution.x * 4) as usize * std::mem::size_of::<u8>()) as u32,
-                    ),
-                    offset: 0,
-                    rows_per_image: NonZeroU32::new(resolution.y),
-                },
-            };
-            encoder.copy_texture_to_buffer(
-                tex_copy_view,
-                buf_copy_view,
-                Extent3d {
-                    width: resolution.x as u32,
-                    height: resolution.y as u32,
-                    depth_or_array_layers: 1,
-                },
-            );
-        }
-
-        let command_buffer = encoder.finish();
-        self.queue.submit(Some(command_buffer));
+        let buffer = self.renderer.encode_frame(resolution, frame_format);
 
         self.transmitter
             .send(CanvasMessage::MovieFrameStarted(
@@ -1,18 +1,28 @@
-use crate::vector::{IntVector2, UIntVector2};
-use crate::{canvas::CanvasMessage, uniforms::UserUniform};
-use crate::{recording::Recorder, utils::AsyncTiffWriter};
+use crate::canvas::CanvasMessage;
 use core::panic;
+use easel_rs::encoder::EncoderProfile;
+use easel_rs::history::UniformHistory;
+use easel_rs::keyframes::KeyframeTrack;
+use easel_rs::lfo::Lfo;
+use easel_rs::mutation::{mutate_uniforms, random_seed, randomise_uniforms};
+use easel_rs::painting::{AsyncPaintingWriter, PaintingFormat};
+use easel_rs::presets::{load_presets_from_json, presets_to_json, Preset};
+use easel_rs::recording::{FrameFormat, Recorder, RecorderBackend};
+use easel_rs::uniforms::{replace_user_uniform, UserUniform};
+use easel_rs::vector::{IntVector2, UIntVector2, Vector2};
 
 use imgui::FontSource;
-use imgui_wgpu::RendererConfig;
+use imgui_wgpu::{RendererConfig, TextureConfig};
 use imgui_winit_support;
-use log::info;
+use log::{error, info};
 use std::{
+    cmp::min,
+    fs,
     sync::mpsc::{Receiver, Sender},
     time::Instant,
 };
 use wgpu::{PowerPreference, RequestAdapterOptions};
-use winit::window::Window;
+use winit::{event::ModifiersState, window::Window};
 
 mod ui;
 pub use self::ui::*;
@@ -20,6 +30,9 @@ pub use self::ui::*;
 mod state;
 pub use self::state::*;
 
+/// Width in pixels of the thumbnails rendered for the gallery. The height follows the canvas aspect ratio.
+static THUMBNAIL_WIDTH: u32 = 160;
+
 /// Message Enums used by [Dashboard] to send messages to interested parties.
 pub enum DashboardMessage {
     PausePlayChanged,
@@ -27,9 +40,40 @@ pub enum DashboardMessage {
     Pause,
     TitlebarStatusChanged,
     PaintingRenderRequested(UIntVector2),
+    /// Painting resolution, tile size, filename to write to, and whether to open the painting in an external app once written.
+    TiledPaintingRenderRequested(UIntVector2, u32, String, bool),
     PaintingResolutionUpdated(UIntVector2),
-    MovieRenderRequested(UIntVector2),
+    PaintingFormatUpdated(PaintingFormat),
+    /// Movie frame resolution, and the format the recorder expects frames in.
+    MovieRenderRequested(UIntVector2, FrameFormat),
+    /// Record a movie offline, advancing time by exactly one frame per frame rendered.
+    /// Contains the movie resolution, the format the recorder expects frames in, the framerate,
+    /// the number of frames to record, or None to record until stopped,
+    /// and whether the frames form a seamless loop starting at t = 0.
+    OfflineRecordingStarted(UIntVector2, FrameFormat, u32, Option<u32>, bool),
+    /// Stop an offline recording before all of its frames have been rendered.
+    OfflineRecordingStopped,
+    /// A user uniform with its new value. Only sent when edited in the Uniforms panel, so changes from elsewhere stick.
     UniformUpdatedViaGUI(UserUniform),
+    /// Replace the keyframe track of the named uniform. An empty track stops animating the uniform.
+    KeyframeTrackUpdatedViaGUI(String, KeyframeTrack),
+    /// Replace the LFO of the named uniform, such as to enable, disable or tune it.
+    LfoUpdatedViaGUI(String, Lfo),
+    /// Write the current user uniforms, keyframes and LFOs back into the uniforms file.
+    UniformsSaveRequested,
+    /// Discard changes to user uniforms, keyframes and LFOs by loading them from the uniforms file again.
+    UniformsRevertRequested,
+    /// Set the mouse position in the uniforms, in pixels, as if the mouse moved there.
+    MouseMovedViaGUI(Vector2),
+    /// Render a thumbnail of the canvas once the uniforms sent before it are applied.
+    /// Contains the ID of the gallery entry the thumbnail is for, and the thumbnail resolution.
+    ThumbnailRequested(u64, UIntVector2),
+    /// Jump to a time in seconds and frame number, setting the uniforms directly.
+    TimeSeekRequested(f32, u32),
+    /// Step time by a number of frames, negative to step back. Contains the frame count and the seconds per frame.
+    FrameStepRequested(i32, f32),
+    /// Multiplier for how fast time passes while playing. Negative values play backwards.
+    PlaybackSpeedUpdated(f32),
 }
 
 /// Centralized controller and GUI class.
@@ -52,6 +96,8 @@ pub struct Dashboard {
     imgui_renderer: imgui_wgpu::Renderer,
     last_frame: std::time::Instant,
     hidpi_factor: f32,
+    /// Modifier keys currently held in the dashboard window.
+    modifiers: ModifiersState,
 
     state: DashboardState,
 
@@ -59,6 +105,9 @@ pub struct Dashboard {
     receiver: Receiver<CanvasMessage>,
     recorder: Option<Recorder>,
     last_movie_frame_time: Option<Instant>,
+    /// Frames requested from and received from the Canvas in the current real time recording.
+    movie_frames_requested: u32,
+    movie_frames_recorded: u32,
 }
 
 impl Dashboard {
@@ -159,11 +208,14 @@ impl Dashboard {
             imgui_renderer: renderer,
             last_frame: std::time::Instant::now(),
             hidpi_factor,
+            modifiers: ModifiersState::empty(),
             state,
             transmitter,
             receiver,
             recorder: None,
             last_movie_frame_time: None,
+            movie_frames_requested: 0,
+            movie_frames_recorded: 0,
         }
     }
 
@@ -178,20 +230,28 @@ impl Dashboard {
                 wgpu::SwapChainError::Timeout => self.state.frame_timeout_count += 1,
                 _ => {}
             },
-            CanvasMessage::PaintingStarted(buf, resolution, start_time) => {
-                let filename = self.state.painting_filename.clone() + ".tiff";
+            CanvasMessage::PaintingStarted(buf, recipe, start_time) => {
+                let filename = format!(
+                    "{}.{}",
+                    self.state.painting_filename,
+                    recipe.format.extension()
+                );
                 self.state.painting_start_time = Some(start_time);
                 let open_externally = match cfg!(target_os = "macos") {
                     true => self.state.open_painting_externally,
                     false => false,
                 };
-                self.state.painting_progress_receiver = Some(AsyncTiffWriter::write(
+                self.state.painting_progress_receiver = Some(AsyncPaintingWriter::write(
                     buf,
-                    UIntVector2::new(resolution.x as u32, resolution.y as u32),
+                    *recipe,
                     filename,
                     open_externally,
                 ));
             }
+            CanvasMessage::TiledPaintingStarted(progress, start_time) => {
+                self.state.painting_start_time = Some(start_time);
+                self.state.painting_progress_receiver = Some(progress);
+            }
             CanvasMessage::ShaderCompilationFailed(err_msg) => {
                 self.state.shader_compilation_error_msg = Some(err_msg);
                 // Pause rendering
@@ -206,16 +266,64 @@ impl Dashboard {
                 self.state.paused = !self.state.paused;
             }
             CanvasMessage::UniformForGUI(uniform) => {
-                self.state
-                    .gui_uniforms
-                    .insert(uniform.name.clone(), uniform);
+                let gui_uniforms = &mut self.state.gui_uniforms;
+                match gui_uniforms.iter_mut().find(|u| u.name == uniform.name) {
+                    Some(existing) => *existing = uniform,
+                    None => gui_uniforms.push(uniform),
+                }
+            }
+            CanvasMessage::KeyframesForGUI(tracks) => {
+                self.state.keyframe_tracks = tracks;
+            }
+            CanvasMessage::LfosForGUI(lfos) => {
+                self.state.lfos = lfos;
+            }
+            CanvasMessage::PlaybackTimeUpdated(time, frame_num) => {
+                self.state.playback_time = time;
+                self.state.playback_frame = frame_num;
             }
             CanvasMessage::UpdatePaintingResolutioninGUI(res) => {
                 self.state.painting_resolution = res;
             }
+            CanvasMessage::UndoRequested => self.undo_uniform_edit(),
+            CanvasMessage::ThumbnailRendered(id, resolution, pixels) => {
+                self.add_thumbnail(id, resolution, &pixels)
+            }
+            CanvasMessage::RedoRequested => self.redo_uniform_edit(),
+            CanvasMessage::MovieFrameRendered(frame_data) => {
+                // Frames rendered before the Canvas saw a stop request arrive after the recorder has stopped.
+                if !self.state.recording_in_progress {
+                    return;
+                }
+                if let Some(ref recorder) = self.recorder {
+                    recorder.add_frame_data(frame_data);
+                } else {
+                    panic!("Offline frame received for movie, but no recorder is instantiated.");
+                }
+            }
+            CanvasMessage::OfflineRecordingFinished => {
+                if self.state.recording_in_progress {
+                    info!("Offline recording finished.");
+                    if let Some(ref mut recorder) = self.recorder {
+                        recorder.stop();
+                    }
+                    self.state.recording_in_progress = false;
+                }
+            }
             CanvasMessage::MovieFrameStarted(buf, resolution, start_time) => {
+                // Frames requested just before the recording stopped arrive after the recorder has stopped.
+                if !self.state.recording_in_progress {
+                    return;
+                }
+                let frame_count = self.state.movie_frame_count();
                 if let Some(ref mut recorder) = self.recorder {
                     recorder.add_frame(buf, resolution, start_time);
+                    self.movie_frames_recorded += 1;
+                    if Some(self.movie_frames_recorded) == frame_count {
+                        info!("Recording finished.");
+                        recorder.stop();
+                        self.state.recording_in_progress = false;
+                    }
                 } else {
                     panic!("Frame received for movie at timestamp {:?}, but no recorder is instantiated.", start_time);
                 }
@@ -223,6 +331,204 @@ impl Dashboard {
         }
     }
 
+    /// Select where the next recorder writes its frames, as from the `--recorder` option.
+    /// Takes effect the next time a recorder is initialized.
+    pub fn set_recorder_backend(&mut self, backend: RecorderBackend) {
+        self.state.recorder_backend = backend;
+    }
+
+    /// Replace the FFmpeg encoder profiles to choose from, as loaded from the `--encoder-profiles` file,
+    /// and select one by name. Returns false, keeping the current selection, if no profile has that name.
+    pub fn set_encoder_profiles(&mut self, profiles: Vec<EncoderProfile>, selected: &str) -> bool {
+        let current = self.state.encoder_profiles[self.state.encoder_profile_idx]
+            .name
+            .clone();
+        let position = |name: &str| profiles.iter().position(|profile| profile.name == name);
+        let (idx, found) = match position(selected) {
+            Some(idx) => (idx, true),
+            None => (position(&current).unwrap_or(0), false),
+        };
+        self.state.encoder_profiles = profiles;
+        self.state.encoder_profile_idx = idx;
+        found
+    }
+
+    /// Select the file presets are saved to, as from the `--presets` option, and load any presets already in it.
+    pub fn set_presets_file(&mut self, file: &str) {
+        self.state.presets_file = Some(String::from(file));
+        if !std::path::Path::new(file).exists() {
+            return;
+        }
+        let text = fs::read_to_string(file).expect("Error reading presets from file.");
+        let json_data = json::parse(&text).expect("Error parsing JSON.");
+        self.state.presets = load_presets_from_json(&json_data);
+        self.state.morph_to_idx = min(1, self.state.presets.len().saturating_sub(1));
+    }
+
+    /// Undo the last edit to the user uniforms, sending the earlier values to the Canvas.
+    pub fn undo_uniform_edit(&mut self) {
+        if let Some(uniforms) = self.state.uniform_history.undo() {
+            self.restore_uniforms(uniforms);
+        }
+    }
+
+    /// Redo the last undone edit to the user uniforms, sending the values to the Canvas.
+    pub fn redo_uniform_edit(&mut self) {
+        if let Some(uniforms) = self.state.uniform_history.redo() {
+            self.restore_uniforms(uniforms);
+        }
+    }
+
+    /// Private helper that sends uniforms from the undo history to the Canvas and shows them straight away.
+    /// Stops any timed morph, which would otherwise overwrite them.
+    fn restore_uniforms(&mut self, uniforms: Vec<UserUniform>) {
+        self.state.morph_start_time = None;
+        for uniform in uniforms {
+            replace_user_uniform(&mut self.state.gui_uniforms, uniform.clone());
+            self.transmitter
+                .send(DashboardMessage::UniformUpdatedViaGUI(uniform))
+                .unwrap();
+        }
+    }
+
+    /// Set every numeric user uniform with a range to new values, keeping them in the gallery.
+    /// Picks a new seed unless the seed is locked. See [easel_rs::mutation].
+    /// * `mutate` - Whether to nudge the current values by the mutation amount, rather than pick them anywhere in range.
+    pub fn explore_uniforms(&mut self, mutate: bool) {
+        if self.state.gui_uniforms.is_empty() {
+            return;
+        }
+        if !self.state.lock_exploration_seed {
+            self.state.exploration_seed = random_seed();
+        }
+        let seed = self.state.exploration_seed;
+        let uniforms = match mutate {
+            true => mutate_uniforms(
+                &self.state.gui_uniforms,
+                self.state.mutation_amount as f64,
+                seed,
+            ),
+            false => randomise_uniforms(&self.state.gui_uniforms, seed),
+        };
+        self.state.morph_start_time = None;
+        apply_uniforms(
+            &self.transmitter,
+            &mut self.state.uniform_history,
+            &self.state.gui_uniforms,
+            &uniforms,
+        );
+
+        let id = self.state.next_gallery_id;
+        self.state.next_gallery_id += 1;
+        let window_size = self.state.render_window_size;
+        let height = THUMBNAIL_WIDTH * window_size.y.max(1) as u32 / window_size.x.max(1) as u32;
+        self.transmitter
+            .send(DashboardMessage::ThumbnailRequested(
+                id,
+                UIntVector2::new(THUMBNAIL_WIDTH, height.max(1)),
+            ))
+            .unwrap();
+        self.state.gallery.push(GalleryEntry {
+            id,
+            seed,
+            mutated: mutate,
+            uniforms,
+            thumbnail: None,
+        });
+        self.trim_gallery();
+    }
+
+    /// Go back to the values of a gallery entry, showing the seed they were picked with.
+    /// * `idx` - Index of the entry in the gallery, oldest first.
+    pub fn apply_gallery_entry(&mut self, idx: usize) {
+        let entry = match self.state.gallery.get(idx) {
+            Some(entry) => entry,
+            None => return,
+        };
+        self.state.morph_start_time = None;
+        self.state.exploration_seed = entry.seed;
+        apply_uniforms(
+            &self.transmitter,
+            &mut self.state.uniform_history,
+            &self.state.gui_uniforms,
+            &entry.uniforms,
+        );
+    }
+
+    /// Drop the oldest gallery entries beyond the gallery size, freeing their thumbnails.
+    pub fn trim_gallery(&mut self) {
+        let excess = self
+            .state
+            .gallery
+            .len()
+            .saturating_sub(self.state.gallery_size.max(1) as usize);
+        for entry in self.state.gallery.drain(..excess) {
+            if let Some(texture_id) = entry.thumbnail {
+                self.imgui_renderer.textures.remove(texture_id);
+            }
+        }
+    }
+
+    /// Private helper that uploads a thumbnail rendered by the Canvas and shows it in the gallery.
+    /// Thumbnails for entries that have since been dropped are ignored.
+    fn add_thumbnail(&mut self, id: u64, resolution: UIntVector2, pixels: &[u8]) {
+        let entry = match self.state.gallery.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let texture = imgui_wgpu::Texture::new(
+            &self.device,
+            &self.imgui_renderer,
+            TextureConfig {
+                size: wgpu::Extent3d {
+                    width: resolution.x,
+                    height: resolution.y,
+                    depth_or_array_layers: 1,
+                },
+                label: Some("Gallery Thumbnail"),
+                format: Some(wgpu::TextureFormat::Rgba8UnormSrgb),
+                ..Default::default()
+            },
+        );
+        texture.write(&self.queue, pixels, resolution.x, resolution.y);
+        entry.thumbnail = Some(self.imgui_renderer.textures.insert(texture));
+    }
+
+    /// Private helper that advances the timed morph between presets, if one is in progress, and applies it.
+    fn update_preset_morph(&mut self) {
+        let start_time = match self.state.morph_start_time {
+            Some(start_time) => start_time,
+            None => return,
+        };
+        let presets = &self.state.presets;
+        let (from, to) = match (
+            presets.get(self.state.morph_from_idx),
+            presets.get(self.state.morph_to_idx),
+        ) {
+            (Some(from), Some(to)) => (from, to),
+            // The presets were deleted mid-morph.
+            _ => {
+                self.state.morph_start_time = None;
+                return;
+            }
+        };
+        let elapsed = start_time.elapsed().as_secs_f32();
+        let amount = match self.state.morph_duration > 0.0 {
+            true => (elapsed / self.state.morph_duration).min(1.0),
+            false => 1.0,
+        };
+        apply_preset(
+            &self.transmitter,
+            &mut self.state.uniform_history,
+            &self.state.gui_uniforms,
+            &from.morph(to, amount),
+        );
+        self.state.morph_amount = amount;
+        if amount >= 1.0 {
+            self.state.morph_start_time = None;
+        }
+    }
+
     /// Expected to be called every frame tick **before** [Self::render_dashboard()]
     /// Checks the receiver queue for any incoming messages, among other things.
     pub fn update(&mut self) {
@@ -236,21 +542,19 @@ impl Dashboard {
                 Err(_) => break,
             }
         }
-        for (_, uniform) in &self.state.gui_uniforms {
-            let err = self
-                .transmitter
-                .send(DashboardMessage::UniformUpdatedViaGUI(uniform.clone()));
-            match err {
-                Ok(_) => {}
-                Err(msg) => info!("Dashboard hung up: {}", msg),
-            }
-        }
+        self.update_preset_morph();
+        let frame_count = self.state.movie_frame_count();
         if let Some(ref mut recorder) = self.recorder {
             if self.state.movie_framerate < 1 {
                 panic!("Invalid framerate {} provided!", self.state.movie_framerate);
             }
-            // If we have not stopped, keep requesting frames on the selected FPS interval
-            let mut frame_needed = self.state.recording_in_progress;
+            // If we have not stopped, keep requesting frames on the selected FPS interval.
+            // Offline recordings are driven by the Canvas instead.
+            let mut frame_needed =
+                self.state.recording_in_progress && !self.state.offline_recording;
+            if let Some(frame_count) = frame_count {
+                frame_needed = frame_needed && self.movie_frames_requested < frame_count;
+            }
             if let Some(last_frame_time) = self.last_movie_frame_time.as_mut() {
                 let seconds_per_frame = 1.0 / (self.state.movie_framerate as f64);
                 let delta = (update_time - *last_frame_time).as_secs_f64();
@@ -258,16 +562,21 @@ impl Dashboard {
             }
             if frame_needed && recorder.ready {
                 self.transmitter
-                    .send(DashboardMessage::MovieRenderRequested(UIntVector2::new(
-                        self.state.recording_resolution.x as u32,
-                        self.state.recording_resolution.y as u32,
-                    )))
+                    .send(DashboardMessage::MovieRenderRequested(
+                        UIntVector2::new(
+                            self.state.recording_resolution.x as u32,
+                            self.state.recording_resolution.y as u32,
+                        ),
+                        recorder.frame_format,
+                    ))
                     .unwrap();
                 self.last_movie_frame_time = Some(update_time);
+                self.movie_frames_requested += 1;
             }
             // If finished, cleanup.
             if recorder.poll() {
                 self.recorder.take().unwrap().finish();
+                self.state.recording_in_progress = false;
             }
         }
 
@@ -284,14 +593,18 @@ impl Dashboard {
             Err(msg) => info!("Canvas hung up: {}", msg),
             Ok(_) => {}
         }
+        // And the currently selected painting format.
+        if let Err(msg) = self
+            .transmitter
+            .send(DashboardMessage::PaintingFormatUpdated(
+                self.state.painting_format,
+            ))
+        {
+            info!("Canvas hung up: {}", msg);
+        }
     }
 
     pub fn post_render(&mut self) {
-        for (_name, uniform) in &self.state.gui_uniforms {
-            self.transmitter
-                .send(DashboardMessage::UniformUpdatedViaGUI(uniform.clone()))
-                .unwrap();
-        }
         self.state.gui_uniforms.clear();
         let now = std::time::Instant::now();
         self.state.last_render_time = (now - self.last_frame).as_secs_f64() * 1000.0;
@@ -299,3 +612,54 @@ impl Dashboard {
         self.last_frame = now;
     }
 }
+
+/// Send user uniforms to the Canvas, recording the changes in the undo history.
+/// Uniforms the Canvas doesn't have, by name and type, are ignored.
+fn apply_uniforms(
+    transmitter: &Sender<DashboardMessage>,
+    history: &mut UniformHistory,
+    gui_uniforms: &[UserUniform],
+    uniforms: &[UserUniform],
+) {
+    for uniform in uniforms {
+        if let Some(current) = gui_uniforms
+            .iter()
+            .find(|u| u.name == uniform.name && u.inherent_type == uniform.inherent_type)
+        {
+            history.record(current, uniform);
+        }
+        transmitter
+            .send(DashboardMessage::UniformUpdatedViaGUI(uniform.clone()))
+            .unwrap();
+    }
+}
+
+/// Send the user uniforms, time and mouse position of a preset to the Canvas,
+/// recording the uniform changes in the undo history, see [apply_uniforms()].
+fn apply_preset(
+    transmitter: &Sender<DashboardMessage>,
+    history: &mut UniformHistory,
+    gui_uniforms: &[UserUniform],
+    preset: &Preset,
+) {
+    apply_uniforms(transmitter, history, gui_uniforms, &preset.uniforms);
+    transmitter
+        .send(DashboardMessage::TimeSeekRequested(
+            preset.time,
+            preset.frame,
+        ))
+        .unwrap();
+    transmitter
+        .send(DashboardMessage::MouseMovedViaGUI(preset.mouse))
+        .unwrap();
+}
+
+/// Write presets to the presets file, if there is one.
+fn save_presets(presets_file: Option<&str>, presets: &[Preset]) {
+    if let Some(file) = presets_file {
+        let text = presets_to_json(presets).pretty(2);
+        if let Err(e) = fs::write(file, text) {
+            error!("Error saving presets to {}: {}", file, e);
+        }
+    }
+}
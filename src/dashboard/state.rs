@@ -1,10 +1,21 @@
-use crate::{
+use easel_rs::{
+    animation::AnimationOptions,
+    encoder::EncoderProfile,
+    history::UniformHistory,
+    keyframes::KeyframeTrack,
+    lfo::Lfo,
+    mutation::random_seed,
+    painting::{PaintingFormat, WriteFinished},
+    presets::Preset,
+    recording::RecorderBackend,
     uniforms::UserUniform,
-    utils::WriteFinished,
     vector::{IntVector2, Vector2},
 };
 use std::collections::HashMap;
 use std::{sync::mpsc::Receiver, usize};
+/// Most uniform edits that can be undone.
+static UNIFORM_HISTORY_CAPACITY: usize = 200;
+
 /// Struct containing information the GUI is displaying and interacting with.
 pub struct DashboardState {
     pub last_render_time: f64,
@@ -13,21 +24,96 @@ pub struct DashboardState {
     pub mouse_pos: Vector2,
     pub render_window_size: IntVector2,
     pub paused: bool,
+    /// Time in seconds in the uniforms of the Canvas, as of its last frame.
+    pub playback_time: f32,
+    /// Frame number in the uniforms of the Canvas, as of its last frame.
+    pub playback_frame: u32,
+    /// Multiplier for how fast time passes while playing. Negative values play backwards.
+    pub playback_speed: f32,
+    /// Length of the timeline scrub slider, in seconds.
+    pub timeline_length: f32,
     pub show_titlebar: bool,
     pub painting_resolution: IntVector2,
     pub recording_resolution: IntVector2,
+    /// Width and height of tiles when rendering paintings in tiles. 0 disables tiling.
+    pub painting_tile_size: i32,
     pub painting_filename: String,
+    /// Format paintings are written in. Also determines the file extension.
+    pub painting_format: PaintingFormat,
     pub recording_filename: String,
+    /// Where recordings are written. Also determines whether the recording filename is a movie or a directory.
+    pub recorder_backend: RecorderBackend,
+    /// FFmpeg encoder profiles to choose from, built-in and from the encoder profiles file.
+    pub encoder_profiles: Vec<EncoderProfile>,
+    /// Index into [Self::encoder_profiles] of the profile movies are encoded with.
+    pub encoder_profile_idx: usize,
+    /// Dithering and ping-pong playback for animated GIF and PNG recordings.
+    pub animation_options: AnimationOptions,
     pub recording_in_progress: bool,
     /// Unit: seconds
     pub movie_framerate: i32,
+    /// Record movies offline, advancing time by exactly one frame per frame regardless of how long rendering takes.
+    pub offline_recording: bool,
+    /// Length of recordings in seconds. 0 records until stopped.
+    pub movie_duration: f32,
+    /// Record [Self::movie_duration] as a seamless loop from t = 0. Only for offline recordings.
+    pub seamless_loop: bool,
     /// Only available on macOS.
     pub open_painting_externally: bool,
     pub pause_while_painting: bool,
     pub painting_progress_receiver: Option<Receiver<WriteFinished>>,
     pub shader_compilation_error_msg: Option<String>,
     pub painting_start_time: Option<std::time::Instant>,
-    pub gui_uniforms: HashMap<String, UserUniform>,
+    /// User uniforms as of the last frame of the Canvas, in the order they are bound.
+    pub gui_uniforms: Vec<UserUniform>,
+    /// Keyframe tracks animating user uniforms, by uniform name, as edited in the curve editor.
+    pub keyframe_tracks: HashMap<String, KeyframeTrack>,
+    /// LFOs driving user uniforms, by uniform name, as toggled and tuned in the Uniforms panel.
+    pub lfos: HashMap<String, Lfo>,
+    /// Named snapshots of the user uniforms, time and mouse position, in the order they are listed.
+    pub presets: Vec<Preset>,
+    /// File presets are loaded from and saved to, if any.
+    pub presets_file: Option<String>,
+    /// Name to give the next preset saved.
+    pub new_preset_name: String,
+    /// Index into [Self::presets] of the preset morphs start from.
+    pub morph_from_idx: usize,
+    /// Index into [Self::presets] of the preset morphs end at.
+    pub morph_to_idx: usize,
+    /// Progress of the morph, from 0 at the first preset to 1 at the second.
+    pub morph_amount: f32,
+    /// Length of timed morphs, in seconds.
+    pub morph_duration: f32,
+    /// When the timed morph in progress started, if any.
+    pub morph_start_time: Option<std::time::Instant>,
+    /// Undo and redo history of edits to the user uniforms, from the Uniforms panel and presets.
+    pub uniform_history: UniformHistory,
+    /// Seed of the last Mutate or Randomise, or of the next one if the seed is locked.
+    pub exploration_seed: u32,
+    /// Whether Mutate and Randomise reuse the seed shown rather than picking a new one, to reproduce a result.
+    pub lock_exploration_seed: bool,
+    /// Largest change Mutate makes to a uniform, as a fraction of its range.
+    pub mutation_amount: f32,
+    /// The latest values from Mutate and Randomise, oldest first.
+    pub gallery: Vec<GalleryEntry>,
+    /// Most entries kept in the gallery.
+    pub gallery_size: i32,
+    /// ID to give the next gallery entry.
+    pub next_gallery_id: u64,
+}
+
+/// Values of the user uniforms from one Mutate or Randomise, kept in the gallery so they can be gone back to.
+pub struct GalleryEntry {
+    /// Matches the entry to the thumbnail rendered for it.
+    pub id: u64,
+    /// Seed the values were picked with.
+    pub seed: u32,
+    /// Whether the values came from Mutate rather than Randomise.
+    pub mutated: bool,
+    /// The user uniforms with the picked values.
+    pub uniforms: Vec<UserUniform>,
+    /// Thumbnail of the canvas with these values, once rendered.
+    pub thumbnail: Option<imgui::TextureId>,
 }
 
 impl DashboardState {
@@ -39,19 +125,60 @@ impl DashboardState {
             mouse_pos: Vector2::zero(),
             render_window_size: IntVector2::zero(),
             paused: false,
+            playback_time: 0.0,
+            playback_frame: 0,
+            playback_speed: 1.0,
+            timeline_length: 60.0,
             show_titlebar: true,
             painting_resolution: IntVector2::zero(),
             recording_resolution: IntVector2::new(1024, 1024),
+            painting_tile_size: 0,
             painting_filename: String::from("Painting"),
+            painting_format: PaintingFormat::Tiff16,
             recording_filename: String::from("Muybridge"),
+            recorder_backend: RecorderBackend::Ffmpeg,
+            animation_options: AnimationOptions::default(),
+            encoder_profiles: EncoderProfile::builtin(),
+            encoder_profile_idx: EncoderProfile::builtin()
+                .iter()
+                .position(|profile| profile.name == EncoderProfile::default_name())
+                .unwrap(),
             recording_in_progress: false,
             movie_framerate: 60,
+            offline_recording: false,
+            movie_duration: 0.0,
+            seamless_loop: false,
             open_painting_externally: true,
             pause_while_painting: true,
             painting_progress_receiver: None,
             shader_compilation_error_msg: None,
             painting_start_time: None,
-            gui_uniforms: HashMap::new(),
+            gui_uniforms: Vec::new(),
+            keyframe_tracks: HashMap::new(),
+            lfos: HashMap::new(),
+            presets: Vec::new(),
+            presets_file: None,
+            new_preset_name: String::from("Preset"),
+            morph_from_idx: 0,
+            morph_to_idx: 0,
+            morph_amount: 0.0,
+            morph_duration: 5.0,
+            morph_start_time: None,
+            uniform_history: UniformHistory::new(UNIFORM_HISTORY_CAPACITY),
+            exploration_seed: random_seed(),
+            lock_exploration_seed: false,
+            mutation_amount: 0.1,
+            gallery: Vec::new(),
+            gallery_size: 8,
+            next_gallery_id: 0,
+        }
+    }
+
+    /// Number of frames to record for the movie duration, or None to record until stopped.
+    pub fn movie_frame_count(&self) -> Option<u32> {
+        match (self.movie_duration * self.movie_framerate as f32).round() as u32 {
+            0 => None,
+            frames => Some(frames),
         }
     }
 }
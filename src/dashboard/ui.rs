@@ -1,10 +1,28 @@
-use super::{Dashboard, DashboardMessage};
-use crate::{recording::Recorder, recording::MOVIE_TEXTURE_FORMAT, uniforms, vector::UIntVector2};
+use super::{apply_preset, save_presets, Dashboard, DashboardMessage};
+use easel_rs::animation::AnimationFormat;
+use easel_rs::keyframes::{Easing, Keyframe, KeyframeTrack};
+use easel_rs::lfo::{Lfo, Waveform};
+use easel_rs::painting::PaintingFormat;
+use easel_rs::presets::Preset;
+use easel_rs::uniforms::{UniformWidget, UserUniform, UserUniformType};
+use easel_rs::utils::{convert_bytes_to_value, convert_value_to_bytes};
+use easel_rs::{recording::Recorder, recording::RecorderBackend, vector::UIntVector2};
 use imgui::Condition;
-use imgui::{im_str, ImString, StyleColor};
-use log::{info, warn};
+use imgui::{im_str, ImStr, ImString, StyleColor};
+use log::{error, info, warn};
+use std::mem::discriminant;
+use std::sync::mpsc::TryRecvError;
 use winit::event::*;
 
+/// Number of points the keyframe curve editor samples each track at.
+static KEYFRAME_PLOT_SAMPLES: usize = 128;
+/// Pixels of vertical mouse movement to turn a uniform's knob across its whole range.
+static KNOB_DRAG_PIXELS: f32 = 200.0;
+/// Change per pixel when dragging a float uniform that has no `step`.
+static FLOAT_DRAG_SPEED: f32 = 0.01;
+/// Width the gallery thumbnails are shown at, in points.
+static THUMBNAIL_DISPLAY_WIDTH: f32 = 72.0;
+
 impl Dashboard {
     /// Renders the UI and responds to UI events.
     pub fn render_dashboard(&mut self) {
@@ -37,6 +55,13 @@ impl Dashboard {
             .push(ui.push_style_color(StyleColor::ButtonHovered, [0.533, 0.851, 0.816, 1.0]));
         color_tokens.push(ui.push_style_color(StyleColor::FrameBg, [0.741, 0.933, 0.984, 1.0]));
         color_tokens.push(ui.push_style_color(StyleColor::WindowBg, [0.906, 0.784, 0.573, 1.0]));
+        // Handled once the frame is rendered, as they update the GUI's own copy of the uniforms.
+        let mut undo_pressed = false;
+        let mut redo_pressed = false;
+        let mut mutate_pressed = false;
+        let mut randomise_pressed = false;
+        let mut applied_gallery_idx = None;
+        let mut gallery_size_changed = false;
 
         {
             let render_time = self.state.last_render_time;
@@ -48,19 +73,77 @@ impl Dashboard {
             let mut pause_button_pressed = false;
             let titlebars_state = self.state.show_titlebar;
             let mut titlebar_button_pressed = false;
+            let mut playback_time = self.state.playback_time;
+            let playback_frame = self.state.playback_frame;
+            let playback_speed = &mut self.state.playback_speed;
+            let timeline_length = &mut self.state.timeline_length;
+            let mut seek_time = None;
+            let mut frames_to_step = 0;
+            let mut playback_speed_changed = false;
             let gui_width = self.size.width as f32 / self.hidpi_factor - 10.0;
             let mut create_painting_button_pressed = false;
             let painting_width = &mut self.state.painting_resolution.x;
             let painting_height = &mut self.state.painting_resolution.y;
+            let painting_tile_size = &mut self.state.painting_tile_size;
+            let mut painting_format = self.state.painting_format;
+            // Compare variants only, so that a JPEG with any quality selects the JPEG entry.
+            let mut painting_format_idx = PaintingFormat::ALL
+                .iter()
+                .position(|format| discriminant(format) == discriminant(&painting_format))
+                .unwrap();
             let _recording_width = &mut self.state.recording_resolution.x;
             let _recording_height = &mut self.state.recording_resolution.y;
             let movie_framerate = &mut self.state.movie_framerate;
+            let offline_recording = &mut self.state.offline_recording;
+            let movie_duration = &mut self.state.movie_duration;
+            let seamless_loop = &mut self.state.seamless_loop;
+            let recorder_backend = &mut self.state.recorder_backend;
+            let encoder_profiles = &self.state.encoder_profiles;
+            let encoder_profile_idx = &mut self.state.encoder_profile_idx;
+            let animation_options = &mut self.state.animation_options;
+            let mut recorder_backend_idx = RecorderBackend::ALL
+                .iter()
+                .position(|backend| backend == recorder_backend)
+                .unwrap();
             let mut painting_filename = ImString::with_capacity(256);
             let mut recording_filename = ImString::with_capacity(256);
             let open_painting_externally = &mut self.state.open_painting_externally;
             let pause_while_painting = &mut self.state.pause_while_painting;
             let shader_compilation_error_msg = self.state.shader_compilation_error_msg.as_ref();
             let user_uniforms = &mut self.state.gui_uniforms;
+            let mut edited_uniforms = Vec::new();
+            let mut save_uniforms_pressed = false;
+            let mut revert_uniforms_pressed = false;
+            let uniform_history = &mut self.state.uniform_history;
+            let keyframe_tracks = &mut self.state.keyframe_tracks;
+            let mut edited_tracks = Vec::new();
+            let lfos = &mut self.state.lfos;
+            let mut edited_lfos = Vec::new();
+            let presets = &self.state.presets;
+            let mut preset_name = ImString::with_capacity(256);
+            preset_name.push_str(&self.state.new_preset_name);
+            let mut preset_name_changed = false;
+            let mut save_preset_pressed = false;
+            let mut applied_preset_idx = None;
+            let mut deleted_preset_idx = None;
+            let morph_from_idx = &mut self.state.morph_from_idx;
+            let morph_to_idx = &mut self.state.morph_to_idx;
+            let morph_amount = &mut self.state.morph_amount;
+            let morph_duration = &mut self.state.morph_duration;
+            let morph_in_progress = self.state.morph_start_time.is_some();
+            let mut morph_amount_changed = false;
+            let mut start_morph_pressed = false;
+            let mutation_amount = &mut self.state.mutation_amount;
+            let mut exploration_seed = self.state.exploration_seed as i32;
+            let mut exploration_seed_changed = false;
+            let lock_exploration_seed = &mut self.state.lock_exploration_seed;
+            let gallery = &self.state.gallery;
+            let gallery_size = &mut self.state.gallery_size;
+            let window_size = self.state.render_window_size;
+            let thumbnail_size = [
+                THUMBNAIL_DISPLAY_WIDTH,
+                THUMBNAIL_DISPLAY_WIDTH * window_size.y.max(1) as f32 / window_size.x.max(1) as f32,
+            ];
             let mut start_record_button_pressed = false;
             let mut stop_record_button_pressed = false;
             let recording_in_progress = &mut self.state.recording_in_progress;
@@ -68,7 +151,8 @@ impl Dashboard {
             let recorder = self.recorder.as_ref();
             let recorder_ready = match recorder {
                 Some(rec) => rec.ready,
-                None => false,
+                // Nothing left to wait on, such as after a recorder failed to start.
+                None => true,
             };
 
             painting_filename.push_str(&self.state.painting_filename);
@@ -80,7 +164,8 @@ impl Dashboard {
                 Some(rx) => {
                     let msg_result = rx.try_recv();
                     match msg_result {
-                        Ok(_) => {
+                        // A writer that hung up has failed and already logged why, so stop waiting on it too.
+                        Ok(_) | Err(TryRecvError::Disconnected) => {
                             self.state.painting_progress_receiver = None;
 
                             // Log the amount of time render + write took.
@@ -97,7 +182,7 @@ impl Dashboard {
                             }
                             false
                         } // Finished.
-                        Err(_) => true, // Still writing, hasn't reported status yet.
+                        Err(TryRecvError::Empty) => true, // Still writing, hasn't reported status yet.
                     }
                 }
             };
@@ -149,6 +234,52 @@ impl Dashboard {
                                 ui.button(im_str!("Show Titlebar"), [gui_width, 25.0]);
                         }
                     }
+                    //---------------------------------
+                    if imgui::CollapsingHeader::new(im_str!("Timeline"))
+                        .default_open(true)
+                        .open_on_arrow(true)
+                        .open_on_double_click(true)
+                        .build(&ui)
+                    {
+                        ui.text(format!("Frame: {}", playback_frame));
+                        // Time can run past either end of the slider, so only clamp the slider range.
+                        let mut slider_time = playback_time.clamp(0.0, *timeline_length);
+                        if imgui::Slider::new(im_str!("Scrub##Timeline"))
+                            .range(0.0..=*timeline_length)
+                            .display_format(im_str!("%.3f s"))
+                            .build(&ui, &mut slider_time)
+                        {
+                            seek_time = Some(slider_time);
+                        }
+                        if ui
+                            .input_float(im_str!("Time##Timeline"), &mut playback_time)
+                            .enter_returns_true(true)
+                            .build()
+                        {
+                            seek_time = Some(playback_time);
+                        }
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text("Type a time in seconds and press Enter to jump to it.");
+                        }
+                        let half_width = gui_width / 2.0 - 4.0;
+                        if ui.button(im_str!("Step Back##Timeline"), [half_width, 25.0]) {
+                            frames_to_step = -1;
+                        }
+                        ui.same_line(0.0);
+                        if ui.button(im_str!("Step Forward##Timeline"), [half_width, 25.0]) {
+                            frames_to_step = 1;
+                        }
+                        playback_speed_changed = imgui::Slider::new(im_str!("Speed##Timeline"))
+                            .range(-4.0..=4.0)
+                            .display_format(im_str!("%.2fx"))
+                            .build(&ui, playback_speed);
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text("Multiplier for how fast time passes. Negative plays backwards. Ctrl+Click to type a value.");
+                        }
+                        ui.input_float(im_str!("Length##Timeline"), timeline_length)
+                            .build();
+                        *timeline_length = timeline_length.max(1.0);
+                    }
 
                     if imgui::CollapsingHeader::new(im_str!("Painting Options"))
                         .default_open(true)
@@ -160,7 +291,30 @@ impl Dashboard {
                             .build();
                         ui.input_int(im_str!("Height##Painting"), painting_height)
                             .build();
+                        // Any width works, but textures can't be empty.
+                        *painting_width = (*painting_width).max(1);
+                        *painting_height = (*painting_height).max(1);
+                        ui.input_int(im_str!("Tile Size##Painting"), painting_tile_size)
+                            .build();
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text("Render in tiles of this size for paintings too large for GPU memory. 0 disables tiling.");
+                        }
 
+                        let format_changed = imgui::ComboBox::new(im_str!("Format##Painting"))
+                            .build_simple(
+                                &ui,
+                                &mut painting_format_idx,
+                                &PaintingFormat::ALL,
+                                &|format| ImString::new(format.description()).into(),
+                            );
+                        if format_changed {
+                            painting_format = PaintingFormat::ALL[painting_format_idx];
+                        }
+                        if let PaintingFormat::Jpeg(quality) = &mut painting_format {
+                            imgui::Slider::new(im_str!("Quality##Painting"))
+                                .range(1..=100)
+                                .build(&ui, quality);
+                        }
                         let file_input =
                             ui.input_text(im_str!("Filename##Painting"), &mut painting_filename);
                         painting_filename_changed = file_input.build();
@@ -184,12 +338,82 @@ impl Dashboard {
                             .build();
                         ui.input_int(im_str!("Height##Movie"), _recording_height)
                             .build();
+                        *_recording_width = (*_recording_width).max(1);
+                        *_recording_height = (*_recording_height).max(1);
                         ui.input_int(im_str!("Framerate##Movie"), movie_framerate)
                             .build();
+                        *movie_framerate = (*movie_framerate).max(1);
+                        // Can't switch modes in the middle of a recording.
+                        if !*recording_in_progress {
+                            ui.checkbox(im_str!("Offline##Movie"), offline_recording);
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Step time by exactly one frame per frame, so slow shaders still record smoothly. The canvas runs slower or faster than real time while recording.");
+                            }
+                            if *offline_recording {
+                                ui.checkbox(im_str!("Seamless Loop##Movie"), seamless_loop);
+                                if ui.is_item_hovered() {
+                                    ui.tooltip_text("Record exactly one Duration of a shader that repeats with that period, starting at t = 0, so the last frame flows into the first. Drives an f32 uniform named loop_phase from 0 to 1, if the shader declares one.");
+                                }
+                            }
+                        }
+                        ui.input_float(im_str!("Duration##Movie"), movie_duration)
+                            .build();
+                        *movie_duration = movie_duration.max(0.0);
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text("Length of the movie or loop in seconds. 0 records until stopped.");
+                        }
+
+                        // The backend is fixed once the recorder is initialized.
+                        if recorder.is_none() {
+                            let backend_changed = imgui::ComboBox::new(im_str!("Output##Movie"))
+                                .build_simple(
+                                    &ui,
+                                    &mut recorder_backend_idx,
+                                    &RecorderBackend::ALL,
+                                    &|backend| ImString::new(backend.description()).into(),
+                                );
+                            if backend_changed {
+                                *recorder_backend = RecorderBackend::ALL[recorder_backend_idx];
+                            }
+                            match recorder_backend {
+                                RecorderBackend::Ffmpeg => {
+                                    imgui::ComboBox::new(im_str!("Encoder##Movie")).build_simple(
+                                        &ui,
+                                        encoder_profile_idx,
+                                        encoder_profiles,
+                                        &|profile| ImString::new(profile.description()).into(),
+                                    );
+                                }
+                                RecorderBackend::Animation(format) => {
+                                    if *format == AnimationFormat::Gif {
+                                        ui.checkbox(
+                                            im_str!("Dither##Movie"),
+                                            &mut animation_options.dither,
+                                        );
+                                        if ui.is_item_hovered() {
+                                            ui.tooltip_text("Hide banding from the 256 colour palette of each frame with error diffusion.");
+                                        }
+                                    }
+                                    ui.checkbox(
+                                        im_str!("Ping-Pong##Movie"),
+                                        &mut animation_options.ping_pong,
+                                    );
+                                    if ui.is_item_hovered() {
+                                        ui.tooltip_text("Play the loop forwards then backwards.");
+                                    }
+                                }
+                                RecorderBackend::ImageSequence(_) => {}
+                            }
+                        }
 
                         let file_input =
                             ui.input_text(im_str!("Filename##Movie"), &mut recording_filename);
                         recording_filename_changed = file_input.build();
+                        if ui.is_item_hovered() {
+                            if let RecorderBackend::ImageSequence(_) = recorder_backend {
+                                ui.tooltip_text("Directory to write the numbered frames to.");
+                            }
+                        }
                         if recorder.is_some() {
                             if *recording_in_progress {
                                 stop_record_button_pressed =
@@ -211,8 +435,244 @@ impl Dashboard {
                             .open_on_double_click(true)
                             .build(&ui)
                         {
-                            for (_name, uniform) in user_uniforms {
-                                uniforms::update_user_uniform_ui(&ui, uniform);
+                            let half_width = gui_width / 2.0 - 4.0;
+                            save_uniforms_pressed =
+                                ui.button(im_str!("Save to JSON##Uniforms"), [half_width, 25.0]);
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Write these values, keyframes and LFOs back into the uniforms file.");
+                            }
+                            ui.same_line(0.0);
+                            revert_uniforms_pressed =
+                                ui.button(im_str!("Revert##Uniforms"), [half_width, 25.0]);
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Discard changes by loading the uniforms file again.");
+                            }
+                            undo_pressed = ui.button(im_str!("Undo##Uniforms"), [half_width, 25.0])
+                                && uniform_history.can_undo();
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Undo the last uniform edit (Ctrl+Z).");
+                            }
+                            ui.same_line(0.0);
+                            redo_pressed = ui.button(im_str!("Redo##Uniforms"), [half_width, 25.0])
+                                && uniform_history.can_redo();
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Redo the last undone uniform edit (Ctrl+Shift+Z).");
+                            }
+                            // Ungrouped uniforms first, then each group in the order it first appears in the file.
+                            let mut groups: Vec<Option<String>> = Vec::new();
+                            for uniform in user_uniforms.iter() {
+                                if !groups.contains(&uniform.metadata.group) {
+                                    groups.push(uniform.metadata.group.clone());
+                                }
+                            }
+                            groups.sort_by_key(|group| group.is_some());
+                            for group in groups {
+                                let group_node = match &group {
+                                    Some(group) => {
+                                        let label = ImString::new(group);
+                                        match imgui::TreeNode::new(&label)
+                                            .default_open(true)
+                                            .push(&ui)
+                                        {
+                                            Some(node) => Some(node),
+                                            // Collapsed
+                                            None => continue,
+                                        }
+                                    }
+                                    None => None,
+                                };
+                                for uniform in user_uniforms
+                                    .iter_mut()
+                                    .filter(|uniform| uniform.metadata.group == group)
+                                {
+                                    let before = uniform.clone();
+                                    update_user_uniform_ui(&ui, uniform);
+                                    if uniform.bytes != before.bytes {
+                                        uniform_history.record(&before, uniform);
+                                        edited_uniforms.push(uniform.clone());
+                                    }
+                                    let mut lfo = lfos.get(&uniform.name).copied();
+                                    if update_lfo_ui(&ui, uniform, &mut lfo) {
+                                        lfos.insert(uniform.name.clone(), lfo.unwrap());
+                                        edited_lfos.push(uniform.name.clone());
+                                    }
+                                }
+                                if let Some(node) = group_node {
+                                    node.pop(&ui);
+                                }
+                            }
+                        }
+                        if imgui::CollapsingHeader::new(im_str!("Keyframes"))
+                            .default_open(false)
+                            .open_on_arrow(true)
+                            .open_on_double_click(true)
+                            .build(&ui)
+                        {
+                            ui.text_wrapped(im_str!(
+                                "Keyframed uniforms follow their curve and ignore edits above."
+                            ));
+                            // Only single numbers can be keyframed.
+                            for uniform in user_uniforms
+                                .iter()
+                                .filter(|uniform| uniform.inherent_type.is_scalar())
+                            {
+                                let name = &uniform.name;
+                                let mut track =
+                                    keyframe_tracks.get(name).cloned().unwrap_or_default();
+                                if update_keyframe_track_ui(
+                                    &ui,
+                                    uniform,
+                                    &mut track,
+                                    playback_time,
+                                    gui_width,
+                                ) {
+                                    match track.keyframes.is_empty() {
+                                        true => keyframe_tracks.remove(name),
+                                        false => keyframe_tracks.insert(name.clone(), track),
+                                    };
+                                    edited_tracks.push(name.clone());
+                                }
+                            }
+                        }
+                        if imgui::CollapsingHeader::new(im_str!("Presets"))
+                            .default_open(false)
+                            .open_on_arrow(true)
+                            .open_on_double_click(true)
+                            .build(&ui)
+                        {
+                            for (idx, preset) in presets.iter().enumerate() {
+                                let label = ImString::new(format!("{}##Preset{}", preset.name, idx));
+                                if imgui::Selectable::new(&label)
+                                    .size([gui_width - 60.0, 0.0])
+                                    .build(&ui)
+                                {
+                                    applied_preset_idx = Some(idx);
+                                }
+                                if ui.is_item_hovered() {
+                                    ui.tooltip_text(format!(
+                                        "Time: {:.2}s, Frame: {}, Mouse: ({}, {})",
+                                        preset.time, preset.frame, preset.mouse.x, preset.mouse.y
+                                    ));
+                                }
+                                ui.same_line(gui_width - 50.0);
+                                let delete_label = ImString::new(format!("Delete##Preset{}", idx));
+                                if ui.small_button(&delete_label) {
+                                    deleted_preset_idx = Some(idx);
+                                }
+                            }
+                            preset_name_changed = ui
+                                .input_text(im_str!("Name##Presets"), &mut preset_name)
+                                .build();
+                            save_preset_pressed = ui.button(
+                                im_str!("Save Current as Preset##Presets"),
+                                [gui_width, 25.0],
+                            );
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Snapshot the uniforms, time and mouse position, replacing any preset with the same name.");
+                            }
+                            if presets.len() >= 2 {
+                                ui.separator();
+                                imgui::ComboBox::new(im_str!("From##Morph")).build_simple(
+                                    &ui,
+                                    morph_from_idx,
+                                    presets,
+                                    &|preset| ImString::new(&preset.name).into(),
+                                );
+                                imgui::ComboBox::new(im_str!("To##Morph")).build_simple(
+                                    &ui,
+                                    morph_to_idx,
+                                    presets,
+                                    &|preset| ImString::new(&preset.name).into(),
+                                );
+                                morph_amount_changed = imgui::Slider::new(im_str!("Morph"))
+                                    .range(0.0..=1.0)
+                                    .build(&ui, morph_amount);
+                                ui.input_float(im_str!("Duration##Morph"), morph_duration)
+                                    .step(0.5)
+                                    .build();
+                                *morph_duration = morph_duration.max(0.0);
+                                match morph_in_progress {
+                                    true => ui.text("Morphing..."),
+                                    false => {
+                                        start_morph_pressed = ui.button(
+                                            im_str!("Morph Over Duration##Morph"),
+                                            [gui_width, 25.0],
+                                        )
+                                    }
+                                }
+                            }
+                        }
+                        if imgui::CollapsingHeader::new(im_str!("Explore"))
+                            .default_open(false)
+                            .open_on_arrow(true)
+                            .open_on_double_click(true)
+                            .build(&ui)
+                        {
+                            ui.text_wrapped(im_str!(
+                                "Only uniforms with a min and max, colours and drop-downs are changed."
+                            ));
+                            let half_width = gui_width / 2.0 - 4.0;
+                            mutate_pressed =
+                                ui.button(im_str!("Mutate##Explore"), [half_width, 25.0]);
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Nudge every uniform by up to the amount below.");
+                            }
+                            ui.same_line(0.0);
+                            randomise_pressed =
+                                ui.button(im_str!("Randomise##Explore"), [half_width, 25.0]);
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Pick every uniform anywhere in its range.");
+                            }
+                            imgui::Slider::new(im_str!("Amount##Explore"))
+                                .range(0.0..=1.0)
+                                .build(&ui, mutation_amount);
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Largest change Mutate makes, as a fraction of each range.");
+                            }
+                            exploration_seed_changed = ui
+                                .input_int(im_str!("Seed##Explore"), &mut exploration_seed)
+                                .build();
+                            ui.checkbox(im_str!("Lock Seed##Explore"), lock_exploration_seed);
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text("Reuse this seed rather than picking a new one, to reproduce a result.");
+                            }
+                            gallery_size_changed = ui
+                                .input_int(im_str!("Gallery Size##Explore"), gallery_size)
+                                .build();
+                            *gallery_size = (*gallery_size).max(1);
+                            // Newest first, wrapping onto as many rows as needed.
+                            let spacing = ui.clone_style().item_spacing[0];
+                            let per_row = ((gui_width + spacing) / (thumbnail_size[0] + spacing))
+                                .max(1.0) as usize;
+                            for (count, (idx, entry)) in gallery.iter().enumerate().rev().enumerate()
+                            {
+                                if count % per_row != 0 {
+                                    ui.same_line(0.0);
+                                }
+                                let clicked = match entry.thumbnail {
+                                    Some(texture_id) => {
+                                        imgui::ImageButton::new(texture_id, thumbnail_size)
+                                            .frame_padding(0)
+                                            .build(&ui)
+                                    }
+                                    None => {
+                                        let label = ImString::new(format!("...##Gallery{}", entry.id));
+                                        ui.button(&label, thumbnail_size)
+                                    }
+                                };
+                                if clicked {
+                                    applied_gallery_idx = Some(idx);
+                                }
+                                if ui.is_item_hovered() {
+                                    ui.tooltip_text(format!(
+                                        "{} with seed {}",
+                                        match entry.mutated {
+                                            true => "Mutated",
+                                            false => "Randomised",
+                                        },
+                                        entry.seed
+                                    ));
+                                }
                             }
                         }
                     }
@@ -240,7 +700,7 @@ impl Dashboard {
                         if *recording_in_progress {
                             ui.text_wrapped(im_str!("Processing frames..."));
                         } else {
-                            ui.text_wrapped(im_str!("Initializing FFMpeg..."));
+                            ui.text_wrapped(im_str!("Initializing recorder..."));
                         }
                     });
                     if init_recorder_button_pressed || stop_record_button_pressed {
@@ -254,6 +714,121 @@ impl Dashboard {
                     .send(DashboardMessage::PausePlayChanged)
                     .unwrap();
             }
+            // Frames are stepped and numbered at the movie framerate, so stepping matches recorded frames.
+            let seconds_per_frame = 1.0 / *movie_framerate as f32;
+            if let Some(time) = seek_time {
+                let frame_num = (time / seconds_per_frame).round().max(0.0) as u32;
+                self.transmitter
+                    .send(DashboardMessage::TimeSeekRequested(time, frame_num))
+                    .unwrap();
+            }
+            if frames_to_step != 0 {
+                self.transmitter
+                    .send(DashboardMessage::FrameStepRequested(
+                        frames_to_step,
+                        seconds_per_frame,
+                    ))
+                    .unwrap();
+            }
+            if playback_speed_changed {
+                self.transmitter
+                    .send(DashboardMessage::PlaybackSpeedUpdated(
+                        self.state.playback_speed,
+                    ))
+                    .unwrap();
+            }
+            for uniform in edited_uniforms {
+                self.transmitter
+                    .send(DashboardMessage::UniformUpdatedViaGUI(uniform))
+                    .unwrap();
+            }
+            if save_uniforms_pressed {
+                self.transmitter
+                    .send(DashboardMessage::UniformsSaveRequested)
+                    .unwrap();
+            }
+            if revert_uniforms_pressed {
+                self.transmitter
+                    .send(DashboardMessage::UniformsRevertRequested)
+                    .unwrap();
+            }
+            if preset_name_changed {
+                self.state.new_preset_name = String::from(preset_name.to_str());
+            }
+            if let Some(idx) = applied_preset_idx {
+                self.state.morph_start_time = None;
+                apply_preset(
+                    &self.transmitter,
+                    &mut self.state.uniform_history,
+                    &self.state.gui_uniforms,
+                    &self.state.presets[idx],
+                );
+            }
+            if save_preset_pressed && !self.state.new_preset_name.is_empty() {
+                let preset = Preset {
+                    name: self.state.new_preset_name.clone(),
+                    time: self.state.playback_time,
+                    frame: self.state.playback_frame,
+                    mouse: self.state.mouse_pos,
+                    uniforms: self.state.gui_uniforms.clone(),
+                };
+                match self
+                    .state
+                    .presets
+                    .iter_mut()
+                    .find(|existing| existing.name == preset.name)
+                {
+                    Some(existing) => *existing = preset,
+                    None => self.state.presets.push(preset),
+                }
+                save_presets(self.state.presets_file.as_deref(), &self.state.presets);
+            }
+            if let Some(idx) = deleted_preset_idx {
+                self.state.presets.remove(idx);
+                let last_idx = self.state.presets.len().saturating_sub(1);
+                self.state.morph_from_idx = self.state.morph_from_idx.min(last_idx);
+                self.state.morph_to_idx = self.state.morph_to_idx.min(last_idx);
+                save_presets(self.state.presets_file.as_deref(), &self.state.presets);
+            }
+            if morph_amount_changed {
+                self.state.morph_start_time = None;
+                let from = &self.state.presets[self.state.morph_from_idx];
+                let to = &self.state.presets[self.state.morph_to_idx];
+                apply_preset(
+                    &self.transmitter,
+                    &mut self.state.uniform_history,
+                    &self.state.gui_uniforms,
+                    &from.morph(to, self.state.morph_amount),
+                );
+            }
+            if exploration_seed_changed {
+                self.state.exploration_seed = exploration_seed as u32;
+            }
+            if start_morph_pressed {
+                self.state.morph_amount = 0.0;
+                self.state.morph_start_time = Some(std::time::Instant::now());
+            }
+            // Edits made while a widget is held, or over a timed morph, are undone together.
+            if !ui.is_any_item_active() && self.state.morph_start_time.is_none() {
+                self.state.uniform_history.commit();
+            }
+            for name in edited_lfos {
+                let lfo = self.state.lfos[&name];
+                self.transmitter
+                    .send(DashboardMessage::LfoUpdatedViaGUI(name, lfo))
+                    .unwrap();
+            }
+            for name in edited_tracks {
+                let track = self
+                    .state
+                    .keyframe_tracks
+                    .get(&name)
+                    .cloned()
+                    .unwrap_or_default();
+                self.transmitter
+                    .send(DashboardMessage::KeyframeTrackUpdatedViaGUI(name, track))
+                    .unwrap();
+            }
             if titlebar_button_pressed {
                 self.state.show_titlebar = !self.state.show_titlebar;
                 self.transmitter
@@ -263,32 +838,95 @@ impl Dashboard {
             if painting_filename_changed {
                 self.state.painting_filename = String::from(painting_filename.to_str());
             }
+            self.state.painting_format = painting_format;
             if create_painting_button_pressed {
                 if *pause_while_painting {
                     self.transmitter.send(DashboardMessage::Pause).unwrap();
                 }
-                self.transmitter
-                    .send(DashboardMessage::PaintingRenderRequested(UIntVector2::new(
-                        self.state.painting_resolution.x as u32,
-                        self.state.painting_resolution.y as u32,
-                    )))
-                    .unwrap();
+                let resolution = UIntVector2::new(
+                    self.state.painting_resolution.x as u32,
+                    self.state.painting_resolution.y as u32,
+                );
+                if *painting_tile_size > 0 {
+                    // Tiled paintings are streamed straight from the canvas to the writer,
+                    // so the canvas creates the writer and reports back with TiledPaintingStarted.
+                    let tile_size = *painting_tile_size as u32;
+                    let open_externally = match cfg!(target_os = "macos") {
+                        true => self.state.open_painting_externally,
+                        false => false,
+                    };
+                    let format = self.state.painting_format;
+                    self.transmitter
+                        .send(DashboardMessage::TiledPaintingRenderRequested(
+                            resolution,
+                            tile_size,
+                            format!("{}.{}", self.state.painting_filename, format.extension()),
+                            open_externally,
+                        ))
+                        .unwrap();
+                } else {
+                    self.transmitter
+                        .send(DashboardMessage::PaintingRenderRequested(resolution))
+                        .unwrap();
+                }
             }
             if recording_filename_changed {
                 self.state.recording_filename = String::from(recording_filename.to_str());
             }
             if init_recorder_button_pressed && self.recorder.is_none() {
-                self.recorder = Some(Recorder::new(
+                let backend = self.state.recorder_backend;
+                let profile = &self.state.encoder_profiles[self.state.encoder_profile_idx];
+                let filename = match backend {
+                    RecorderBackend::Ffmpeg => {
+                        format!("{}.{}", self.state.recording_filename, profile.container)
+                    }
+                    RecorderBackend::ImageSequence(_) => self.state.recording_filename.clone(),
+                    RecorderBackend::Animation(format) => {
+                        format!("{}.{}", self.state.recording_filename, format.extension())
+                    }
+                };
+                self.recorder = Some(Recorder::with_backend(
+                    backend,
+                    profile,
+                    self.state.animation_options,
                     self.state.recording_resolution.x as u32,
                     self.state.recording_resolution.y as u32,
-                    MOVIE_TEXTURE_FORMAT,
                     *movie_framerate as u32,
-                    format!("{}.mp4", self.state.recording_filename),
+                    filename,
                 ));
             }
-            if start_record_button_pressed {
+            let seamless_loop = self.state.offline_recording && self.state.seamless_loop;
+            if start_record_button_pressed
+                && seamless_loop
+                && self.state.movie_frame_count().is_none()
+            {
+                error!(
+                    "Set a Duration to the period of the loop before recording a seamless loop."
+                );
+            } else if start_record_button_pressed {
                 self.state.recording_in_progress = true;
+                self.movie_frames_requested = 0;
+                self.movie_frames_recorded = 0;
+                if self.state.offline_recording {
+                    self.transmitter
+                        .send(DashboardMessage::OfflineRecordingStarted(
+                            UIntVector2::new(
+                                self.state.recording_resolution.x as u32,
+                                self.state.recording_resolution.y as u32,
+                            ),
+                            self.recorder.as_ref().unwrap().frame_format,
+                            self.state.movie_framerate as u32,
+                            self.state.movie_frame_count(),
+                            seamless_loop,
+                        ))
+                        .unwrap();
+                }
             } else if stop_record_button_pressed {
+                if self.state.offline_recording {
+                    self.transmitter
+                        .send(DashboardMessage::OfflineRecordingStopped)
+                        .unwrap();
+                }
                 self.recorder.as_mut().unwrap().stop();
                 self.state.recording_in_progress = false;
             }
@@ -326,6 +964,22 @@ impl Dashboard {
         }
 
         self.queue.submit(Some(encoder.finish()));
+
+        if undo_pressed {
+            self.undo_uniform_edit();
+        }
+        if redo_pressed {
+            self.redo_uniform_edit();
+        }
+        if mutate_pressed || randomise_pressed {
+            self.explore_uniforms(mutate_pressed);
+        }
+        if let Some(idx) = applied_gallery_idx {
+            self.apply_gallery_entry(idx);
+        }
+        if gallery_size_changed {
+            self.trim_gallery();
+        }
     }
 
     /// Receives events from the winit event queue and responds appropriately.
@@ -345,6 +999,7 @@ impl Dashboard {
                 };
                 self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
             }
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers,
             WindowEvent::KeyboardInput { input, .. } => match input {
                 KeyboardInput {
                     state: ElementState::Pressed,
@@ -356,6 +1011,19 @@ impl Dashboard {
                         .send(DashboardMessage::PausePlayChanged)
                         .unwrap();
                 }
+                // Text fields have their own undo.
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Z),
+                    ..
+                } if (self.modifiers.ctrl() || self.modifiers.logo())
+                    && !self.imgui_context.io().want_text_input =>
+                {
+                    match self.modifiers.shift() {
+                        true => self.redo_uniform_edit(),
+                        false => self.undo_uniform_edit(),
+                    }
+                }
                 _ => (),
             },
             _ => {}
@@ -367,3 +1035,510 @@ impl Dashboard {
             .handle_event(self.imgui_context.io_mut(), &self.window, event);
     }
 }
+
+/// Builds the UI element for the given uniform and updates it with the latest value.
+/// Uses the widget, range, label, tooltip and step in the uniform's [UniformMetadata].
+///
+/// * `ui` - Reference to [imgui::Ui] object.
+/// * `uniform` - The [UserUniform] object to visualise and update.
+pub fn update_user_uniform_ui(ui: &imgui::Ui, uniform: &mut UserUniform) {
+    let display_name = uniform
+        .metadata
+        .label
+        .clone()
+        .unwrap_or_else(|| uniform.name.clone());
+    // Keep the uniform name in the ID, so uniforms can share a label.
+    let label = match &uniform.metadata.label {
+        Some(label) => ImString::new(format!("{}##{}", label, uniform.name)),
+        None => ImString::new(uniform.name.clone()),
+    };
+    let float_step = uniform.metadata.step.unwrap_or(0.0) as f32;
+    let int_step = uniform.metadata.step.map_or(1, |step| step as i32);
+    match uniform.metadata.widget {
+        UniformWidget::Input => match uniform.inherent_type {
+            // 64 bit floats are edited at 32 bit precision.
+            UserUniformType::Float32 | UserUniformType::Float64 => {
+                let mut value = uniform.as_f64() as f32;
+                if ui.input_float(&label, &mut value).step(float_step).build() {
+                    // Typed values may be out of range.
+                    uniform.set_from_f64(uniform.metadata.clamp(value as f64));
+                }
+            }
+            // Integers are edited as 32 bit signed values, unsigned values are clamped at zero.
+            UserUniformType::Int32
+            | UserUniformType::Int64
+            | UserUniformType::UInt32
+            | UserUniformType::UInt64 => {
+                let mut value = uniform.as_f64() as i32;
+                if ui.input_int(&label, &mut value).step(int_step).build() {
+                    uniform.set_from_f64(uniform.metadata.clamp(value as f64));
+                }
+            }
+            // Bool is a special case
+            UserUniformType::Bool => {
+                let value = uniform.get_value::<u32>().unwrap();
+                let mut value_bool = value != 0;
+                ui.checkbox(&label, &mut value_bool);
+                uniform.bytes = convert_value_to_bytes(value_bool as u32);
+            }
+            // Vectors
+            UserUniformType::Vec2 => {
+                let mut value = uniform.get_value::<[f32; 2]>().unwrap();
+                ui.input_float2(&label, &mut value).build();
+                uniform.bytes = convert_value_to_bytes(value);
+            }
+            UserUniformType::Vec3 => {
+                let mut value = uniform.get_value::<[f32; 3]>().unwrap();
+                ui.input_float3(&label, &mut value).build();
+                uniform.bytes = convert_value_to_bytes(value);
+            }
+            UserUniformType::Vec4 => {
+                let mut value = uniform.get_value::<[f32; 4]>().unwrap();
+                ui.input_float4(&label, &mut value).build();
+                uniform.bytes = convert_value_to_bytes(value);
+            }
+            UserUniformType::IVec2 => {
+                let mut value = uniform.get_value::<[i32; 2]>().unwrap();
+                ui.input_int2(&label, &mut value).build();
+                uniform.bytes = convert_value_to_bytes(value);
+            }
+            UserUniformType::IVec3 => {
+                let mut value = uniform.get_value::<[i32; 3]>().unwrap();
+                ui.input_int3(&label, &mut value).build();
+                uniform.bytes = convert_value_to_bytes(value);
+            }
+            UserUniformType::IVec4 => {
+                let mut value = uniform.get_value::<[i32; 4]>().unwrap();
+                ui.input_int4(&label, &mut value).build();
+                uniform.bytes = convert_value_to_bytes(value);
+            }
+            // Unsigned vectors are edited as signed and clamped at zero.
+            UserUniformType::UVec2 => {
+                let mut value = uniform.get_value::<[i32; 2]>().unwrap();
+                ui.input_int2(&label, &mut value).build();
+                uniform.bytes = value
+                    .iter()
+                    .flat_map(|v| convert_value_to_bytes((*v).max(0) as u32))
+                    .collect();
+            }
+            UserUniformType::UVec3 => {
+                let mut value = uniform.get_value::<[i32; 3]>().unwrap();
+                ui.input_int3(&label, &mut value).build();
+                uniform.bytes = value
+                    .iter()
+                    .flat_map(|v| convert_value_to_bytes((*v).max(0) as u32))
+                    .collect();
+            }
+            UserUniformType::UVec4 => {
+                let mut value = uniform.get_value::<[i32; 4]>().unwrap();
+                ui.input_int4(&label, &mut value).build();
+                uniform.bytes = value
+                    .iter()
+                    .flat_map(|v| convert_value_to_bytes((*v).max(0) as u32))
+                    .collect();
+            }
+            // Matrices are edited one column per row.
+            UserUniformType::Mat3 => {
+                let mut value = uniform.get_value::<[[f32; 3]; 3]>().unwrap();
+                for (i, column) in value.iter_mut().enumerate() {
+                    ui.input_float3(
+                        &ImString::new(format!("{}[{}]##{}", display_name, i, uniform.name)),
+                        column,
+                    )
+                    .build();
+                }
+                uniform.bytes = convert_value_to_bytes(value);
+            }
+            UserUniformType::Mat4 => {
+                let mut value = uniform.get_value::<[[f32; 4]; 4]>().unwrap();
+                for (i, column) in value.iter_mut().enumerate() {
+                    ui.input_float4(
+                        &ImString::new(format!("{}[{}]##{}", display_name, i, uniform.name)),
+                        column,
+                    )
+                    .build();
+                }
+                uniform.bytes = convert_value_to_bytes(value);
+            }
+            // Colours
+            UserUniformType::Color3 => {
+                let mut value = uniform.get_value::<[f32; 3]>().unwrap();
+                imgui::ColorEdit::new(&label, &mut value).build(ui);
+                uniform.bytes = convert_value_to_bytes(value);
+            }
+            UserUniformType::Color4 => {
+                let mut value = uniform.get_value::<[f32; 4]>().unwrap();
+                imgui::ColorEdit::new(&label, &mut value)
+                    .alpha_bar(true)
+                    .build(ui);
+                uniform.bytes = convert_value_to_bytes(value);
+            }
+        },
+        _ if uniform.inherent_type.is_scalar() => update_scalar_widget_ui(ui, &label, uniform),
+        _ => update_vector_widget_ui(ui, &label, uniform),
+    }
+    if let Some(tooltip) = &uniform.metadata.tooltip {
+        if ui.is_item_hovered() {
+            ui.tooltip_text(tooltip);
+        }
+    }
+}
+
+/// Private helper that builds the slider, drag, knob or combo chosen in the metadata of a single number uniform.
+fn update_scalar_widget_ui(ui: &imgui::Ui, label: &ImStr, uniform: &mut UserUniform) {
+    let metadata = &uniform.metadata;
+    let integer = !matches!(
+        uniform.inherent_type,
+        UserUniformType::Float32 | UserUniformType::Float64
+    );
+    let mut value = uniform.as_f64();
+    let edited = match metadata.widget {
+        UniformWidget::Slider => {
+            let (min, max) = metadata.range().unwrap();
+            match integer {
+                true => {
+                    let mut value_i32 = value as i32;
+                    let edited = imgui::Slider::new(label)
+                        .range(min as i32..=max as i32)
+                        .build(ui, &mut value_i32);
+                    value = metadata.snap(value_i32 as f64);
+                    edited
+                }
+                false => {
+                    let mut value_f32 = value as f32;
+                    let edited = imgui::Slider::new(label)
+                        .range(min as f32..=max as f32)
+                        .build(ui, &mut value_f32);
+                    value = metadata.snap(value_f32 as f64);
+                    edited
+                }
+            }
+        }
+        UniformWidget::Drag => match integer {
+            true => {
+                let mut value_i32 = value as i32;
+                let edited = imgui::Drag::new(label)
+                    .speed(metadata.step.unwrap_or(1.0) as f32)
+                    .build(ui, &mut value_i32);
+                value = value_i32 as f64;
+                edited
+            }
+            false => {
+                let mut value_f32 = value as f32;
+                let edited = imgui::Drag::new(label)
+                    .speed(metadata.step.map_or(FLOAT_DRAG_SPEED, |step| step as f32))
+                    .build(ui, &mut value_f32);
+                value = value_f32 as f64;
+                edited
+            }
+        },
+        UniformWidget::Knob => {
+            let (min, max) = metadata.range().unwrap();
+            let mut value_f32 = value as f32;
+            let edited = knob(ui, label, &mut value_f32, min as f32, max as f32);
+            value = metadata.snap(value_f32 as f64);
+            edited
+        }
+        UniformWidget::Combo => {
+            // Select the option nearest the value, in case it was set some other way.
+            let mut option_idx = 0;
+            for (i, option) in metadata.options.iter().enumerate() {
+                if (option.1 - value).abs() < (metadata.options[option_idx].1 - value).abs() {
+                    option_idx = i;
+                }
+            }
+            let edited = imgui::ComboBox::new(label).build_simple(
+                ui,
+                &mut option_idx,
+                &metadata.options,
+                &|option| ImString::new(option.0.clone()).into(),
+            );
+            value = metadata.options[option_idx].1;
+            edited
+        }
+        UniformWidget::Input => unreachable!(),
+    };
+    // Values typed into a slider or drag may be out of range.
+    let value = metadata.clamp(value);
+    if edited {
+        uniform.set_from_f64(value);
+    }
+}
+
+/// Private helper that builds a slider or drag for every component of a vector uniform, as chosen in its metadata.
+fn update_vector_widget_ui(ui: &imgui::Ui, label: &ImStr, uniform: &mut UserUniform) {
+    let metadata = &uniform.metadata;
+    match uniform.inherent_type {
+        UserUniformType::Vec2 | UserUniformType::Vec3 | UserUniformType::Vec4 => {
+            let mut values: Vec<f32> = uniform
+                .bytes
+                .chunks_exact(4)
+                .map(|bytes| convert_bytes_to_value::<f32>(bytes).unwrap())
+                .collect();
+            let edited = match metadata.range() {
+                Some((min, max)) if metadata.widget == UniformWidget::Slider => {
+                    imgui::Slider::new(label)
+                        .range(min as f32..=max as f32)
+                        .build_array(ui, &mut values)
+                }
+                _ => imgui::Drag::new(label)
+                    .speed(metadata.step.map_or(FLOAT_DRAG_SPEED, |step| step as f32))
+                    .build_array(ui, &mut values),
+            };
+            if edited {
+                uniform.bytes = values
+                    .iter()
+                    .flat_map(|v| convert_value_to_bytes(metadata.clamp(*v as f64) as f32))
+                    .collect();
+            }
+        }
+        _ => {
+            // Unsigned vectors are edited as signed and clamped at zero.
+            let unsigned = matches!(
+                uniform.inherent_type,
+                UserUniformType::UVec2 | UserUniformType::UVec3 | UserUniformType::UVec4
+            );
+            let mut values: Vec<i32> = uniform
+                .bytes
+                .chunks_exact(4)
+                .map(|bytes| convert_bytes_to_value::<i32>(bytes).unwrap())
+                .collect();
+            let edited = match metadata.range() {
+                Some((min, max)) if metadata.widget == UniformWidget::Slider => {
+                    imgui::Slider::new(label)
+                        .range(min as i32..=max as i32)
+                        .build_array(ui, &mut values)
+                }
+                _ => imgui::Drag::new(label)
+                    .speed(metadata.step.unwrap_or(1.0) as f32)
+                    .build_array(ui, &mut values),
+            };
+            if edited {
+                uniform.bytes = values
+                    .iter()
+                    .flat_map(|v| {
+                        let v = metadata.clamp(*v as f64) as i32;
+                        match unsigned {
+                            true => convert_value_to_bytes(v.max(0) as u32),
+                            false => convert_value_to_bytes(v),
+                        }
+                    })
+                    .collect();
+            }
+        }
+    }
+}
+
+/// Private helper that builds a rotary knob for a value from `min` to `max`, turned by dragging up and down.
+/// The label and value are shown to the right of the knob. Returns true if the value was changed.
+fn knob(ui: &imgui::Ui, label: &ImStr, value: &mut f32, min: f32, max: f32) -> bool {
+    let radius = ui.frame_height();
+    let corner = ui.cursor_screen_pos();
+    let center = [corner[0] + radius, corner[1] + radius];
+    ui.invisible_button(label, [2.0 * radius, 2.0 * radius]);
+    let mut edited = false;
+    if ui.is_item_active() {
+        let delta = -ui.io().mouse_delta[1];
+        if delta != 0.0 {
+            *value = (*value + delta * (max - min) / KNOB_DRAG_PIXELS).clamp(min, max);
+            edited = true;
+        }
+    }
+    // The knob sweeps 270 degrees clockwise, from the bottom left to the bottom right.
+    let progress = match max > min {
+        true => (*value - min) / (max - min),
+        false => 0.0,
+    };
+    let angle = (0.75 + 1.5 * progress) * std::f32::consts::PI;
+    let tip = [
+        center[0] + 0.8 * radius * angle.cos(),
+        center[1] + 0.8 * radius * angle.sin(),
+    ];
+    {
+        let draw_list = ui.get_window_draw_list();
+        let background = match ui.is_item_hovered() || ui.is_item_active() {
+            true => ui.style_color(StyleColor::FrameBgHovered),
+            false => ui.style_color(StyleColor::FrameBg),
+        };
+        draw_list
+            .add_circle(center, radius, background)
+            .filled(true)
+            .build();
+        draw_list
+            .add_line(center, tip, ui.style_color(StyleColor::SliderGrabActive))
+            .thickness(2.0)
+            .build();
+    }
+    ui.same_line(0.0);
+    let name = label.to_str().split("##").next().unwrap();
+    ui.text(format!("{}\n{:.3}", name, value));
+    edited
+}
+
+/// Builds a toggle for the LFO driving the given uniform and, while it is enabled, its settings.
+/// Returns true if the LFO was edited, in which case `lfo` is always Some.
+///
+/// * `ui` - Reference to [imgui::Ui] object.
+/// * `uniform` - The [UserUniform] the LFO drives. A new LFO oscillates around its current value.
+/// * `lfo` - The [Lfo] to visualise and edit, or None if the uniform has never had one.
+pub fn update_lfo_ui(ui: &imgui::Ui, uniform: &UserUniform, lfo: &mut Option<Lfo>) -> bool {
+    // Booleans aren't numeric and vectors have more than one value, so neither can be modulated.
+    if !uniform.inherent_type.is_scalar() || matches!(uniform.inherent_type, UserUniformType::Bool)
+    {
+        return false;
+    }
+    let mut edited = false;
+    let id = ui.push_id(uniform.name.as_str());
+    let mut enabled = matches!(lfo, Some(lfo) if lfo.enabled);
+    if ui.checkbox(im_str!("LFO"), &mut enabled) {
+        let lfo = lfo.get_or_insert_with(|| Lfo::new(&uniform.name, uniform.as_f64() as f32));
+        lfo.enabled = enabled;
+        edited = true;
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip_text(
+            "Drive this uniform with a low-frequency oscillator. Overrides any keyframes.",
+        );
+    }
+    if let Some(lfo) = lfo.as_mut().filter(|lfo| lfo.enabled) {
+        ui.indent();
+        let mut waveform_idx = Waveform::ALL
+            .iter()
+            .position(|waveform| *waveform == lfo.waveform)
+            .unwrap();
+        if imgui::ComboBox::new(im_str!("Waveform")).build_simple(
+            ui,
+            &mut waveform_idx,
+            &Waveform::ALL,
+            &|waveform| ImString::new(waveform.name()).into(),
+        ) {
+            lfo.waveform = Waveform::ALL[waveform_idx];
+            edited = true;
+        }
+        edited |= imgui::Slider::new(im_str!("Rate"))
+            .range(0.0..=10.0)
+            .display_format(im_str!("%.3f Hz"))
+            .build(ui, &mut lfo.rate);
+        edited |= ui.input_float(im_str!("Depth"), &mut lfo.depth).build();
+        edited |= ui.input_float(im_str!("Offset"), &mut lfo.offset).build();
+        edited |= imgui::Slider::new(im_str!("Phase"))
+            .range(0.0..=1.0)
+            .build(ui, &mut lfo.phase);
+        ui.unindent();
+    }
+    id.pop(ui);
+    edited
+}
+
+/// Builds a small curve editor for the keyframe track of the given uniform:
+/// a plot of the curve, the time, value and easing of each keyframe, and a button to add a keyframe.
+/// Returns true if the track was edited.
+///
+/// * `ui` - Reference to [imgui::Ui] object.
+/// * `uniform` - The [UserUniform] the track animates. New keyframes take its current value.
+/// * `track` - The [KeyframeTrack] to visualise and edit. Empty if the uniform is not animated.
+/// * `playhead` - Current time in seconds, where new keyframes are added.
+/// * `width` - Width of the editor, in pixels.
+pub fn update_keyframe_track_ui(
+    ui: &imgui::Ui,
+    uniform: &UserUniform,
+    track: &mut KeyframeTrack,
+    playhead: f32,
+    width: f32,
+) -> bool {
+    let mut edited = false;
+    let label = ImString::new(format!(
+        "{} ({} keyframes)",
+        uniform.name,
+        track.keyframes.len()
+    ));
+    let id = ui.push_id(uniform.name.as_str());
+    imgui::TreeNode::new(im_str!("Track"))
+        .label(&label)
+        .build(ui, || {
+            if let Some((start, end)) = track.time_range() {
+                let curve: Vec<f32> = (0..KEYFRAME_PLOT_SAMPLES)
+                    .map(|i| {
+                        let t = i as f32 / (KEYFRAME_PLOT_SAMPLES - 1) as f32;
+                        track.evaluate(start + (end - start) * t).unwrap() as f32
+                    })
+                    .collect();
+                let overlay = ImString::new(format!("{:.2} s - {:.2} s", start, end));
+                ui.plot_lines(im_str!("##Curve"), &curve)
+                    .overlay_text(&overlay)
+                    .graph_size([width, 60.0])
+                    .build();
+            }
+            let field_width = width * 0.3;
+            let mut removed = None;
+            for (i, keyframe) in track.keyframes.iter_mut().enumerate() {
+                let key_id = ui.push_id(i as i32);
+                ui.separator();
+                ui.set_next_item_width(field_width);
+                // Only commit times on Enter, so keyframes don't reorder while typing.
+                let mut time = keyframe.time;
+                if ui
+                    .input_float(im_str!("s##Time"), &mut time)
+                    .enter_returns_true(true)
+                    .build()
+                {
+                    keyframe.time = time;
+                    edited = true;
+                }
+                ui.same_line(0.0);
+                ui.set_next_item_width(field_width);
+                let mut value = keyframe.value as f32;
+                if ui.input_float(im_str!("##Value"), &mut value).build() {
+                    keyframe.value = value as f64;
+                    edited = true;
+                }
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Delete")) {
+                    removed = Some(i);
+                }
+                let mut easing_idx = Easing::ALL
+                    .iter()
+                    .position(|easing| discriminant(easing) == discriminant(&keyframe.easing))
+                    .unwrap();
+                ui.set_next_item_width(field_width);
+                if imgui::ComboBox::new(im_str!("Easing")).build_simple(
+                    ui,
+                    &mut easing_idx,
+                    &Easing::ALL,
+                    &|easing| ImString::new(easing.name()).into(),
+                ) {
+                    keyframe.easing = Easing::ALL[easing_idx];
+                    edited = true;
+                }
+                if let Easing::Bezier(points) = &mut keyframe.easing {
+                    if ui.input_float4(im_str!("Handles"), points).build() {
+                        edited = true;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(
+                            "Bezier control points x1, y1, x2, y2, as in CSS cubic-bezier().",
+                        );
+                    }
+                }
+                key_id.pop(ui);
+            }
+            if let Some(i) = removed {
+                track.keyframes.remove(i);
+                edited = true;
+            }
+            ui.separator();
+            if ui.button(im_str!("Add Keyframe at Playhead"), [width, 20.0]) {
+                track.insert(Keyframe {
+                    time: playhead,
+                    value: uniform.as_f64(),
+                    easing: Easing::Linear,
+                });
+                edited = true;
+            }
+        });
+    id.pop(ui);
+    if edited {
+        track.sort();
+    }
+    edited
+}
@@ -0,0 +1,253 @@
+use crate::recording::{HDR_MOVIE_TEXTURE_FORMAT, MOVIE_TEXTURE_FORMAT};
+use log::error;
+
+/// How an [EncoderProfile] controls the size and quality of the encoded movie.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RateControl {
+    /// Leave rate control to the codec, or to the profile's extra arguments.
+    Default,
+    /// Constant rate factor, passed as `-crf`. Lower is higher quality.
+    Crf(u32),
+    /// Target bitrate, passed as `-b:v`, such as `8M`.
+    Bitrate(String),
+}
+
+/// A named set of FFmpeg output options used to encode movie recordings.
+/// Frames are piped to FFmpeg as raw RGBA in the [crate::recording::FrameFormat] of the profile's
+/// [EncoderProfile::texture_format()]: 8 bits per component, or 16 bits with [EncoderProfile::high_bit_depth].
+/// The profile decides everything after that.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EncoderProfile {
+    /// Name used to select the profile on the command line and in the GUI.
+    pub name: String,
+    /// File extension of the movie, which also selects the container, such as `mp4`, `mov` or `mkv`.
+    pub container: String,
+    /// FFmpeg video encoder, passed as `-c:v`.
+    pub codec: String,
+    /// Pixel format of the encoded movie, passed as `-pix_fmt`.
+    pub pixel_format: String,
+    /// Quality or bitrate of the encoded movie.
+    pub rate_control: RateControl,
+    /// Any other FFmpeg output options, such as `-preset slow`, added after the options above.
+    pub extra_args: Vec<String>,
+    /// Pipe frames to FFmpeg as 16 bits per component from the half float painting pipeline, instead of 8 bits.
+    /// Only worthwhile with a pixel format of more than 8 bits, such as `yuv420p10le` or `yuva444p10le`.
+    pub high_bit_depth: bool,
+}
+
+/// Private helper to shorten the list of built-in profiles.
+fn profile(
+    name: &str,
+    container: &str,
+    codec: &str,
+    pixel_format: &str,
+    rate_control: RateControl,
+    extra_args: &[&str],
+    high_bit_depth: bool,
+) -> EncoderProfile {
+    EncoderProfile {
+        name: String::from(name),
+        container: String::from(container),
+        codec: String::from(codec),
+        pixel_format: String::from(pixel_format),
+        rate_control,
+        extra_args: extra_args.iter().map(|arg| String::from(*arg)).collect(),
+        high_bit_depth,
+    }
+}
+
+impl EncoderProfile {
+    /// The profiles available without a config file:
+    /// * `h264-web` - H.264 in MP4, plays nearly everywhere.
+    /// * `hevc-lossless` - Lossless x265 in MP4. Very large files.
+    /// * `hevc-main10` - 10-bit x265 in MP4, for smooth gradients in a deliverable file.
+    /// * `hevc-nvenc` - HEVC on NVIDIA GPUs in MP4.
+    /// * `prores4444` - 10-bit ProRes 4444 with alpha in MOV, for editing.
+    /// * `ffv1` - Lossless 16-bit FFV1 RGBA in MKV, for archival.
+    /// * `vp9` - VP9 in WebM.
+    pub fn builtin() -> Vec<EncoderProfile> {
+        vec![
+            profile(
+                "h264-web",
+                "mp4",
+                "libx264",
+                "yuv420p",
+                RateControl::Crf(18),
+                &["-preset", "slow", "-movflags", "+faststart"],
+                false,
+            ),
+            profile(
+                "hevc-lossless",
+                "mp4",
+                "libx265",
+                "yuv420p",
+                RateControl::Default,
+                &["-x265-params", "lossless=1"],
+                false,
+            ),
+            profile(
+                "hevc-main10",
+                "mp4",
+                "libx265",
+                "yuv420p10le",
+                RateControl::Crf(16),
+                &["-preset", "slow", "-tag:v", "hvc1"],
+                true,
+            ),
+            profile(
+                "hevc-nvenc",
+                "mp4",
+                "hevc_nvenc",
+                "yuv420p",
+                RateControl::Default,
+                &["-preset", "2"], // medium
+                false,
+            ),
+            profile(
+                "prores4444",
+                "mov",
+                "prores_ks",
+                "yuva444p10le",
+                RateControl::Default,
+                &["-profile:v", "4444", "-alpha_bits", "16", "-vendor", "apl0"],
+                true,
+            ),
+            profile(
+                "ffv1",
+                "mkv",
+                "ffv1",
+                "gbrap16le",
+                RateControl::Default,
+                &["-level", "3", "-g", "1", "-slicecrc", "1"],
+                true,
+            ),
+            profile(
+                "vp9",
+                "webm",
+                "libvpx-vp9",
+                "yuv420p",
+                RateControl::Crf(31),
+                // Constant quality mode in libvpx requires a bitrate of 0.
+                &["-b:v", "0", "-row-mt", "1"],
+                false,
+            ),
+        ]
+    }
+
+    /// Name of the profile used when none is chosen, matching what Easel has always recorded with.
+    /// NVENC on Windows, lossless x265 elsewhere.
+    pub fn default_name() -> &'static str {
+        match cfg!(target_os = "windows") {
+            true => "hevc-nvenc",
+            false => "hevc-lossless",
+        }
+    }
+
+    /// Read a profile from a config file entry. Only `codec` is required.
+    /// `container` defaults to `mp4`, `pixel format` to `yuv420p` and `high bit depth` to false.
+    /// Returns an error message if the entry is malformed.
+    /// * `name` - Name of the profile.
+    /// * `data` - JSON object describing the profile, see [load_encoder_profiles_from_json()].
+    pub fn from_json(name: &str, data: &json::JsonValue) -> Result<EncoderProfile, String> {
+        let codec = data["codec"]
+            .as_str()
+            .ok_or(format!("Encoder profile {} is missing a codec", name))?;
+        let rate_control = match (data["crf"].is_null(), data["bitrate"].is_null()) {
+            (true, true) => RateControl::Default,
+            (false, true) => RateControl::Crf(
+                data["crf"]
+                    .as_u32()
+                    .ok_or(format!("Encoder profile {} has an invalid crf", name))?,
+            ),
+            (true, false) => RateControl::Bitrate(String::from(
+                data["bitrate"]
+                    .as_str()
+                    .ok_or(format!("Encoder profile {} has an invalid bitrate", name))?,
+            )),
+            (false, false) => {
+                return Err(format!(
+                    "Encoder profile {} sets both crf and bitrate, choose one",
+                    name
+                ))
+            }
+        };
+        let mut extra_args = Vec::new();
+        for arg in data["args"].members() {
+            match arg.as_str() {
+                Some(arg) => extra_args.push(String::from(arg)),
+                None => return Err(format!("Encoder profile {} has a non-string arg", name)),
+            }
+        }
+        Ok(EncoderProfile {
+            name: String::from(name),
+            container: String::from(data["container"].as_str().unwrap_or("mp4")),
+            codec: String::from(codec),
+            pixel_format: String::from(data["pixel format"].as_str().unwrap_or("yuv420p")),
+            rate_control,
+            extra_args,
+            high_bit_depth: data["high bit depth"].as_bool().unwrap_or(false),
+        })
+    }
+
+    /// The texture format frames are rendered in for this profile,
+    /// [MOVIE_TEXTURE_FORMAT] or [HDR_MOVIE_TEXTURE_FORMAT] for [Self::high_bit_depth].
+    pub fn texture_format(&self) -> wgpu::TextureFormat {
+        match self.high_bit_depth {
+            true => HDR_MOVIE_TEXTURE_FORMAT,
+            false => MOVIE_TEXTURE_FORMAT,
+        }
+    }
+
+    /// The FFmpeg output options for this profile, everything between the input and the output file.
+    pub fn output_args(&self) -> Vec<String> {
+        let mut args = vec![String::from("-c:v"), self.codec.clone()];
+        match &self.rate_control {
+            RateControl::Default => {}
+            RateControl::Crf(crf) => {
+                args.extend_from_slice(&[String::from("-crf"), crf.to_string()])
+            }
+            RateControl::Bitrate(bitrate) => {
+                args.extend_from_slice(&[String::from("-b:v"), bitrate.clone()])
+            }
+        }
+        args.extend_from_slice(&[String::from("-pix_fmt"), self.pixel_format.clone()]);
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+
+    /// Human-readable description of the profile, as shown in the GUI, such as `h264-web (libx264, .mp4)`.
+    pub fn description(&self) -> String {
+        format!("{} ({}, .{})", self.name, self.codec, self.container)
+    }
+}
+
+/// Load encoder profiles from a parsed JSON config file and merge them with [EncoderProfile::builtin()].
+/// Profiles in the file replace built-in profiles of the same name. Malformed profiles are logged and skipped.
+/// Expected format:
+/// ```json
+/// {
+///     "profiles": {
+///         "review": {
+///             "container": "mp4",
+///             "codec": "libx264",
+///             "pixel format": "yuv420p",
+///             "bitrate": "12M",
+///             "args": ["-preset", "fast"],
+///             "high bit depth": false
+///         }
+///     }
+/// }
+/// ```
+pub fn load_encoder_profiles_from_json(data: &json::JsonValue) -> Vec<EncoderProfile> {
+    let mut profiles = EncoderProfile::builtin();
+    for (name, entry) in data["profiles"].entries() {
+        match EncoderProfile::from_json(name, entry) {
+            Ok(profile) => match profiles.iter_mut().find(|p| p.name == profile.name) {
+                Some(existing) => *existing = profile,
+                None => profiles.push(profile),
+            },
+            Err(e) => error!("{}", e),
+        }
+    }
+    profiles
+}
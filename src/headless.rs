@@ -0,0 +1,302 @@
+use easel_rs::keyframes;
+use easel_rs::lfo;
+use easel_rs::painting::{AsyncPaintingWriter, PaintingFormat};
+use easel_rs::recipe::{PaintingRecipe, ShaderSources};
+use easel_rs::renderer::Renderer;
+use easel_rs::uniforms::{self, UserUniform};
+use easel_rs::utils;
+use easel_rs::vector::{UIntVector2, Vector4};
+use futures::executor::block_on;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::sync::mpsc::TryRecvError;
+use std::{fs, path::Path};
+
+/// How long to sleep between device polls while waiting for a painting to be written.
+static POLL_INTERVAL_MS: u64 = 5;
+
+/// Private helper that parses the `format` and `quality` arguments.
+/// Returns None, after logging the valid names, if the format is unknown.
+fn parse_format(matches: &clap::ArgMatches) -> Option<PaintingFormat> {
+    let format_name = matches.value_of("format").unwrap();
+    let mut format = match PaintingFormat::from_name(format_name) {
+        Some(format) => format,
+        None => {
+            let names: Vec<&str> = PaintingFormat::ALL.iter().map(|f| f.name()).collect();
+            error!(
+                "Unknown painting format {}, must be one of: {}",
+                format_name,
+                names.join(", ")
+            );
+            return None;
+        }
+    };
+    if let PaintingFormat::Jpeg(quality) = &mut format {
+        *quality = matches
+            .value_of("quality")
+            .unwrap()
+            .parse::<u8>()
+            .expect("Invalid quality provided. Must be an integer from 1 to 100");
+    }
+    Some(format)
+}
+
+/// Private helper that parses the optional `tile-size` argument.
+fn parse_tile_size(matches: &clap::ArgMatches) -> Option<u32> {
+    matches.value_of("tile-size").map(|size| {
+        size.parse::<u32>()
+            .expect("Invalid tile size provided. Must be a positive integer")
+    })
+}
+
+/// Private helper that loads the shader, textures and post-processing shaders and builds a [Renderer] from them.
+/// Returns None, after logging why, if any file fails to load.
+fn create_renderer(
+    sources: &ShaderSources,
+    user_uniforms: Option<Vec<UserUniform>>,
+    resolution: UIntVector2,
+) -> Option<Renderer> {
+    let fs_spv_data = match utils::load_shader(
+        &sources.shader,
+        user_uniforms.as_deref().unwrap_or_default(),
+    ) {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Error compiling/loading shader: {}", e);
+            return None;
+        }
+    };
+
+    let mut images: Vec<image::DynamicImage> = Vec::new();
+    for a_file in &sources.textures {
+        match image::open(Path::new(a_file)) {
+            Ok(img) => images.push(img),
+            Err(error) => {
+                error!("Error loading image: {}", error);
+                return None;
+            }
+        }
+    }
+
+    let mut renderer = block_on(Renderer::new(
+        &fs_spv_data,
+        Some(images),
+        user_uniforms,
+        resolution,
+    ));
+    for shader in &sources.postprocess {
+        let spv_data = utils::load_shader(shader, &renderer.user_uniforms).unwrap();
+        renderer.add_post_processing_shader(spv_data);
+    }
+    Some(renderer)
+}
+
+/// Private helper that renders the painting described by `recipe` and waits for it to be written to `output`.
+/// * `tile_size` - Optionally render in tiles of this size, streaming each row of tiles to disk.
+fn write_painting(
+    renderer: &Renderer,
+    recipe: PaintingRecipe,
+    output: String,
+    tile_size: Option<u32>,
+) {
+    let resolution = recipe.resolution;
+    let srgb = !recipe.format.is_linear();
+    let start_time = std::time::Instant::now();
+    let progress = match tile_size {
+        Some(tile_size) => {
+            let tile_size = tile_size.max(1);
+            info!("Rendering in {}x{} tiles", tile_size, tile_size);
+            let (bands, progress) = AsyncPaintingWriter::write_bands(recipe, output, false);
+            renderer.render_painting_tiled(resolution, tile_size, srgb, |band| {
+                bands.send(band).expect("Painting writer hung up early.")
+            });
+            progress
+        }
+        None => {
+            let buffer = renderer.encode_painting(resolution, srgb);
+            AsyncPaintingWriter::write(buffer, recipe, output, false)
+        }
+    };
+    // The device must be polled for the buffer mapping in the writer to resolve.
+    loop {
+        renderer.device.poll(wgpu::Maintain::Poll);
+        match progress.try_recv() {
+            Ok(_) => break,
+            Err(TryRecvError::Empty) => {
+                std::thread::sleep(std::time::Duration::from_millis(POLL_INTERVAL_MS))
+            }
+            Err(TryRecvError::Disconnected) => {
+                error!("Painting writer hung up before finishing.");
+                return;
+            }
+        }
+    }
+    info!(
+        "Painting render + write took {} seconds",
+        start_time.elapsed().as_secs_f64()
+    );
+}
+
+/// Renders a single painting without opening any windows, then exits.
+/// Runs the same painting pipeline as the `Create` button in the Dashboard, including all
+/// post-processing operations and the final sRGB conversion.
+/// * `matches` - Arguments of the `render` subcommand.
+pub fn render_painting(matches: &clap::ArgMatches) {
+    let shader_file = matches
+        .value_of("shader")
+        .expect("Please provide a shader file.");
+    let width = matches
+        .value_of("width")
+        .unwrap()
+        .parse::<u32>()
+        .expect("Invalid width provided. Must be a positive integer");
+    let height = matches
+        .value_of("height")
+        .unwrap()
+        .parse::<u32>()
+        .expect("Invalid height provided. Must be a positive integer");
+    let time = matches
+        .value_of("time")
+        .unwrap()
+        .parse::<f32>()
+        .expect("Invalid time provided. Must be a number of seconds");
+    let format = match parse_format(matches) {
+        Some(format) => format,
+        None => return,
+    };
+    let output = match matches.value_of("output") {
+        Some(output) => String::from(output),
+        None => format!("Painting.{}", format.extension()),
+    };
+    let tile_size = parse_tile_size(matches);
+
+    let sources = ShaderSources {
+        shader: String::from(shader_file),
+        textures: matches
+            .values_of("textures")
+            .map(|files| files.map(String::from).collect())
+            .unwrap_or_default(),
+        postprocess: matches
+            .values_of("postprocess")
+            .map(|files| files.map(String::from).collect())
+            .unwrap_or_default(),
+    };
+
+    let mut custom_uniforms = None;
+    let mut keyframe_tracks = HashMap::new();
+    let mut lfos = HashMap::new();
+    if let Some(uniforms_file) = matches.value_of("uniforms") {
+        let text = fs::read_to_string(uniforms_file).expect("Error reading uniforms from file.");
+        let json_data = json::parse(&text).expect("Error parsing JSON.");
+        let cu = uniforms::load_uniforms_from_json(&json_data);
+        if !cu.is_empty() {
+            custom_uniforms = Some(cu);
+        }
+        keyframe_tracks = keyframes::load_keyframes_from_json(&json_data);
+        lfos = lfo::load_lfos_from_json(&json_data);
+    }
+
+    let resolution = UIntVector2::new(width, height);
+    let mut renderer = match create_renderer(&sources, custom_uniforms, resolution) {
+        Some(renderer) => renderer,
+        None => return,
+    };
+
+    renderer.uniforms.time = time;
+    keyframes::apply_keyframes(&keyframe_tracks, &mut renderer.user_uniforms, time);
+    lfo::apply_lfos(&lfos, &mut renderer.user_uniforms, time);
+    renderer.write_uniforms();
+    info!(
+        "Rendering {}x{} painting of {} at t = {}s",
+        width, height, shader_file, time
+    );
+    let recipe = PaintingRecipe::new(sources, &renderer, resolution, format);
+    write_painting(&renderer, recipe, output, tile_size);
+}
+
+/// Renders a painting again from its recipe at a new resolution, without opening any windows, then exits.
+/// The shader, textures, post-processing shaders and custom uniforms are reloaded from the recipe,
+/// and the time, frame, mouse and date uniforms are forced to their recorded values.
+/// * `matches` - Arguments of the `rerender` subcommand.
+pub fn rerender_painting(matches: &clap::ArgMatches) {
+    let recipe_file = matches
+        .value_of("recipe")
+        .expect("Please provide a painting recipe.");
+    let scale = matches
+        .value_of("scale")
+        .unwrap()
+        .parse::<f32>()
+        .expect("Invalid scale provided. Must be a positive number");
+    if scale <= 0.0 {
+        error!(
+            "Invalid scale {} provided. Must be a positive number",
+            scale
+        );
+        return;
+    }
+    let text = fs::read_to_string(recipe_file).expect("Error reading painting recipe from file.");
+    let json_data = json::parse(&text).expect("Error parsing JSON.");
+    let recipe = match PaintingRecipe::from_json(&json_data) {
+        Ok(recipe) => recipe,
+        Err(e) => {
+            error!("Invalid painting recipe {}: {}", recipe_file, e);
+            return;
+        }
+    };
+    let format = match matches.is_present("format") {
+        true => match parse_format(matches) {
+            Some(format) => format,
+            None => return,
+        },
+        false => recipe.format,
+    };
+    let output = match matches.value_of("output") {
+        Some(output) => String::from(output),
+        None => format!(
+            "{}-{}x.{}",
+            Path::new(recipe_file).with_extension("").display(),
+            scale,
+            format.extension()
+        ),
+    };
+    let tile_size = parse_tile_size(matches);
+    if !recipe.sources_unchanged() {
+        warn!("Shader files differ from the recipe, the painting may not match the original.");
+    }
+
+    let recorded_resolution = recipe.resolution;
+    let resolution = UIntVector2::new(
+        ((recorded_resolution.x as f32 * scale).round() as u32).max(1),
+        ((recorded_resolution.y as f32 * scale).round() as u32).max(1),
+    );
+    let user_uniforms = recipe.user_uniforms.clone();
+    let user_uniforms = match user_uniforms.is_empty() {
+        true => None,
+        false => Some(user_uniforms),
+    };
+    let mut renderer = match create_renderer(&recipe.sources, user_uniforms, resolution) {
+        Some(renderer) => renderer,
+        None => return,
+    };
+
+    // Restore the recorded uniforms at the recorded resolution.
+    // The painting pipeline then scales the mouse position up to the new resolution.
+    let num_textures = renderer.uniforms.num_textures;
+    renderer.uniforms = recipe.uniforms;
+    renderer.uniforms.resolution.x = recorded_resolution.x as f32;
+    renderer.uniforms.resolution.y = recorded_resolution.y as f32;
+    renderer.uniforms.num_textures = num_textures;
+    renderer.uniforms.tile_offset = Vector4::zero();
+    renderer.write_uniforms();
+    info!(
+        "Rendering {} again at {}x{}, {}x the recorded {}x{}",
+        recipe_file,
+        resolution.x,
+        resolution.y,
+        scale,
+        recorded_resolution.x,
+        recorded_resolution.y
+    );
+    let recipe = PaintingRecipe::new(recipe.sources, &renderer, resolution, format);
+    write_painting(&renderer, recipe, output, tile_size);
+}
@@ -0,0 +1,200 @@
+use crate::uniforms::UserUniform;
+
+/// A change to the value of one user uniform, keeping the value before and after.
+#[derive(Clone)]
+pub struct UniformEdit {
+    /// The uniform as it was before the change.
+    pub before: UserUniform,
+    /// The uniform as it was after the change.
+    pub after: UserUniform,
+}
+
+/// Undo and redo stacks of changes to user uniforms.
+/// Changes are recorded into a pending entry until [UniformHistory::commit()] is called, so every change made during
+/// one continuous drag becomes a single entry that is undone in one step.
+pub struct UniformHistory {
+    /// Entries that can be undone, most recent last.
+    undo_stack: Vec<Vec<UniformEdit>>,
+    /// Entries that were undone and can be redone, most recently undone last.
+    redo_stack: Vec<Vec<UniformEdit>>,
+    /// Changes recorded since the last commit.
+    pending: Vec<UniformEdit>,
+    /// Most entries kept on the undo stack. The oldest entries are dropped first.
+    capacity: usize,
+}
+
+impl UniformHistory {
+    /// Create an empty history.
+    /// * `capacity` - Most entries to keep on the undo stack.
+    pub fn new(capacity: usize) -> UniformHistory {
+        UniformHistory {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            pending: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Record a change to a uniform into the pending entry.
+    /// Repeated changes to the same uniform are merged, keeping the first value before and the last value after.
+    /// * `before` - The uniform before the change.
+    /// * `after` - The uniform after the change.
+    pub fn record(&mut self, before: &UserUniform, after: &UserUniform) {
+        match self
+            .pending
+            .iter_mut()
+            .find(|edit| edit.after.name == after.name)
+        {
+            Some(edit) => edit.after = after.clone(),
+            None => self.pending.push(UniformEdit {
+                before: before.clone(),
+                after: after.clone(),
+            }),
+        }
+    }
+
+    /// Close the pending entry and push it onto the undo stack, clearing the redo stack.
+    /// Does nothing if nothing was recorded, or every change was reverted before the commit.
+    pub fn commit(&mut self) {
+        let edits: Vec<UniformEdit> = self
+            .pending
+            .drain(..)
+            .filter(|edit| edit.before.bytes != edit.after.bytes)
+            .collect();
+        if edits.is_empty() {
+            return;
+        }
+        self.undo_stack.push(edits);
+        if self.undo_stack.len() > self.capacity {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    /// Undo the most recent entry, committing any pending changes first.
+    /// Returns the uniforms to restore, as they were before the entry, or None if there is nothing to undo.
+    pub fn undo(&mut self) -> Option<Vec<UserUniform>> {
+        self.commit();
+        let edits = self.undo_stack.pop()?;
+        let uniforms = edits.iter().map(|edit| edit.before.clone()).collect();
+        self.redo_stack.push(edits);
+        Some(uniforms)
+    }
+
+    /// Redo the most recently undone entry. Pending changes are committed first, which clears the redo stack.
+    /// Returns the uniforms to restore, as they were after the entry, or None if there is nothing to redo.
+    pub fn redo(&mut self) -> Option<Vec<UserUniform>> {
+        self.commit();
+        let edits = self.redo_stack.pop()?;
+        let uniforms = edits.iter().map(|edit| edit.after.clone()).collect();
+        self.undo_stack.push(edits);
+        Some(uniforms)
+    }
+
+    /// Whether there is an entry to undo, including pending changes.
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty() || !self.pending.is_empty()
+    }
+
+    /// Whether there is an undone entry to redo, and no pending changes that would replace it.
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+            && self
+                .pending
+                .iter()
+                .all(|edit| edit.before.bytes == edit.after.bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uniforms::{UniformMetadata, UserUniformType};
+
+    fn uniform(name: &str, value: f64) -> UserUniform {
+        let mut uniform = UserUniform {
+            bytes: Vec::new(),
+            name: String::from(name),
+            inherent_type: UserUniformType::Float32,
+            metadata: UniformMetadata::default(),
+        };
+        uniform.set_from_f64(value);
+        uniform
+    }
+
+    fn values(uniforms: Option<Vec<UserUniform>>) -> Vec<(String, f64)> {
+        uniforms
+            .unwrap()
+            .iter()
+            .map(|uniform| (uniform.name.clone(), uniform.as_f64()))
+            .collect()
+    }
+
+    #[test]
+    fn edits_to_one_uniform_merge_into_one_entry() {
+        let mut history = UniformHistory::new(10);
+        history.record(&uniform("zoom", 1.0), &uniform("zoom", 2.0));
+        history.record(&uniform("zoom", 2.0), &uniform("zoom", 3.0));
+        history.record(&uniform("gain", 0.0), &uniform("gain", 0.5));
+        history.commit();
+        assert_eq!(
+            values(history.undo()),
+            vec![(String::from("zoom"), 1.0), (String::from("gain"), 0.0)]
+        );
+        assert!(!history.can_undo());
+        assert_eq!(
+            values(history.redo()),
+            vec![(String::from("zoom"), 3.0), (String::from("gain"), 0.5)]
+        );
+    }
+
+    #[test]
+    fn reverted_edits_are_dropped() {
+        let mut history = UniformHistory::new(10);
+        history.record(&uniform("zoom", 1.0), &uniform("zoom", 2.0));
+        history.record(&uniform("zoom", 2.0), &uniform("zoom", 1.0));
+        history.commit();
+        assert!(!history.can_undo());
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted_at_capacity() {
+        let mut history = UniformHistory::new(2);
+        for value in 1..=3 {
+            history.record(
+                &uniform("zoom", value as f64 - 1.0),
+                &uniform("zoom", value as f64),
+            );
+            history.commit();
+        }
+        assert_eq!(values(history.undo()), vec![(String::from("zoom"), 2.0)]);
+        assert_eq!(values(history.undo()), vec![(String::from("zoom"), 1.0)]);
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn new_commit_clears_redo() {
+        let mut history = UniformHistory::new(10);
+        history.record(&uniform("zoom", 1.0), &uniform("zoom", 2.0));
+        history.commit();
+        history.undo();
+        assert!(history.can_redo());
+        history.record(&uniform("zoom", 1.0), &uniform("zoom", 4.0));
+        // Pending changes would replace the undone entry.
+        assert!(!history.can_redo());
+        history.commit();
+        assert!(!history.can_redo());
+        assert!(history.redo().is_none());
+        assert_eq!(values(history.undo()), vec![(String::from("zoom"), 1.0)]);
+    }
+
+    #[test]
+    fn undo_commits_pending_edits() {
+        let mut history = UniformHistory::new(10);
+        history.record(&uniform("zoom", 1.0), &uniform("zoom", 2.0));
+        assert!(history.can_undo());
+        assert_eq!(values(history.undo()), vec![(String::from("zoom"), 1.0)]);
+        assert_eq!(values(history.redo()), vec![(String::from("zoom"), 2.0)]);
+    }
+}
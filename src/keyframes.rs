@@ -0,0 +1,246 @@
+use crate::uniforms::UserUniform;
+use crate::utils::f32_to_json;
+use log::error;
+use std::collections::HashMap;
+
+/// Number of bisection steps used to invert the x curve of a [Easing::Bezier]. Enough for f32 precision.
+static BEZIER_SOLVER_ITERATIONS: u32 = 24;
+
+/// How the value of a [Keyframe] eases into the value of the keyframe after it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    /// Hold the value until the next keyframe, then jump.
+    Step,
+    /// Interpolate linearly.
+    Linear,
+    /// Ease in and out along a cubic, slow at both keyframes and fastest in between.
+    Cubic,
+    /// Ease along a cubic Bézier curve from (0, 0) to (1, 1), given its two control points as `[x1, y1, x2, y2]`,
+    /// the same as CSS `cubic-bezier()`. The x coordinates are clamped to [0, 1].
+    Bezier([f32; 4]),
+}
+
+impl Easing {
+    /// Every kind of easing, in the order shown in the GUI. The Bézier entry has the control points of [Self::Cubic].
+    pub const ALL: [Easing; 4] = [
+        Easing::Step,
+        Easing::Linear,
+        Easing::Cubic,
+        Easing::Bezier([0.42, 0.0, 0.58, 1.0]),
+    ];
+
+    /// Short name used in the uniforms file and the GUI.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Easing::Step => "step",
+            Easing::Linear => "linear",
+            Easing::Cubic => "cubic",
+            Easing::Bezier(_) => "bezier",
+        }
+    }
+
+    /// Eased progress through a segment.
+    /// * `t` - Linear progress through the segment, from 0 to 1.
+    pub fn apply(&self, t: f64) -> f64 {
+        match self {
+            Easing::Step => 0.0,
+            Easing::Linear => t,
+            Easing::Cubic => t * t * (3.0 - 2.0 * t),
+            Easing::Bezier([x1, y1, x2, y2]) => {
+                let x1 = (*x1 as f64).clamp(0.0, 1.0);
+                let x2 = (*x2 as f64).clamp(0.0, 1.0);
+                let bezier = |p1: f64, p2: f64, s: f64| {
+                    let inv = 1.0 - s;
+                    3.0 * inv * inv * s * p1 + 3.0 * inv * s * s * p2 + s * s * s
+                };
+                // With x1 and x2 in [0, 1] the x curve is monotonic, so bisect for the parameter at x = t.
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..BEZIER_SOLVER_ITERATIONS {
+                    let mid = 0.5 * (low + high);
+                    if bezier(x1, x2, mid) < t {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                bezier(*y1 as f64, *y2 as f64, 0.5 * (low + high))
+            }
+        }
+    }
+
+    /// Read an easing in the form written by [Self::to_json()]: a name, or an array of 4 Bézier control point coordinates.
+    pub fn from_json(data: &json::JsonValue) -> Option<Easing> {
+        if data.is_array() {
+            let mut points = [0.0; 4];
+            if data.len() != points.len() {
+                return None;
+            }
+            for (point, value) in points.iter_mut().zip(data.members()) {
+                *point = value.as_f32()?;
+            }
+            return Some(Easing::Bezier(points));
+        }
+        match data.as_str()? {
+            "step" => Some(Easing::Step),
+            "linear" => Some(Easing::Linear),
+            "cubic" => Some(Easing::Cubic),
+            _ => None,
+        }
+    }
+
+    /// The easing as stored in the uniforms file, see [load_keyframes_from_json()].
+    pub fn to_json(&self) -> json::JsonValue {
+        match self {
+            Easing::Bezier(points) => points
+                .iter()
+                .copied()
+                .map(f32_to_json)
+                .collect::<Vec<_>>()
+                .into(),
+            _ => self.name().into(),
+        }
+    }
+}
+
+/// The value of a uniform at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    /// Time in seconds, matching `u_time`.
+    pub time: f32,
+    /// Value of the uniform, converted to the uniform's own type when applied.
+    pub value: f64,
+    /// How the value eases into the next keyframe. Unused on the last keyframe.
+    pub easing: Easing,
+}
+
+/// Keyframes animating a single user uniform over time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyframeTrack {
+    /// Keyframes sorted by time. Call [Self::sort()] after changing any times.
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl KeyframeTrack {
+    /// Sort the keyframes by time. Keyframes at the same time keep their order.
+    pub fn sort(&mut self) {
+        self.keyframes
+            .sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+    }
+
+    /// Add a keyframe, replacing any keyframe already at the same time.
+    pub fn insert(&mut self, keyframe: Keyframe) {
+        match self.keyframes.iter_mut().find(|k| k.time == keyframe.time) {
+            Some(existing) => *existing = keyframe,
+            None => {
+                self.keyframes.push(keyframe);
+                self.sort();
+            }
+        }
+    }
+
+    /// Value of the track at a point in time, or None if it has no keyframes.
+    /// Before the first keyframe and after the last, the value holds at that keyframe's value.
+    /// * `time` - Time in seconds, matching `u_time`.
+    pub fn evaluate(&self, time: f32) -> Option<f64> {
+        let first = self.keyframes.first()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+        // The first keyframe after `time`, so the segment runs from the keyframe before it.
+        let next_idx = match self.keyframes.iter().position(|k| k.time > time) {
+            Some(idx) => idx,
+            None => return Some(self.keyframes.last().unwrap().value),
+        };
+        let from = &self.keyframes[next_idx - 1];
+        let to = &self.keyframes[next_idx];
+        let t = ((time - from.time) / (to.time - from.time)) as f64;
+        Some(from.value + (to.value - from.value) * from.easing.apply(t))
+    }
+
+    /// Time of the first and last keyframes, or None if the track has no keyframes.
+    pub fn time_range(&self) -> Option<(f32, f32)> {
+        Some((self.keyframes.first()?.time, self.keyframes.last()?.time))
+    }
+
+    /// The track as stored in the uniforms file, see [load_keyframes_from_json()].
+    pub fn to_json(&self) -> json::JsonValue {
+        let mut keyframes = json::JsonValue::new_array();
+        for keyframe in &self.keyframes {
+            let mut entry = json::array![f32_to_json(keyframe.time), keyframe.value];
+            if keyframe.easing != Easing::Linear {
+                entry.push(keyframe.easing.to_json()).unwrap();
+            }
+            keyframes.push(entry).unwrap();
+        }
+        keyframes
+    }
+}
+
+/// Loads keyframe tracks for user uniforms from the same JSON file as the uniforms themselves.
+/// Each track is named after the uniform it animates, and is an array of `[time, value]` or `[time, value, easing]` keyframes.
+/// The easing applies from that keyframe to the next one and is one of `"step"`, `"linear"` (the default), `"cubic"`,
+/// or the control points of a cubic Bézier curve as `[x1, y1, x2, y2]`. Malformed keyframes are logged and skipped.
+/// Example valid format:
+/// ```text
+/// "uniforms": {
+///     "zoom": ["f32", 1.0]
+/// },
+/// "keyframes": {
+///     "zoom": [[0.0, 1.0, "cubic"], [4.0, 8.0, [0.7, 0.0, 0.3, 1.0]], [10.0, 2.0]]
+/// }
+/// ```
+/// Returns the tracks by uniform name, with their keyframes sorted by time.
+pub fn load_keyframes_from_json(data: &json::JsonValue) -> HashMap<String, KeyframeTrack> {
+    let mut tracks = HashMap::new();
+    for (name, entries) in data["keyframes"].entries() {
+        let mut track = KeyframeTrack::default();
+        for entry in entries.members() {
+            let easing = match entry[2].is_null() {
+                true => Some(Easing::Linear),
+                false => Easing::from_json(&entry[2]),
+            };
+            match (entry[0].as_f32(), entry[1].as_f64(), easing) {
+                (Some(time), Some(value), Some(easing)) => track.keyframes.push(Keyframe {
+                    time,
+                    value,
+                    easing,
+                }),
+                _ => error!("Invalid keyframe {} for uniform {}, ignoring.", entry, name),
+            }
+        }
+        track.sort();
+        if !track.keyframes.is_empty() {
+            tracks.insert(String::from(name), track);
+        }
+    }
+    tracks
+}
+
+/// Describe keyframe tracks in the form read by [load_keyframes_from_json()], to store under `"keyframes"`.
+/// Tracks are sorted by uniform name, so saving the same tracks twice writes the same file.
+pub fn keyframes_to_json(tracks: &HashMap<String, KeyframeTrack>) -> json::JsonValue {
+    let mut data = json::JsonValue::new_object();
+    let mut names: Vec<&String> = tracks.keys().collect();
+    names.sort();
+    for name in names {
+        data[name.as_str()] = tracks[name].to_json();
+    }
+    data
+}
+
+/// Set every user uniform that has a keyframe track to the value of its track at a point in time.
+/// Tracks without a matching uniform are ignored.
+/// * `tracks` - Keyframe tracks by uniform name.
+/// * `user_uniforms` - The uniforms to animate.
+/// * `time` - Time in seconds, matching `u_time`.
+pub fn apply_keyframes(
+    tracks: &HashMap<String, KeyframeTrack>,
+    user_uniforms: &mut [UserUniform],
+    time: f32,
+) {
+    for uniform in user_uniforms {
+        if let Some(value) = tracks.get(&uniform.name).and_then(|t| t.evaluate(time)) {
+            uniform.set_from_f64(value);
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::uniforms::UserUniform;
+use crate::utils::f32_to_json;
+use log::error;
+use std::collections::HashMap;
+
+/// Shape of the wave an [Lfo] oscillates with. Every waveform runs from -1 to 1 over one cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Waveform {
+    /// Starts at 0 and rises first.
+    Sine,
+    /// Starts at 0 and rises first, like [Self::Sine] but with straight ramps.
+    Triangle,
+    /// 1 for the first half of each cycle, -1 for the second.
+    Square,
+    /// Ramps from -1 up to 1 over each cycle, then jumps back down.
+    Saw,
+    /// Jumps to a new random value at the start of each cycle and holds it, like a sample & hold.
+    RandomHold,
+    /// Glides smoothly between a new random value at the start of each cycle.
+    SmoothNoise,
+}
+
+impl Waveform {
+    /// Every waveform, in the order shown in the GUI.
+    pub const ALL: [Waveform; 6] = [
+        Waveform::Sine,
+        Waveform::Triangle,
+        Waveform::Square,
+        Waveform::Saw,
+        Waveform::RandomHold,
+        Waveform::SmoothNoise,
+    ];
+
+    /// Short name used in the uniforms file and the GUI.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Waveform::Sine => "sine",
+            Waveform::Triangle => "triangle",
+            Waveform::Square => "square",
+            Waveform::Saw => "saw",
+            Waveform::RandomHold => "random hold",
+            Waveform::SmoothNoise => "smooth noise",
+        }
+    }
+
+    /// Look up a waveform by its [Self::name()].
+    pub fn from_name(name: &str) -> Option<Waveform> {
+        Waveform::ALL.iter().copied().find(|w| w.name() == name)
+    }
+
+    /// Value of the wave, from -1 to 1.
+    /// * `cycles` - Position along the wave, in cycles. Each whole number starts a new cycle.
+    /// * `seed` - Picks the sequence of values for the random waveforms. Unused by the others.
+    pub fn sample(&self, cycles: f64, seed: u32) -> f64 {
+        let cycle = cycles.floor();
+        let p = cycles - cycle;
+        match self {
+            Waveform::Sine => (p * std::f64::consts::TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * ((p + 0.25).fract() - 0.5).abs(),
+            Waveform::Square => match p < 0.5 {
+                true => 1.0,
+                false => -1.0,
+            },
+            Waveform::Saw => 2.0 * p - 1.0,
+            Waveform::RandomHold => random_value(seed, cycle as i64),
+            Waveform::SmoothNoise => {
+                let from = random_value(seed, cycle as i64);
+                let to = random_value(seed, cycle as i64 + 1);
+                from + (to - from) * p * p * (3.0 - 2.0 * p)
+            }
+        }
+    }
+}
+
+/// Returns a repeatable random value from -1 to 1 for a cycle of a random waveform, or any other numbered draw.
+/// Uses the SplitMix64 finaliser, so neighbouring cycles and seeds are uncorrelated.
+pub(crate) fn random_value(seed: u32, cycle: i64) -> f64 {
+    let mut z = (cycle as u64 ^ ((seed as u64) << 32)).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    // Top 53 bits as a float in [0, 1).
+    (z >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+}
+
+/// Derives a default seed from a uniform name, so uniforms with random LFOs don't move in lockstep.
+/// 32-bit FNV-1a.
+pub(crate) fn seed_from_name(name: &str) -> u32 {
+    name.bytes().fold(0x811C_9DC5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// A low-frequency oscillator driving the value of a user uniform over time.
+/// The uniform is set to `offset + depth * wave`, where the wave runs from -1 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lfo {
+    /// Shape of the wave.
+    pub waveform: Waveform,
+    /// Cycles per second of `u_time`.
+    pub rate: f32,
+    /// Half the distance between the lowest and highest values.
+    pub depth: f32,
+    /// Value in the middle of the wave.
+    pub offset: f32,
+    /// Position in the cycle at `u_time = 0`, from 0 to 1.
+    pub phase: f32,
+    /// Picks the sequence of values for [Waveform::RandomHold] and [Waveform::SmoothNoise].
+    pub seed: u32,
+    /// Whether the LFO drives the uniform. Disabled LFOs keep their settings but leave the uniform alone.
+    pub enabled: bool,
+}
+
+impl Lfo {
+    /// A one cycle per second sine wave of depth 1 around `offset`, seeded from the name of the uniform it drives.
+    pub fn new(name: &str, offset: f32) -> Lfo {
+        Lfo {
+            waveform: Waveform::Sine,
+            rate: 1.0,
+            depth: 1.0,
+            offset,
+            phase: 0.0,
+            seed: seed_from_name(name),
+            enabled: true,
+        }
+    }
+
+    /// Value of the LFO at a point in time.
+    /// * `time` - Time in seconds, matching `u_time`.
+    pub fn evaluate(&self, time: f32) -> f64 {
+        let cycles = time as f64 * self.rate as f64 + self.phase as f64;
+        self.offset as f64 + self.depth as f64 * self.waveform.sample(cycles, self.seed)
+    }
+
+    /// Read an LFO from a uniforms file entry, see [load_lfos_from_json()].
+    /// Returns an error message if the entry is malformed.
+    /// * `name` - Name of the uniform the LFO drives.
+    /// * `data` - JSON object describing the LFO.
+    pub fn from_json(name: &str, data: &json::JsonValue) -> Result<Lfo, String> {
+        let mut lfo = Lfo::new(name, 0.0);
+        if let Some(waveform) = data["waveform"].as_str() {
+            lfo.waveform = Waveform::from_name(waveform).ok_or(format!(
+                "LFO for {} has unknown waveform {}",
+                name, waveform
+            ))?;
+        }
+        let float = |key: &str, default: f32| match &data[key] {
+            json::JsonValue::Null => Ok(default),
+            value => value
+                .as_f32()
+                .ok_or(format!("LFO for {} has an invalid {}", name, key)),
+        };
+        lfo.rate = float("rate", lfo.rate)?;
+        lfo.depth = float("depth", lfo.depth)?;
+        lfo.offset = float("offset", lfo.offset)?;
+        lfo.phase = float("phase", lfo.phase)?;
+        lfo.seed = data["seed"].as_u32().unwrap_or(lfo.seed);
+        lfo.enabled = data["enabled"].as_bool().unwrap_or(true);
+        Ok(lfo)
+    }
+
+    /// The LFO as stored in the uniforms file, see [load_lfos_from_json()].
+    pub fn to_json(&self) -> json::JsonValue {
+        json::object! {
+            "waveform": self.waveform.name(),
+            "rate": f32_to_json(self.rate),
+            "depth": f32_to_json(self.depth),
+            "offset": f32_to_json(self.offset),
+            "phase": f32_to_json(self.phase),
+            "seed": self.seed,
+            "enabled": self.enabled,
+        }
+    }
+}
+
+/// Loads LFOs for user uniforms from the same JSON file as the uniforms themselves.
+/// Each LFO is named after the uniform it drives. Every setting is optional:
+/// `waveform` is one of `"sine"` (the default), `"triangle"`, `"square"`, `"saw"`, `"random hold"` or `"smooth noise"`,
+/// `rate` is in cycles per second (default 1), `depth` defaults to 1, `offset` to 0 and `phase` to 0.
+/// `seed` picks the random sequence, and defaults to one derived from the uniform name. Malformed LFOs are logged and skipped.
+/// Example valid format:
+/// ```text
+/// "uniforms": {
+///     "hue": ["f32", 0.5]
+/// },
+/// "lfos": {
+///     "hue": { "waveform": "smooth noise", "rate": 0.25, "depth": 0.5, "offset": 0.5, "enabled": true }
+/// }
+/// ```
+/// Returns the LFOs by uniform name.
+pub fn load_lfos_from_json(data: &json::JsonValue) -> HashMap<String, Lfo> {
+    let mut lfos = HashMap::new();
+    for (name, entry) in data["lfos"].entries() {
+        match Lfo::from_json(name, entry) {
+            Ok(lfo) => {
+                lfos.insert(String::from(name), lfo);
+            }
+            Err(e) => error!("{}, ignoring.", e),
+        }
+    }
+    lfos
+}
+
+/// Describe LFOs in the form read by [load_lfos_from_json()], to store under `"lfos"`.
+/// LFOs are sorted by uniform name, so saving the same LFOs twice writes the same file.
+pub fn lfos_to_json(lfos: &HashMap<String, Lfo>) -> json::JsonValue {
+    let mut data = json::JsonValue::new_object();
+    let mut names: Vec<&String> = lfos.keys().collect();
+    names.sort();
+    for name in names {
+        data[name.as_str()] = lfos[name].to_json();
+    }
+    data
+}
+
+/// Set every user uniform that has an enabled LFO to the value of its LFO at a point in time.
+/// LFOs without a matching uniform are ignored.
+/// * `lfos` - LFOs by uniform name.
+/// * `user_uniforms` - The uniforms to drive.
+/// * `time` - Time in seconds, matching `u_time`.
+pub fn apply_lfos(lfos: &HashMap<String, Lfo>, user_uniforms: &mut [UserUniform], time: f32) {
+    for uniform in user_uniforms {
+        if let Some(lfo) = lfos.get(&uniform.name).filter(|lfo| lfo.enabled) {
+            uniform.set_from_f64(lfo.evaluate(time));
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! # Easel Library
+//! The shader-to-image pipeline behind the `easel` program, usable from other tools.
+//!
+//! The central type is [renderer::Renderer], which compiles a fragment shader into render, painting and movie pipelines,
+//! binds input textures and [uniforms::UserUniform]s, runs any post-processing effects, and returns pixel buffers.
+//! It does not depend on a window or GUI, so it runs on machines without a display.
+//!
+//! ```text
+//! let spirv = easel_rs::utils::load_shader("shader.frag", &[]).unwrap();
+//! let resolution = UIntVector2::new(4096, 4096);
+//! let mut renderer = block_on(Renderer::new(&spirv, None, None, resolution));
+//! renderer.uniforms.time = 12.5;
+//! renderer.write_uniforms();
+//! let pixels = renderer.render_painting(resolution);
+//! ```
+//!
+//! Shaders must follow the same binding conventions as for the `easel` program; see its documentation for details.
+
+pub mod animation;
+pub mod encoder;
+pub mod history;
+pub mod keyframes;
+pub mod lfo;
+pub mod mutation;
+pub mod painting;
+pub mod postprocessing;
+pub mod presets;
+pub mod recipe;
+// pub mod push_constants;
+pub mod recording;
+pub mod renderer;
+pub mod skeletons;
+pub mod texture;
+pub mod uniforms;
+pub mod utils;
+pub mod vector;
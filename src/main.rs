@@ -2,13 +2,15 @@
 //! Easel is a shader playground for creating high-quality digital paintings for printing.
 //! To this end, Easel intentionally uses high bitrate textures during the render process even though they are less memory and compute efficient.
 //! Easel is designed to be part of a workflow where you may want to further edit your digital paintings in an image editing program for later printing and display.
-//! Paintings are rendered using 16-bits-per-component textures and written to disk as uncompressed high-res 16-bit TIFF files.
+//! Paintings are rendered using 16-bits-per-component textures and written to disk as uncompressed high-res 16-bit TIFF files by default.
+//! See [Painting Formats](#painting-formats) for the other formats available.
 //!
 //! While rendering to screen, lower bitrate textures are used for efficiency.
 //! However, when the `Create Painting` button is pressed, a separte render pipeline utilising 16-bit textures is run to create the digital painting.
 //! Please note that using high bitrate texture such as these consumes large amounts of memory.
 //! The maximum painting resolution is determined by the amount of memory in your GPU.
-//! Attempting to use more than this will cause the program to crash.
+//! For larger paintings, set a tile size in the `Painting Options` and the painting will be rendered tile by tile
+//! and streamed to disk. See [Tiled Paintings](#tiled-paintings) below.
 //!
 //! Easel is designed to be cross-platform and run on Windows, macOS, and Linux.
 //! It uses [wgpu] as the render backend and [imgui] for the GUI.
@@ -31,6 +33,7 @@
 //!   - Time in seconds since last frame.
 //!   - Current render frame count (starts at 0)
 //!   - Current mouse position + mouse position in the previous frame.
+//!   - Pixel offset of the current tile, when rendering a [tiled painting](#tiled-paintings).
 //!
 //! Use the skeleton shader as a reference for the order and bindings for these uniforms.
 //!
@@ -50,12 +53,38 @@
 //! }
 //! ````
 //!
-//! At this time, the only the following data types are supported for push constants and uniforms: `i32`, `i64`, `f32`, `f64`, `u32`, `u64`, and `bool`.
+//! At this time, the only the following data types are supported for push constants: `i32`, `i64`, `f32`, `f64`, `u32`, `u64`, and `bool`.
+//! Uniforms also support the vector types `vec2`, `vec3`, `vec4`, `ivec2`, `ivec3`, `ivec4`, `uvec2`, `uvec3` and `uvec4`,
+//! the matrix types `mat3` and `mat4`, given as an array of columns or as one array in column-major order, and `color`.
+//! Colours are an RGB or RGBA array, bound as a `vec3` or `vec4`, and edited with a colour picker in the Dashboard:
+//! ```text
+//! "uniforms": {
+//!     "tint": ["color", [1.0, 0.4, 0.2]],
+//!     "offset": ["vec2", [0.5, -0.25]],
+//!     "warp": ["mat3", [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]]
+//! }
+//! ```
 //! **Note:** `bool` uniforms are bound as `u32` in shaders to respect alignment constraints.
+//! Keyframes and LFOs only apply to single numbers in the Dashboard.
+//!
+//! ### Uniform Metadata
+//! To control how a uniform is edited in the Dashboard, give it as an object with its `type` and `value` plus any of
+//! `min`, `max`, `step`, `widget`, `label`, `tooltip` and `group`. The widget is `input`, `slider`, `drag`, `knob` or `combo`.
+//! Sliders and knobs need a `min` and `max`, and combos pick from named `options`, an array of names for 0, 1, 2 and so on,
+//! or an object of names and values. Uniforms with the same `group` are shown together under a collapsible heading.
+//! ```text
+//! "uniforms": {
+//!     "zoom": { "type": "f32", "value": 4.0, "min": 1.0, "max": 10.0, "step": 0.5, "widget": "knob", "group": "Camera" },
+//!     "blend": { "type": "u32", "value": 0, "options": ["add", "multiply", "screen"], "label": "Blend Mode" },
+//!     "antialiasing": ["bool", true]
+//! }
+//! ```
+//! Without a `widget`, uniforms with `options` are combos, uniforms with a `min` and `max` are sliders, and others are inputs.
 //!
 //! ### Binding Order
 //! Easel-provided uniforms are always bound to set 0, binding 0. If you also provide uniforms, they are bound to set 0 binding 1.
-//! The order of bindings within the set is the same as the order in the JSON file. For example:
+//! Custom uniforms are packed into a single block with the `std140` layout, in the same order as in the JSON file,
+//! so the block in the shader must declare the same members in the same order. For example:
 //! ```text
 //! layout(set = 0, binding = 0) uniform EaselUniforms {
 //!     vec4 u_resolution;
@@ -66,6 +95,21 @@
 //! };
 //! layout(set = 0, binding = 1) uniform MyUniforms { bool antialiasing; };
 //!```
+//! Custom uniforms can be edited and reloaded while Easel runs, but not added, removed, reordered or changed in type.
+//! `Save to JSON` in the Dashboard's `Uniforms` panel writes the edited values, keyframes and LFOs back into the file,
+//! keeping metadata and any other keys, and `Revert` discards the edits by loading the file again.
+//! Edits to custom uniforms, including applying presets, can be undone with `Ctrl+Z` and redone with `Ctrl+Shift+Z`
+//! in either window, or with `Undo` and `Redo` in the `Uniforms` panel. Every change made during one drag is a single step.
+//!
+//! ### Generated Uniform Blocks
+//! Rather than keeping the block in step with the JSON file by hand, shaders can `#include "easel_uniforms.glsl"`.
+//! Easel generates this file from the uniforms file when compiling, declaring every custom uniform in order with matching
+//! std140 types, so `["color", [1.0, 0.4, 0.2]]` becomes a `vec3` and `bool` stays `bool`. Characters not allowed in GLSL
+//! names become underscores. Place the include near the top of the shader, as 64-bit integers add an `#extension` directive.
+//! A real `easel_uniforms.glsl` next to the shader takes precedence. To see or save the generated block, use:
+//! ```text
+//! easel uniforms header wipe-uniforms.json -o easel_uniforms.glsl
+//! ```
 //!
 //! ## Texture Loading
 //! Up to [wgpu::Limits::max_sampled_textures_per_shader_stage] images can be loaded and bound as input textures to the fragment shader using the `-t` option.
@@ -90,6 +134,149 @@
 //! If you would like to live-code your shaders, Easel also supports auto-loading of both the shader file and the JSON file.
 //! This works for both text shaders and SPIR-V blobs. Auto-reloading of postprocessing shaders is not supported at this time.
 //!
+//! ## Timeline
+//! The `Timeline` panel in the Dashboard controls `u_time` and `u_frame_num` directly. Drag the scrub slider, or type a
+//! time and press Enter, to jump straight to it, even while paused. The step buttons move one frame back or forward at the
+//! movie framerate, and the speed slider slows time down, speeds it up, or plays it backwards with a negative speed.
+//!
+//! ## Keyframe Animation
+//! Custom uniforms can be animated over `u_time` with keyframes, stored under `"keyframes"` in the uniforms file.
+//! Each keyframe is `[time, value]` or `[time, value, easing]`, where the easing applies until the next keyframe and is
+//! `"step"`, `"linear"` (the default), `"cubic"` to ease in and out, or Bezier control points `[x1, y1, x2, y2]` as in CSS.
+//! ```text
+//! {
+//!     "uniforms": { "zoom": ["f32", 1.0] },
+//!     "keyframes": { "zoom": [[0.0, 1.0, "cubic"], [4.0, 8.0, [0.7, 0.0, 0.3, 1.0]], [10.0, 2.0]] }
+//! }
+//! ```
+//! Before the first keyframe and after the last, the value holds. Keyframes can also be added and edited under
+//! `Keyframes` in the Dashboard, which plots each curve. Keyframed uniforms ignore edits in the `Uniforms` panel,
+//! and the `render` subcommand evaluates them at `--time`.
+//!
+//! ## LFOs
+//! Numeric custom uniforms can also be driven by a low-frequency oscillator, set to `offset + depth * wave` where the wave
+//! runs from -1 to 1 at `rate` cycles per second of `u_time`, starting `phase` cycles in. The waveform is one of `sine`,
+//! `triangle`, `square`, `saw`, `random hold` or `smooth noise`. LFOs are stored under `"lfos"` in the uniforms file,
+//! and every setting is optional:
+//! ```text
+//! {
+//!     "uniforms": { "hue": ["f32", 0.5] },
+//!     "lfos": { "hue": { "waveform": "smooth noise", "rate": 0.25, "depth": 0.5, "offset": 0.5, "enabled": true } }
+//! }
+//! ```
+//! Tick `LFO` under a uniform in the `Uniforms` panel to toggle its LFO and tune it live. Enabled LFOs override keyframes.
+//!
+//! ## Presets
+//! `Save Current as Preset` under `Presets` in the Dashboard stores a named snapshot of every custom uniform, along with
+//! the time and mouse position. Click a preset in the list to switch back to it. With two or more presets, pick a `From`
+//! and `To` preset to morph between them with the `Morph` slider, or over `Duration` seconds with `Morph Over Duration`.
+//! Numbers and vectors are interpolated component by component, integers are rounded and booleans switch halfway.
+//!
+//! Presets are kept in the file passed with `--presets`, by default `<uniforms>-presets.json` next to the uniforms file:
+//! ```text
+//! {
+//!     "presets": {
+//!         "calm": { "time": 12.5, "frame": 750, "mouse": [640.0, 360.0], "uniforms": { "zoom": ["f32", 2.0] } },
+//!         "storm": { "time": 3.0, "frame": 180, "mouse": [0.0, 0.0], "uniforms": { "zoom": ["f32", 9.5] } }
+//!     }
+//! }
+//! ```
+//!
+//! ## Exploring
+//! Under `Explore` in the Dashboard, `Randomise` sets every custom uniform with a `min` and `max` to a value picked
+//! uniformly from its range, and `Mutate` nudges each one by up to `Amount` of its range either way.
+//! Colours without a range use 0 to 1, uniforms with named options switch to one of their options, and the rest,
+//! including booleans, are left alone. Values snap to the uniform's `step`.
+//!
+//! Each press picks a new seed, shown as `Seed`. Tick `Lock Seed` to reuse the seed shown instead, so typing in a seed
+//! and pressing `Randomise` gives the same values again. The last `Gallery Size` results are kept as thumbnails of the
+//! canvas below; click one to go back to its values. Both can be undone like any other uniform edit.
+//!
+//! ## Headless Rendering
+//! Paintings can also be rendered without opening any windows, e.g. on machines without a display.
+//! The `render` subcommand runs the painting pipeline once, writes the result to disk and exits.
+//! ```text
+//! easel render shader.frag --width 12000 --height 8000 --time 12.5 -o out.tiff
+//! ```
+//! Textures, uniforms and post-processing shaders are provided with the same options as the windowed mode.
+//!
+//! ## Painting Formats
+//! The format paintings are written in is selected in the `Painting Options`, or with `--format` for the `render` subcommand.
+//!
+//!   - `tiff16`: 16-bit unsigned integer TIFF, in sRGB. Values outside of [0, 1] are clamped.
+//!   - `tiff32f`: 32-bit float TIFF, in linear color.
+//!   - `exr16f`: Half float OpenEXR, in linear color.
+//!   - `exr32f`: Float OpenEXR, in linear color.
+//!   - `tiff16-lzw`, `tiff16-deflate`: 16-bit TIFF with LZW or Deflate compression.
+//!   - `png16`, `png8`: 16-bit or 8-bit PNG, in sRGB.
+//!   - `jpeg`: JPEG, in sRGB, without alpha. The quality is set with `--quality` or the `Quality` slider.
+//!
+//! The float formats skip the final sRGB conversion and keep values outside of [0, 1], ready for grading in a compositor.
+//! OpenEXR and JPEG paintings are assembled in memory before writing, even when rendering in tiles.
+//! WebP is not available yet, as the version of the `image` crate Easel uses has no WebP encoder.
+//!
+//! ## Tiled Paintings
+//! Paintings larger than what fits in GPU memory can be rendered in square tiles, using either the `Tile Size` option
+//! in the GUI or `--tile-size` with the `render` subcommand. Each row of tiles is read back and streamed into the TIFF file,
+//! so neither the GPU nor the CPU ever hold the whole painting at once.
+//!
+//! While tiling, `u_resolution` is still the resolution of the whole painting and the pixel offset of the current tile
+//! is provided in `u_tile_offset.xy`. Shaders that should tile seamlessly must compute their coordinates from
+//! `gl_FragCoord.xy + u_tile_offset.xy` rather than `tex_coords`, which only spans the current tile.
+//! Post-processing effects that sample neighbouring pixels will show seams at tile edges.
+//!
+//! ## Painting Recipes
+//! Every painting records how it was made: a SHA-256 hash of the shader source, the texture and post-processing files,
+//! all of the provided uniforms, the value of every custom uniform and the Easel version.
+//! This recipe is embedded in the painting itself (the ImageDescription tag of TIFF files, a text chunk named `Easel Recipe`
+//! in PNG files, and an `Easel Recipe` header attribute in OpenEXR files) and is also written as JSON next to the painting,
+//! with the same name and a `.json` extension. The custom uniforms are stored under `"uniforms"` in the same format as
+//! a uniforms file, so the recipe can be passed back in with `--uniforms`.
+//!
+//! The `rerender` subcommand renders a painting again from its recipe, at a new resolution:
+//! ```text
+//! easel rerender Painting.json --scale 4
+//! ```
+//! The shader, textures, post-processing effects and custom uniforms are reloaded from the files named in the recipe,
+//! relative to the working directory, and the time, frame, mouse and date uniforms are set to their recorded values.
+//! A warning is logged if any shader has changed since the painting was made.
+//!
+//! ## Recording Movies
+//! Movies are encoded by piping frames to FFmpeg, which must be installed and on the `PATH`.
+//! In the default real time mode, frames are captured from the live canvas on the framerate interval, so frames that
+//! take longer than one interval to render are dropped. In offline mode, `u_time` advances by exactly `1 / framerate`
+//! per frame and every frame is read back and encoded in order, so the movie is smooth however slowly the shader runs.
+//!
+//! For shaders that are periodic in `u_time`, tick `Seamless Loop` in offline mode and set the `Duration` to the period.
+//! Easel then renders exactly `period * framerate` frames starting at `u_time = 0`, so the frame after the last one
+//! would be the first one again and the movie loops without a jump. If the uniforms file declares an `f32` uniform
+//! named `loop_phase`, it runs from 0 to 1 over the loop, `frame / frame count`.
+//!
+//! The FFmpeg encoder is chosen with a named profile, under `Encoder` in the `Recording Options` or with `--encoder`.
+//! Each profile sets the container, codec, pixel format, quality and any other FFmpeg options. The built-in profiles are
+//! `h264-web`, `hevc-lossless`, `hevc-main10`, `hevc-nvenc`, `prores4444` (with alpha), `ffv1` and `vp9`.
+//! Profiles with `high bit depth` set, such as `hevc-main10`, `prores4444` and `ffv1`, render frames through the half float
+//! painting pipeline and pipe them to FFmpeg as 16-bit `rgba64le`, so slow gradients don't band.
+//! More profiles can be added in a JSON file passed with `--encoder-profiles`:
+//! ```json
+//! {
+//!     "profiles": {
+//!         "review": { "container": "mp4", "codec": "libx264", "pixel format": "yuv420p", "bitrate": "12M", "args": ["-preset", "fast"] },
+//!         "grade": { "container": "mov", "codec": "prores_ks", "pixel format": "yuv444p10le", "high bit depth": true }
+//!     }
+//! }
+//! ```
+//! Set either `crf` or `bitrate`, not both. Only `codec` is required.
+//!
+//! To record without FFmpeg, choose an image sequence under `Output` in the `Recording Options`, or pass its painting
+//! format name to `--recorder`, such as `--recorder exr16f`. Each frame is then written as a numbered image into a
+//! directory named after the recording, such as `Muybridge/Muybridge_00000.exr`.
+//!
+//! Short loops can be written straight to an animated GIF or PNG with the `gif` and `apng` recorders, again without FFmpeg.
+//! Frames are kept in memory until the recording stops, so set a `Duration` for the length of the loop.
+//! GIF frames are reduced to a 256 colour palette each, optionally with dithering to hide banding,
+//! and `Ping-Pong` plays the loop forwards then backwards. The `Duration` also stops real time recordings.
+//!
 //! # Help
 //! Run `easel --help` to see all options and instructions.
 //!
@@ -104,16 +291,9 @@
 mod canvas;
 mod dashboard;
 // mod drawable;
-mod postprocessing;
-// mod push_constants;
-mod recording;
-mod skeletons;
-mod texture;
-mod uniforms;
-mod utils;
-mod vector;
+mod headless;
 
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg};
 use futures::executor::block_on;
 use log::{error, info};
 use winit::{
@@ -127,6 +307,7 @@ use crate::{
     dashboard::{Dashboard, DashboardMessage},
 };
 use canvas::Canvas;
+use easel_rs::{encoder, keyframes, lfo, recording::RecorderBackend, skeletons, uniforms, utils};
 use std::sync::mpsc::channel;
 use std::{cmp::max, thread, time::Instant};
 use std::{collections::HashMap, fs, path::Path};
@@ -145,6 +326,21 @@ fn main() {
     // Load command line args.
     let matches = setup_program_args();
 
+    if let Some(render_matches) = matches.subcommand_matches("render") {
+        headless::render_painting(render_matches);
+        return;
+    }
+    if let Some(rerender_matches) = matches.subcommand_matches("rerender") {
+        headless::rerender_painting(rerender_matches);
+        return;
+    }
+    if let Some(uniforms_matches) = matches.subcommand_matches("uniforms") {
+        if let Some(header_matches) = uniforms_matches.subcommand_matches("header") {
+            write_uniforms_header(header_matches);
+        }
+        return;
+    }
+
     let shader_file = matches
         .value_of("shader")
         .expect("Please provide a shader file.");
@@ -169,6 +365,33 @@ fn main() {
         }
     }
 
+    let recorder_name = matches.value_of("recorder").unwrap();
+    let recorder_backend = match RecorderBackend::from_name(recorder_name) {
+        Some(backend) => backend,
+        None => {
+            let names: Vec<&str> = RecorderBackend::ALL.iter().map(|b| b.name()).collect();
+            error!(
+                "Unknown recorder {}, must be one of: {}",
+                recorder_name,
+                names.join(", ")
+            );
+            return;
+        }
+    };
+
+    let encoder_profiles = match matches.value_of("encoder-profiles") {
+        Some(profiles_file) => {
+            let text = fs::read_to_string(profiles_file)
+                .expect("Error reading encoder profiles from file.");
+            let json_data = json::parse(&text).expect("Error parsing JSON.");
+            encoder::load_encoder_profiles_from_json(&json_data)
+        }
+        None => encoder::EncoderProfile::builtin(),
+    };
+    let encoder_name = matches
+        .value_of("encoder")
+        .unwrap_or_else(|| encoder::EncoderProfile::default_name());
+
     // Set width & height, if specified.
     let mut canvas_width = 1920;
     let mut canvas_height = 1280;
@@ -206,16 +429,10 @@ fn main() {
     // Make channels for sending events to Canvas
     let (canvas_event_tx, canvas_event_rx) = channel();
     drawables.insert(render_window.id(), canvas_event_tx);
-    let fs_spv_data = match utils::load_shader(shader_file) {
-        Ok(data) => data,
-        Err(e) => {
-            error!("Error compiling/loading shader: {}", e);
-            return;
-        }
-    };
-
     // Load custom uniforms from JSON file if specified.
     let mut custom_uniforms = None;
+    let mut keyframe_tracks = HashMap::new();
+    let mut lfos = HashMap::new();
     // let mut push_constants = None;
     if let Some(uniforms_file) = matches.value_of("uniforms") {
         let text = fs::read_to_string(uniforms_file).expect("Error reading uniforms from file.");
@@ -224,11 +441,21 @@ fn main() {
         if !cu.is_empty() {
             custom_uniforms = Some(cu);
         }
+        keyframe_tracks = keyframes::load_keyframes_from_json(&json_data);
+        lfos = lfo::load_lfos_from_json(&json_data);
         // let pc = push_constants::load_push_constants_from_json(&json_data);
         // if !pc.is_empty() {
         //     push_constants = Some(pc);
         // }
     }
+    let fs_spv_data =
+        match utils::load_shader(shader_file, custom_uniforms.as_deref().unwrap_or_default()) {
+            Ok(data) => data,
+            Err(e) => {
+                error!("Error compiling/loading shader: {}", e);
+                return;
+            }
+        };
     // Setup render state.
     let mut canvas = Box::new(block_on(Canvas::new(
         render_window,
@@ -240,11 +467,19 @@ fn main() {
         state_rx,
     )));
 
+    canvas.set_keyframe_tracks(keyframe_tracks);
+    canvas.set_lfos(lfos);
+    canvas.uniforms_file = matches.value_of("uniforms").map(String::from);
+    canvas.shader_sources.shader = String::from(shader_file);
+    canvas.shader_sources.textures = images_to_load.iter().map(|f| f.to_string()).collect();
+
     // Setup post-processing shaders if specified
     if let Some(postprocess_shaders) = matches.values_of("postprocess") {
         let mut postprocess_shader_modules = Vec::with_capacity(postprocess_shaders.len());
         for shader in postprocess_shaders {
-            postprocess_shader_modules.push(utils::load_shader(shader).unwrap());
+            postprocess_shader_modules
+                .push(utils::load_shader(shader, &canvas.renderer.user_uniforms).unwrap());
+            canvas.shader_sources.postprocess.push(String::from(shader));
         }
         for module in postprocess_shader_modules {
             canvas.add_post_processing_shader(module);
@@ -300,6 +535,16 @@ fn main() {
 
     // Setup Dashboard
     let mut dashboard = block_on(Dashboard::new(dashboard_window, dashboard_tx, dashboard_rx));
+    dashboard.set_recorder_backend(recorder_backend);
+    if !dashboard.set_encoder_profiles(encoder_profiles, encoder_name) {
+        error!(
+            "Unknown encoder profile {}, using the default.",
+            encoder_name
+        );
+    }
+    if let Some(presets_file) = presets_file_path(&matches) {
+        dashboard.set_presets_file(&presets_file);
+    }
     let mut last_render_time = Instant::now();
     event_loop.run(move |event, _event_loop, control_flow| {
         *control_flow = match !drawables.is_empty() {
@@ -356,11 +601,41 @@ fn main() {
     });
 }
 
+/// Private helper that writes the GLSL block declaring the uniforms in a uniforms file, for the `uniforms header` subcommand.
+/// Writes to the `output` file if given, otherwise prints to stdout.
+fn write_uniforms_header(matches: &clap::ArgMatches) {
+    let uniforms_file = matches.value_of("uniforms").unwrap();
+    let text = fs::read_to_string(uniforms_file).expect("Error reading uniforms from file.");
+    let json_data = json::parse(&text).expect("Error parsing JSON.");
+    let header = skeletons::uniforms_header(&uniforms::load_uniforms_from_json(&json_data));
+    match matches.value_of("output") {
+        Some(output) => {
+            if let Err(e) = fs::write(output, header) {
+                error!("Error writing uniforms header to {}: {}", output, e);
+            }
+        }
+        None => print!("{}", header),
+    }
+}
+
+/// Private helper that picks the presets file, from `--presets` or else next to the uniforms file.
+/// Returns None if neither option was given.
+fn presets_file_path(matches: &clap::ArgMatches) -> Option<String> {
+    if let Some(presets_file) = matches.value_of("presets") {
+        return Some(String::from(presets_file));
+    }
+    let uniforms_file = Path::new(matches.value_of("uniforms")?);
+    let stem = uniforms_file.file_stem()?.to_string_lossy();
+    let presets_file = uniforms_file.with_file_name(format!("{}-presets.json", stem));
+    Some(presets_file.to_string_lossy().into_owned())
+}
+
 /// Sets up all arguments to be parsed by Easel
 fn setup_program_args() -> clap::ArgMatches {
     App::new("Easel")
         .version("1.0.1")
         .author("Siddharth A. <sid.atre@me.com>")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::new("shader")
                 .about("The fragment shader to use.")
@@ -411,6 +686,13 @@ fn setup_program_args() -> clap::ArgMatches {
                 .short('u')
                 .long("uniforms")
         )
+        .arg(
+            Arg::new("presets")
+                .long_about("Provide a JSON file to load named presets of the custom uniforms from and save them to. Default is <uniforms>-presets.json next to the uniforms file.")
+                .required(false)
+                .takes_value(true)
+                .long("presets")
+        )
         .arg(Arg::new("postprocess")
             .long_about("Provided a shader to run after main fragment shader. Multiple can be provided. Postprocessing operations are applied in the order given here.")
             .required(false)
@@ -424,5 +706,176 @@ fn setup_program_args() -> clap::ArgMatches {
             .short('g')
             .long("generate")
         )
+        .arg(
+            Arg::new("recorder")
+                .long_about("Where movie recordings are written. ffmpeg encodes a movie, gif and apng write a looping animation, and any painting format writes numbered frames into a directory instead. One of: ffmpeg, gif, apng, png8, png16, tiff16, tiff16-lzw, tiff16-deflate, tiff32f, exr16f, exr32f.")
+                .required(false)
+                .takes_value(true)
+                .short('r')
+                .long("recorder")
+                .default_value("ffmpeg")
+        )
+        .arg(
+            Arg::new("encoder")
+                .long_about("FFmpeg encoder profile movies are recorded with. Built-in profiles are h264-web, hevc-lossless, hevc-main10, hevc-nvenc, prores4444, ffv1 and vp9. Defaults to hevc-nvenc on Windows and hevc-lossless elsewhere.")
+                .required(false)
+                .takes_value(true)
+                .short('e')
+                .long("encoder")
+        )
+        .arg(
+            Arg::new("encoder-profiles")
+                .long_about("Provide a JSON file with additional FFmpeg encoder profiles. Profiles with the name of a built-in profile replace it.")
+                .required(false)
+                .takes_value(true)
+                .long("encoder-profiles")
+        )
+        .subcommand(
+            App::new("render")
+                .about("Render a single painting without opening any windows, then exit.")
+                .arg(
+                    Arg::new("shader")
+                        .about("The fragment shader to use.")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("width")
+                        .about("Width of painting")
+                        .takes_value(true)
+                        .short('w')
+                        .long("width")
+                        .default_value("1920")
+                )
+                .arg(
+                    Arg::new("height")
+                        .about("Height of painting")
+                        .takes_value(true)
+                        .short('h')
+                        .long("height")
+                        .default_value("1280")
+                )
+                .arg(
+                    Arg::new("time")
+                        .about("Time in seconds at which to render the painting.")
+                        .takes_value(true)
+                        .long("time")
+                        .default_value("0.0")
+                )
+                .arg(
+                    Arg::new("output")
+                        .about("File to write the painting to. Defaults to Painting.<extension of format>.")
+                        .takes_value(true)
+                        .short('o')
+                        .long("output")
+                )
+                .arg(
+                    Arg::new("format")
+                        .long_about("Format to write the painting in. One of tiff16 (16-bit TIFF), tiff16-lzw and tiff16-deflate (compressed 16-bit TIFF), tiff32f (32-bit float TIFF), exr16f (half float OpenEXR), exr32f (float OpenEXR), png16 (16-bit PNG), png8 (8-bit PNG) or jpeg. Float formats are written in linear color. Defaults to tiff16.")
+                        .takes_value(true)
+                        .long("format")
+                        .default_value("tiff16")
+                )
+                .arg(
+                    Arg::new("quality")
+                        .about("Quality of JPEG paintings, from 1 to 100.")
+                        .takes_value(true)
+                        .long("quality")
+                        .default_value("90")
+                )
+                .arg(
+                    Arg::new("tile-size")
+                        .long_about("Render the painting in square tiles of this many pixels and stream them to disk. Use for paintings too large to fit in GPU memory.")
+                        .takes_value(true)
+                        .long("tile-size")
+                )
+                .arg(
+                    Arg::new("textures")
+                        .long_about("List of images to load. Textures are bound to the shader Set 1 in the order specified here.")
+                        .takes_value(true)
+                        .short('t')
+                        .long("textures")
+                        .multiple(true)
+                )
+                .arg(
+                    Arg::new("uniforms")
+                        .long_about("Provide a JSON file with custom uniforms. Uniforms are bound in Set 2 in the order provided.")
+                        .takes_value(true)
+                        .short('u')
+                        .long("uniforms")
+                )
+                .arg(Arg::new("postprocess")
+                    .long_about("Provided a shader to run after main fragment shader. Multiple can be provided. Postprocessing operations are applied in the order given here.")
+                    .takes_value(true)
+                    .multiple(true)
+                    .short('p')
+                    .long("postprocess"))
+        )
+        .subcommand(
+            App::new("rerender")
+                .about("Render a painting again from its recipe at a new resolution, without opening any windows, then exit.")
+                .arg(
+                    Arg::new("recipe")
+                        .about("The recipe JSON file written next to the painting.")
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("scale")
+                        .about("Factor to scale the resolution of the painting by.")
+                        .takes_value(true)
+                        .short('s')
+                        .long("scale")
+                        .default_value("1")
+                )
+                .arg(
+                    Arg::new("output")
+                        .about("File to write the painting to. Defaults to <recipe>-<scale>x.<extension of format>.")
+                        .takes_value(true)
+                        .short('o')
+                        .long("output")
+                )
+                .arg(
+                    Arg::new("format")
+                        .long_about("Format to write the painting in, see the render subcommand. Defaults to the format of the original painting.")
+                        .takes_value(true)
+                        .long("format")
+                )
+                .arg(
+                    Arg::new("quality")
+                        .about("Quality of JPEG paintings, from 1 to 100.")
+                        .takes_value(true)
+                        .long("quality")
+                        .default_value("90")
+                )
+                .arg(
+                    Arg::new("tile-size")
+                        .long_about("Render the painting in square tiles of this many pixels and stream them to disk. Use for paintings too large to fit in GPU memory.")
+                        .takes_value(true)
+                        .long("tile-size")
+                )
+        )
+        .subcommand(
+            App::new("uniforms")
+                .about("Tools for custom uniforms files.")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("header")
+                        .long_about("Write the GLSL uniform block declaring the custom uniforms in a JSON file, in order and with std140-compatible types. This is the same block shaders get from #include \"easel_uniforms.glsl\".")
+                        .arg(
+                            Arg::new("uniforms")
+                                .about("The JSON file with custom uniforms.")
+                                .index(1)
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .about("File to write the block to. Defaults to stdout.")
+                                .takes_value(true)
+                                .short('o')
+                                .long("output")
+                        )
+                )
+        )
         .get_matches()
 }
@@ -0,0 +1,215 @@
+use crate::lfo::{random_value, seed_from_name};
+use crate::uniforms::{UserUniform, UserUniformType};
+
+/// Private helper that draws a repeatable sequence of random numbers for one uniform.
+/// Each uniform gets its own sequence, so adding or removing a uniform doesn't change what the others get for a seed.
+struct RandomSequence {
+    seed: u32,
+    draws: i64,
+}
+
+impl RandomSequence {
+    fn new(seed: u32, uniform: &UserUniform) -> RandomSequence {
+        RandomSequence {
+            seed: seed ^ seed_from_name(&uniform.name),
+            draws: 0,
+        }
+    }
+
+    /// The next number in the sequence, from 0 to 1.
+    fn next(&mut self) -> f64 {
+        let value = random_value(self.seed, self.draws);
+        self.draws += 1;
+        (value + 1.0) / 2.0
+    }
+}
+
+/// Pick a new seed for [randomise_uniforms()] or [mutate_uniforms()] from the system clock.
+pub fn random_seed() -> u32 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    (nanos as u32) ^ (nanos >> 32) as u32
+}
+
+/// The range a uniform is explored within: its declared `min` and `max`, or 0 to 1 for colours without them.
+/// Returns None for booleans and for uniforms without a declared range, which are left unchanged.
+pub fn exploration_range(uniform: &UserUniform) -> Option<(f64, f64)> {
+    match uniform.inherent_type {
+        UserUniformType::Bool => None,
+        UserUniformType::Color3 | UserUniformType::Color4 => {
+            Some(uniform.metadata.range().unwrap_or((0.0, 1.0)))
+        }
+        _ => uniform.metadata.range(),
+    }
+}
+
+/// Set every numeric uniform with a range to a random value drawn uniformly from its range, see [exploration_range()].
+/// Uniforms with named options are set to one of their options instead. Values snap to the uniform's `step`.
+/// The same seed always gives the same values.
+/// * `uniforms` - Uniforms to randomise.
+/// * `seed` - Picks the random values.
+pub fn randomise_uniforms(uniforms: &[UserUniform], seed: u32) -> Vec<UserUniform> {
+    explore_uniforms(uniforms, seed, 1.0, |_, min, max, random| {
+        min + random * (max - min)
+    })
+}
+
+/// Nudge every numeric uniform with a range by a random amount, up to a fraction of its range either way,
+/// see [exploration_range()]. Uniforms with named options switch to a random option with a chance of `amount`.
+/// Values snap to the uniform's `step` and stay within the range.
+/// The same seed always gives the same values from the same starting values.
+/// * `uniforms` - Uniforms to mutate.
+/// * `amount` - Largest change as a fraction of each range, from 0 to 1.
+/// * `seed` - Picks the random changes.
+pub fn mutate_uniforms(uniforms: &[UserUniform], amount: f64, seed: u32) -> Vec<UserUniform> {
+    explore_uniforms(uniforms, seed, amount, |value, min, max, random| {
+        value + (random * 2.0 - 1.0) * amount * (max - min)
+    })
+}
+
+/// Private helper that sets every component of each uniform with a range to a new value, then clamps and snaps it.
+/// * `option_chance` - Chance that uniforms with named options switch to a random option.
+/// * `new_value` - Gives a new value from the current value, the range, and a random number from 0 to 1.
+fn explore_uniforms<F: Fn(f64, f64, f64, f64) -> f64>(
+    uniforms: &[UserUniform],
+    seed: u32,
+    option_chance: f64,
+    new_value: F,
+) -> Vec<UserUniform> {
+    uniforms
+        .iter()
+        .map(|uniform| {
+            let mut random = RandomSequence::new(seed, uniform);
+            let mut uniform = uniform.clone();
+            let options = &uniform.metadata.options;
+            if !options.is_empty() {
+                // Uniforms with named options only take their values.
+                let (switch, pick) = (random.next(), random.next());
+                if switch < option_chance {
+                    let idx = ((pick * options.len() as f64) as usize).min(options.len() - 1);
+                    let value = options[idx].1;
+                    uniform.set_from_f64(value);
+                }
+                return uniform;
+            }
+            if let Some((min, max)) = exploration_range(&uniform) {
+                let components: Vec<f64> = uniform
+                    .components()
+                    .iter()
+                    .map(|value| {
+                        let value = new_value(*value, min, max, random.next()).clamp(min, max);
+                        uniform.metadata.snap(value)
+                    })
+                    .collect();
+                uniform.set_components(&components);
+            }
+            uniform
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uniforms::load_uniforms_from_json;
+
+    fn uniforms() -> Vec<UserUniform> {
+        load_uniforms_from_json(
+            &json::parse(
+                r#"{"uniforms": {
+                    "zoom": {"type": "f32", "value": 4.0, "min": 1.0, "max": 10.0, "step": 0.5},
+                    "count": {"type": "i32", "value": 3, "min": 0, "max": 8, "step": 2},
+                    "mode": {"type": "u32", "value": 0, "options": {"add": 0, "multiply": 5, "screen": 9}},
+                    "offset": {"type": "vec2", "value": [0.0, 0.0], "min": -1.0, "max": 1.0},
+                    "free": ["f32", 2.0]
+                }}"#,
+            )
+            .unwrap(),
+        )
+    }
+
+    fn components(uniforms: &[UserUniform]) -> Vec<Vec<f64>> {
+        uniforms
+            .iter()
+            .map(|uniform| uniform.components())
+            .collect()
+    }
+
+    fn check_in_range(uniforms: &[UserUniform]) {
+        let zoom = uniforms[0].as_f64();
+        assert!((1.0..=10.0).contains(&zoom));
+        assert_eq!(((zoom - 1.0) / 0.5).fract(), 0.0);
+        let count = uniforms[1].as_f64();
+        assert!((0.0..=8.0).contains(&count));
+        assert_eq!(count % 2.0, 0.0);
+        assert!([0.0, 5.0, 9.0].contains(&uniforms[2].as_f64()));
+        for value in uniforms[3].components() {
+            assert!((-1.0..=1.0).contains(&value));
+        }
+        // Uniforms without a range are left unchanged.
+        assert_eq!(uniforms[4].as_f64(), 2.0);
+    }
+
+    #[test]
+    fn same_seed_gives_same_values() {
+        let uniforms = uniforms();
+        assert_eq!(
+            components(&randomise_uniforms(&uniforms, 42)),
+            components(&randomise_uniforms(&uniforms, 42))
+        );
+        assert_eq!(
+            components(&mutate_uniforms(&uniforms, 0.2, 42)),
+            components(&mutate_uniforms(&uniforms, 0.2, 42))
+        );
+        assert_ne!(
+            components(&randomise_uniforms(&uniforms, 42)),
+            components(&randomise_uniforms(&uniforms, 43))
+        );
+    }
+
+    #[test]
+    fn values_stay_in_range_and_snap_to_step() {
+        let uniforms = uniforms();
+        for seed in 0..100 {
+            let randomised = randomise_uniforms(&uniforms, seed);
+            check_in_range(&randomised);
+            check_in_range(&mutate_uniforms(&randomised, 0.5, seed));
+        }
+    }
+
+    #[test]
+    fn options_pick_a_listed_option() {
+        let uniforms = uniforms();
+        let mut picked = Vec::new();
+        for seed in 0..100 {
+            let value = randomise_uniforms(&uniforms, seed)[2].as_f64();
+            assert!([0.0, 5.0, 9.0].contains(&value));
+            if !picked.contains(&value) {
+                picked.push(value);
+            }
+        }
+        assert_eq!(picked.len(), 3);
+        // Without any mutation, options never switch.
+        for seed in 0..100 {
+            assert_eq!(mutate_uniforms(&uniforms, 0.0, seed)[2].as_f64(), 0.0);
+        }
+    }
+
+    #[test]
+    fn values_do_not_depend_on_other_uniforms() {
+        let uniforms = uniforms();
+        let fewer = &uniforms[2..];
+        for seed in 0..10 {
+            assert_eq!(
+                components(&randomise_uniforms(&uniforms, seed)[2..]),
+                components(&randomise_uniforms(fewer, seed))
+            );
+            assert_eq!(
+                components(&mutate_uniforms(&uniforms, 0.3, seed)[2..]),
+                components(&mutate_uniforms(fewer, 0.3, seed))
+            );
+        }
+    }
+}
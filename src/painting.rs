@@ -0,0 +1,514 @@
+use crate::recipe::{PaintingRecipe, RECIPE_KEY};
+use crate::utils::read_painting_data;
+use crate::vector::UIntVector2;
+use futures::executor::block_on;
+use half::f16;
+use image::codecs::jpeg::JpegEncoder;
+use log::{error, info};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{channel, sync_channel, Receiver, SyncSender};
+use tiff::encoder::compression::{Compression, Deflate, Lzw, Uncompressed};
+use tiff::encoder::{colortype, TiffEncoder, TiffValue};
+use tiff::tags::Tag;
+
+/// JPEG quality used when none is specified, from 1 (worst) to 100 (best).
+pub static DEFAULT_JPEG_QUALITY: u8 = 90;
+
+/// The file formats and encodings paintings can be written with.
+/// WebP is not supported: the version of the `image` crate Easel depends on can only decode WebP, not encode it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaintingFormat {
+    /// Uncompressed TIFF, 16-bit unsigned integer per component. Components are clamped to [0, 1].
+    Tiff16,
+    /// LZW compressed TIFF, 16-bit unsigned integer per component. Components are clamped to [0, 1].
+    Tiff16Lzw,
+    /// Deflate compressed TIFF, 16-bit unsigned integer per component. Components are clamped to [0, 1].
+    Tiff16Deflate,
+    /// Uncompressed TIFF, 32-bit float per component, in linear color.
+    TiffFloat32,
+    /// OpenEXR, 16-bit half float per component, in linear color.
+    ExrHalf,
+    /// OpenEXR, 32-bit float per component, in linear color.
+    ExrFloat,
+    /// PNG, 16-bit unsigned integer per component. Components are clamped to [0, 1].
+    Png16,
+    /// PNG, 8-bit unsigned integer per component. Components are clamped to [0, 1].
+    Png8,
+    /// JPEG with the given quality, from 1 to 100. The alpha channel is dropped.
+    Jpeg(u8),
+}
+
+impl PaintingFormat {
+    /// All formats, in the order they are listed in the GUI.
+    pub const ALL: [PaintingFormat; 9] = [
+        PaintingFormat::Tiff16,
+        PaintingFormat::Tiff16Lzw,
+        PaintingFormat::Tiff16Deflate,
+        PaintingFormat::TiffFloat32,
+        PaintingFormat::ExrHalf,
+        PaintingFormat::ExrFloat,
+        PaintingFormat::Png16,
+        PaintingFormat::Png8,
+        PaintingFormat::Jpeg(DEFAULT_JPEG_QUALITY),
+    ];
+
+    /// Short name used to select the format on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaintingFormat::Tiff16 => "tiff16",
+            PaintingFormat::Tiff16Lzw => "tiff16-lzw",
+            PaintingFormat::Tiff16Deflate => "tiff16-deflate",
+            PaintingFormat::TiffFloat32 => "tiff32f",
+            PaintingFormat::ExrHalf => "exr16f",
+            PaintingFormat::ExrFloat => "exr32f",
+            PaintingFormat::Png16 => "png16",
+            PaintingFormat::Png8 => "png8",
+            PaintingFormat::Jpeg(_) => "jpeg",
+        }
+    }
+
+    /// Look up a format by its [Self::name()]. JPEG uses the [DEFAULT_JPEG_QUALITY].
+    pub fn from_name(name: &str) -> Option<PaintingFormat> {
+        PaintingFormat::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name() == name)
+    }
+
+    /// Human-readable description of the format, as shown in the GUI.
+    pub fn description(&self) -> &'static str {
+        match self {
+            PaintingFormat::Tiff16 => "TIFF, 16-bit",
+            PaintingFormat::Tiff16Lzw => "TIFF, 16-bit, LZW",
+            PaintingFormat::Tiff16Deflate => "TIFF, 16-bit, Deflate",
+            PaintingFormat::TiffFloat32 => "TIFF, 32-bit float (linear)",
+            PaintingFormat::ExrHalf => "OpenEXR, half float (linear)",
+            PaintingFormat::ExrFloat => "OpenEXR, float (linear)",
+            PaintingFormat::Png16 => "PNG, 16-bit",
+            PaintingFormat::Png8 => "PNG, 8-bit",
+            PaintingFormat::Jpeg(_) => "JPEG",
+        }
+    }
+
+    /// File extension of the format, without the leading dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PaintingFormat::Tiff16
+            | PaintingFormat::Tiff16Lzw
+            | PaintingFormat::Tiff16Deflate
+            | PaintingFormat::TiffFloat32 => "tiff",
+            PaintingFormat::ExrHalf | PaintingFormat::ExrFloat => "exr",
+            PaintingFormat::Png16 | PaintingFormat::Png8 => "png",
+            PaintingFormat::Jpeg(_) => "jpg",
+        }
+    }
+
+    /// Whether the format stores linear color.
+    /// Paintings written in linear formats skip the sRGB conversion and keep values outside [0, 1].
+    pub fn is_linear(&self) -> bool {
+        matches!(
+            self,
+            PaintingFormat::TiffFloat32 | PaintingFormat::ExrHalf | PaintingFormat::ExrFloat
+        )
+    }
+}
+
+/// Private helper to convert a painting component to a 16-bit unsigned integer, clamping it to [0, 1].
+fn component_to_u16(component: f16) -> u16 {
+    (component.to_f32().clamp(0.0, 1.0) * 65535.0).round() as u16
+}
+
+/// Private helper to convert a painting component to an 8-bit unsigned integer, clamping it to [0, 1].
+fn component_to_u8(component: f16) -> u8 {
+    (component.to_f32().clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// An enum used by the [AsyncPaintingWriter] class to signify a write operation has finished.
+pub enum WriteFinished {
+    Finished,
+}
+
+/// A struct used to write a painting to disk after rendering.
+pub struct AsyncPaintingWriter {}
+
+impl AsyncPaintingWriter {
+    /// Private helper method called by [AsyncPaintingWriter::write] and [AsyncPaintingWriter::write_bands].
+    /// Writes the painting with the recipe embedded, followed by the sidecar recipe JSON file.
+    /// Returns false if the bands ran out before the painting was complete.
+    fn write_painting_to_disk<I: Iterator<Item = Vec<f16>>>(
+        bands: I,
+        recipe: &PaintingRecipe,
+        filename: &str,
+        _open_external_app: bool,
+    ) -> bool {
+        if !Self::encode_to_file(
+            bands,
+            recipe.resolution,
+            recipe.format,
+            Some(recipe),
+            filename,
+        ) {
+            return false;
+        }
+        let sidecar = PaintingRecipe::sidecar_path(filename);
+        if let Err(e) = std::fs::write(&sidecar, recipe.to_json().pretty(4)) {
+            error!("Error writing painting recipe {}: {}", sidecar.display(), e);
+        }
+
+        // Once writing has finished, open in external app if specified.
+        #[cfg(target_os = "macos")]
+        if _open_external_app {
+            std::process::Command::new("open")
+                .arg(filename)
+                .spawn()
+                .expect("Error launching external app to display painting.");
+        }
+        true
+    }
+
+    /// Private helper that encodes an image in the given format and writes it to `filename`.
+    /// The recipe, if any, is embedded in formats that support text metadata.
+    /// Returns false if the bands ran out before the image was complete.
+    fn encode_to_file<I: Iterator<Item = Vec<f16>>>(
+        bands: I,
+        resolution: UIntVector2,
+        format: PaintingFormat,
+        recipe: Option<&PaintingRecipe>,
+        filename: &str,
+    ) -> bool {
+        match format {
+            PaintingFormat::Tiff16 => Self::write_tiff::<colortype::RGBA16, _, _, _>(
+                bands,
+                resolution,
+                recipe,
+                filename,
+                Uncompressed,
+                component_to_u16,
+            ),
+            PaintingFormat::Tiff16Lzw => Self::write_tiff::<colortype::RGBA16, _, _, _>(
+                bands,
+                resolution,
+                recipe,
+                filename,
+                Lzw,
+                component_to_u16,
+            ),
+            PaintingFormat::Tiff16Deflate => Self::write_tiff::<colortype::RGBA16, _, _, _>(
+                bands,
+                resolution,
+                recipe,
+                filename,
+                Deflate::default(),
+                component_to_u16,
+            ),
+            PaintingFormat::TiffFloat32 => Self::write_tiff::<colortype::RGBA32Float, _, _, _>(
+                bands,
+                resolution,
+                recipe,
+                filename,
+                Uncompressed,
+                |c| c.to_f32(),
+            ),
+            PaintingFormat::ExrHalf | PaintingFormat::ExrFloat => {
+                Self::write_exr(bands, resolution, format, recipe, filename)
+            }
+            PaintingFormat::Png16 | PaintingFormat::Png8 => {
+                Self::write_png(bands, resolution, format, recipe, filename)
+            }
+            // JPEG has no standard place for text metadata, so paintings only get the sidecar file.
+            PaintingFormat::Jpeg(quality) => Self::write_jpeg(bands, resolution, quality, filename),
+        }
+    }
+
+    /// Private helper that writes a TIFF strip by strip as bands arrive,
+    /// so the whole painting never has to be held in memory.
+    /// The recipe is stored in the ImageDescription tag.
+    /// * `compression` - Compression algorithm to encode each strip with.
+    /// * `convert` - Converts each component to the sample type of the TIFF color type.
+    fn write_tiff<C, D, I, F>(
+        bands: I,
+        resolution: UIntVector2,
+        recipe: Option<&PaintingRecipe>,
+        filename: &str,
+        compression: D,
+        convert: F,
+    ) -> bool
+    where
+        C: colortype::ColorType,
+        [C::Inner]: TiffValue,
+        D: Compression,
+        I: Iterator<Item = Vec<f16>>,
+        F: Fn(f16) -> C::Inner,
+    {
+        let file = File::create(Path::new(filename)).unwrap();
+        let mut tiff = TiffEncoder::new(BufWriter::new(file)).unwrap();
+        let mut image = tiff
+            .new_image_with_compression::<C, D>(resolution.x, resolution.y, compression)
+            .unwrap();
+        if let Some(recipe) = recipe {
+            let encoder = image.encoder();
+            encoder
+                .write_tag(Tag::Software, recipe.software().as_str())
+                .unwrap();
+            encoder
+                .write_tag(Tag::ImageDescription, recipe.to_json().dump().as_str())
+                .unwrap();
+        }
+
+        let mut strip = Vec::new();
+        for band in bands {
+            for component in band {
+                strip.push(convert(component));
+                if strip.len() as u64 == image.next_strip_sample_count() {
+                    image.write_strip(&strip).unwrap();
+                    strip.clear();
+                }
+            }
+        }
+        if image.next_strip_sample_count() > 0 {
+            return false;
+        }
+        image.finish().unwrap();
+        true
+    }
+
+    /// Private helper that writes an OpenEXR file.
+    /// EXR files are encoded from the whole painting, so all bands are gathered in memory first.
+    /// The recipe is stored as a text attribute in the header.
+    fn write_exr<I: Iterator<Item = Vec<f16>>>(
+        bands: I,
+        resolution: UIntVector2,
+        format: PaintingFormat,
+        recipe: Option<&PaintingRecipe>,
+        filename: &str,
+    ) -> bool {
+        use exr::prelude::{AttributeValue, Image, SpecificChannels, Text, Vec2, WritableImage};
+
+        let (width, height) = (resolution.x as usize, resolution.y as usize);
+        let mut components = Vec::with_capacity(width * height * 4);
+        for band in bands {
+            components.extend_from_slice(&band);
+        }
+        if components.len() < width * height * 4 {
+            return false;
+        }
+
+        // The exr crate has its own version of the half crate, so halves are passed on bit for bit.
+        let component = |x: usize, y: usize, i: usize| components[(y * width + x) * 4 + i];
+        let software = recipe.and_then(|recipe| Text::new_or_none(recipe.software()));
+        let mut other = std::collections::HashMap::new();
+        if let Some(recipe) = recipe {
+            let recipe_json = recipe.to_json().dump();
+            other.insert(
+                Text::new_or_panic(RECIPE_KEY),
+                AttributeValue::Text(Text::from_slice_unchecked(recipe_json.as_bytes())),
+            );
+        }
+        let result = match format {
+            PaintingFormat::ExrHalf => {
+                let mut image = Image::from_channels(
+                    (width, height),
+                    SpecificChannels::rgba(|Vec2(x, y)| {
+                        let half = |i| exr::prelude::f16::from_bits(component(x, y, i).to_bits());
+                        (half(0), half(1), half(2), half(3))
+                    }),
+                );
+                image.attributes.other = other;
+                image.layer_data.attributes.software_name = software;
+                image.write().to_file(filename)
+            }
+            _ => {
+                let mut image = Image::from_channels(
+                    (width, height),
+                    SpecificChannels::rgba(|Vec2(x, y)| {
+                        let float = |i| component(x, y, i).to_f32();
+                        (float(0), float(1), float(2), float(3))
+                    }),
+                );
+                image.attributes.other = other;
+                image.layer_data.attributes.software_name = software;
+                image.write().to_file(filename)
+            }
+        };
+        result.expect("Error writing OpenEXR painting.");
+        true
+    }
+
+    /// Private helper that writes a PNG row by row as bands arrive,
+    /// so the whole painting never has to be held in memory.
+    /// The recipe is stored in a text chunk.
+    fn write_png<I: Iterator<Item = Vec<f16>>>(
+        bands: I,
+        resolution: UIntVector2,
+        format: PaintingFormat,
+        recipe: Option<&PaintingRecipe>,
+        filename: &str,
+    ) -> bool {
+        let file = File::create(Path::new(filename)).unwrap();
+        let mut encoder = png::Encoder::new(BufWriter::new(file), resolution.x, resolution.y);
+        encoder.set_color(png::ColorType::Rgba);
+        if let Some(recipe) = recipe {
+            encoder
+                .add_text_chunk(String::from("Software"), recipe.software())
+                .unwrap();
+            // tEXt chunks are Latin-1, so recipes with other characters in file names go in a UTF-8 iTXt chunk.
+            let text = recipe.to_json().dump();
+            match text.is_ascii() {
+                true => encoder.add_text_chunk(String::from(RECIPE_KEY), text),
+                false => encoder.add_itxt_chunk(String::from(RECIPE_KEY), text),
+            }
+            .unwrap();
+        }
+        let sixteen_bit = format == PaintingFormat::Png16;
+        match sixteen_bit {
+            true => encoder.set_depth(png::BitDepth::Sixteen),
+            false => encoder.set_depth(png::BitDepth::Eight),
+        }
+        let mut stream = encoder
+            .write_header()
+            .unwrap()
+            .into_stream_writer()
+            .unwrap();
+
+        let mut bytes = Vec::new();
+        let mut components_written = 0;
+        for band in bands {
+            bytes.clear();
+            for component in &band {
+                // PNG stores 16-bit samples in big endian byte order.
+                match sixteen_bit {
+                    true => bytes.extend_from_slice(&component_to_u16(*component).to_be_bytes()),
+                    false => bytes.push(component_to_u8(*component)),
+                }
+            }
+            stream.write_all(&bytes).unwrap();
+            components_written += band.len();
+        }
+        if components_written < (resolution.x * resolution.y * 4) as usize {
+            return false;
+        }
+        stream.finish().unwrap();
+        true
+    }
+
+    /// Private helper that writes a JPEG.
+    /// JPEG files are encoded from the whole painting, so all bands are gathered in memory first.
+    fn write_jpeg<I: Iterator<Item = Vec<f16>>>(
+        bands: I,
+        resolution: UIntVector2,
+        quality: u8,
+        filename: &str,
+    ) -> bool {
+        let mut pixel_data = Vec::with_capacity((resolution.x * resolution.y * 4) as usize);
+        for band in bands {
+            pixel_data.extend(band.into_iter().map(component_to_u8));
+        }
+        if pixel_data.len() < (resolution.x * resolution.y * 4) as usize {
+            return false;
+        }
+
+        let file = File::create(Path::new(filename)).unwrap();
+        let mut buf_writer = BufWriter::new(file);
+        JpegEncoder::new_with_quality(&mut buf_writer, quality.clamp(1, 100))
+            .encode(
+                &pixel_data,
+                resolution.x,
+                resolution.y,
+                image::ColorType::Rgba8,
+            )
+            .expect("Error writing JPEG painting.");
+        true
+    }
+
+    /// Given a painting present in GPU memory, copy to CPU, encode it in the format of the recipe and write to disk.
+    /// The recipe is embedded in the painting and written to a sidecar JSON file, see [PaintingRecipe::sidecar_path()].
+    /// **Note:** This function launches an async task and returns immediately.
+    /// Use the returned [std::sync::mpsc::Receiver] object which can be used to poll for status updates.
+    /// * `painting` - WGPU buffer holding the image data.
+    /// * `recipe` - How the painting was rendered, including its resolution and format.
+    /// * `filename` - File will be written relative to working directory. Should end with the extension of the format.
+    /// * `open_external_app` - Optionally launch external program to view the image. Only supported on macOS and Windows.
+    pub fn write(
+        buffer: wgpu::Buffer,
+        recipe: PaintingRecipe,
+        filename: String,
+        open_external_app: bool,
+    ) -> Receiver<WriteFinished> {
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let mut pixel_data = Vec::new();
+            block_on(read_painting_data(
+                buffer,
+                recipe.resolution,
+                &mut pixel_data,
+            ));
+            AsyncPaintingWriter::write_painting_to_disk(
+                std::iter::once(pixel_data),
+                &recipe,
+                &filename,
+                open_external_app,
+            );
+            info!("Wrote painting {} to disk", filename);
+            tx.send(WriteFinished::Finished).unwrap();
+        });
+        rx
+    }
+
+    /// Stream a painting that is rendered in horizontal bands, such as by
+    /// [crate::renderer::Renderer::render_painting_tiled()], to a file on disk.
+    /// TIFF and PNG paintings are written as the bands arrive. OpenEXR and JPEG paintings are gathered in memory and written at the end.
+    /// **Note:** This function launches a writer thread and returns immediately.
+    /// Send the bands, top to bottom, on the returned [std::sync::mpsc::SyncSender]. Each band holds whole rows
+    /// of tightly packed RGBA half float pixels.
+    /// The returned [std::sync::mpsc::Receiver] can be used to poll for status updates.
+    /// * `recipe` - How the painting is rendered, including its full resolution and format.
+    /// * `filename` - File will be written relative to working directory. Should end with the extension of the format.
+    /// * `open_external_app` - Optionally launch external program to view the image. Only supported on macOS and Windows.
+    pub fn write_bands(
+        recipe: PaintingRecipe,
+        filename: String,
+        open_external_app: bool,
+    ) -> (SyncSender<Vec<f16>>, Receiver<WriteFinished>) {
+        // Only buffer one band ahead, to bound memory use when the disk is slower than the GPU.
+        let (band_tx, band_rx) = sync_channel(1);
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            if !AsyncPaintingWriter::write_painting_to_disk(
+                band_rx.iter(),
+                &recipe,
+                &filename,
+                open_external_app,
+            ) {
+                error!("Painting {} ended before all bands were written", filename);
+                return;
+            }
+            info!("Wrote painting {} to disk", filename);
+            tx.send(WriteFinished::Finished).unwrap();
+        });
+        (band_tx, rx)
+    }
+
+    /// Encode a single image, such as a frame of an image sequence, and write it to disk.
+    /// Unlike paintings, no recipe is embedded or written alongside it.
+    /// **Note:** This function blocks until the file has been written.
+    /// * `pixel_data` - Tightly packed RGBA half float pixels, as read back by [crate::utils::read_painting_data()].
+    /// * `resolution` - The width and height of the image.
+    /// * `format` - Format to encode the image with.
+    /// * `filename` - File will be written relative to working directory. Should end with the extension of `format`.
+    pub fn write_frame(
+        pixel_data: Vec<f16>,
+        resolution: UIntVector2,
+        format: PaintingFormat,
+        filename: &str,
+    ) -> bool {
+        Self::encode_to_file(
+            std::iter::once(pixel_data),
+            resolution,
+            format,
+            None,
+            filename,
+        )
+    }
+}
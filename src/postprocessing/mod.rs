@@ -6,6 +6,7 @@ use wgpu::{
     RenderPassDescriptor, RenderPipelineDescriptor,
 };
 
+#[derive(Clone, Copy)]
 pub enum PipelineType {
     Render,
     Painting,
@@ -31,7 +32,7 @@ impl PostProcess {
         // Load shaders
         let vs_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
             label: Some("Vertex Shader"),
-            source: wgpu::util::make_spirv(crate::canvas::VS_MODULE_BYTES),
+            source: wgpu::util::make_spirv(crate::renderer::VS_MODULE_BYTES),
             flags: wgpu::ShaderFlags::VALIDATION,
         });
         let fs_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
@@ -97,7 +98,7 @@ impl PostProcess {
             module: &fs_module,
             entry_point: "main",
             targets: &[wgpu::ColorTargetState {
-                format: crate::canvas::RENDER_TEXTURE_FORMAT,
+                format: crate::renderer::RENDER_TEXTURE_FORMAT,
                 blend: Some(BlendState {
                     color: wgpu::BlendComponent::REPLACE,
                     alpha: wgpu::BlendComponent::REPLACE,
@@ -134,7 +135,7 @@ impl PostProcess {
             module: &fs_module,
             entry_point: "main",
             targets: &[wgpu::ColorTargetState {
-                format: crate::canvas::PAINTING_TEXTURE_FORMAT,
+                format: crate::renderer::PAINTING_TEXTURE_FORMAT,
                 blend: Some(BlendState {
                     color: wgpu::BlendComponent::REPLACE,
                     alpha: wgpu::BlendComponent::REPLACE,
@@ -0,0 +1,107 @@
+use crate::uniforms::{load_uniforms_from_json, UserUniform};
+use crate::utils::f32_to_json;
+use crate::vector::Vector2;
+
+/// A named snapshot of every user uniform, plus the time and mouse position, to switch back to or morph between.
+#[derive(Clone)]
+pub struct Preset {
+    /// Name shown in the Dashboard. Unique within a presets file.
+    pub name: String,
+    /// Time in seconds, matching `u_time`.
+    pub time: f32,
+    /// Frame number, matching `u_frame_num`.
+    pub frame: u32,
+    /// Mouse position in pixels, matching the xy of `u_mouse_info`.
+    pub mouse: Vector2,
+    /// Values of the user uniforms, in the order they are bound.
+    pub uniforms: Vec<UserUniform>,
+}
+
+impl Preset {
+    /// Read a preset from a presets file entry, see [load_presets_from_json()]. Missing settings are zero.
+    /// * `name` - Name of the preset.
+    /// * `data` - JSON object describing the preset.
+    pub fn from_json(name: &str, data: &json::JsonValue) -> Preset {
+        Preset {
+            name: String::from(name),
+            time: data["time"].as_f32().unwrap_or(0.0),
+            frame: data["frame"].as_u32().unwrap_or(0),
+            mouse: Vector2::new(
+                data["mouse"][0].as_f32().unwrap_or(0.0),
+                data["mouse"][1].as_f32().unwrap_or(0.0),
+            ),
+            uniforms: load_uniforms_from_json(data),
+        }
+    }
+
+    /// The preset as stored in a presets file, see [load_presets_from_json()].
+    pub fn to_json(&self) -> json::JsonValue {
+        let mut uniforms = json::JsonValue::new_object();
+        for uniform in &self.uniforms {
+            uniforms[uniform.name.as_str()] = uniform.to_json();
+        }
+        json::object! {
+            "time": f32_to_json(self.time),
+            "frame": self.frame,
+            "mouse": json::array![f32_to_json(self.mouse.x), f32_to_json(self.mouse.y)],
+            "uniforms": uniforms,
+        }
+    }
+
+    /// Blend this preset into another. The time, mouse position and every component of each uniform are interpolated,
+    /// see [UserUniform::lerp()]. Uniforms missing from `other` or of a different type keep their value from this preset.
+    /// * `other` - The preset to morph towards.
+    /// * `t` - Progress from this preset at 0 to `other` at 1.
+    pub fn morph(&self, other: &Preset, t: f32) -> Preset {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Preset {
+            name: match t < 0.5 {
+                true => self.name.clone(),
+                false => other.name.clone(),
+            },
+            time: lerp(self.time, other.time),
+            frame: lerp(self.frame as f32, other.frame as f32).round() as u32,
+            mouse: Vector2::new(
+                lerp(self.mouse.x, other.mouse.x),
+                lerp(self.mouse.y, other.mouse.y),
+            ),
+            uniforms: self
+                .uniforms
+                .iter()
+                .map(
+                    |uniform| match other.uniforms.iter().find(|u| u.name == uniform.name) {
+                        Some(target) => uniform.lerp(target, t as f64),
+                        None => uniform.clone(),
+                    },
+                )
+                .collect(),
+        }
+    }
+}
+
+/// Loads named presets of user uniforms from a presets file.
+/// Each preset has the time and frame number, the mouse position in pixels, and the uniforms in the same form
+/// as the uniforms file, see [crate::uniforms::load_uniforms_from_json()].
+/// Example valid format:
+/// ```text
+/// "presets": {
+///     "calm": { "time": 12.5, "frame": 750, "mouse": [640.0, 360.0], "uniforms": { "zoom": ["f32", 2.0] } },
+///     "storm": { "time": 3.0, "frame": 180, "mouse": [0.0, 0.0], "uniforms": { "zoom": ["f32", 9.5] } }
+/// }
+/// ```
+/// Returns the presets in the order they are listed in the file.
+pub fn load_presets_from_json(data: &json::JsonValue) -> Vec<Preset> {
+    data["presets"]
+        .entries()
+        .map(|(name, entry)| Preset::from_json(name, entry))
+        .collect()
+}
+
+/// Describe presets in the form read by [load_presets_from_json()], as the whole presets file.
+pub fn presets_to_json(presets: &[Preset]) -> json::JsonValue {
+    let mut data = json::JsonValue::new_object();
+    for preset in presets {
+        data[preset.name.as_str()] = preset.to_json();
+    }
+    json::object! { "presets": data }
+}
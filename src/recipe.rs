@@ -0,0 +1,198 @@
+use crate::painting::PaintingFormat;
+use crate::renderer::Renderer;
+use crate::uniforms::{load_uniforms_from_json, Uniforms, UserUniform};
+use crate::vector::UIntVector2;
+use log::{error, warn};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Key the recipe is stored under in PNG text chunks and OpenEXR header attributes.
+pub static RECIPE_KEY: &str = "Easel Recipe";
+
+/// The files a shader is rendered from, as given on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderSources {
+    /// Fragment shader, GLSL source or SPIR-V binary.
+    pub shader: String,
+    /// Images bound to the shader, in binding order.
+    pub textures: Vec<String>,
+    /// Post-processing shaders, in the order they are applied.
+    pub postprocess: Vec<String>,
+}
+
+/// Everything needed to reproduce a painting.
+/// Embedded in every painting written by [crate::painting::AsyncPaintingWriter] and also written as a sidecar JSON file.
+#[derive(Clone)]
+pub struct PaintingRecipe {
+    /// The files the painting was rendered from.
+    pub sources: ShaderSources,
+    /// SHA-256 of the fragment shader file at the time of painting, in hex.
+    pub shader_hash: String,
+    /// SHA-256 of each post-processing shader file, in the same order as [ShaderSources::postprocess].
+    pub postprocess_hashes: Vec<String>,
+    /// The built-in uniforms the painting was rendered with.
+    pub uniforms: Uniforms,
+    /// Every user-specified uniform, in declaration order.
+    pub user_uniforms: Vec<UserUniform>,
+    /// Width and height of the painting.
+    pub resolution: UIntVector2,
+    /// Format the painting was written in.
+    pub format: PaintingFormat,
+    /// Version of Easel that rendered the painting.
+    pub easel_version: String,
+}
+
+/// Private helper that hashes a file on disk. Returns an empty string if the file can't be read.
+fn hash_file(file: &str) -> String {
+    match std::fs::read(file) {
+        Ok(bytes) => format!("{:x}", Sha256::digest(&bytes)),
+        Err(e) => {
+            error!("Error reading {} to hash for painting recipe: {}", file, e);
+            String::new()
+        }
+    }
+}
+
+impl PaintingRecipe {
+    /// Capture the current state of a [Renderer] for a painting about to be rendered.
+    /// Shader files are hashed as they are on disk now, so call this right before rendering.
+    /// * `sources` - The files the renderer was created from.
+    /// * `renderer` - Renderer the painting will be rendered with.
+    /// * `resolution` - Width and height of the painting.
+    /// * `format` - Format the painting will be written in.
+    pub fn new(
+        sources: ShaderSources,
+        renderer: &Renderer,
+        resolution: UIntVector2,
+        format: PaintingFormat,
+    ) -> Self {
+        PaintingRecipe {
+            shader_hash: hash_file(&sources.shader),
+            postprocess_hashes: sources.postprocess.iter().map(|f| hash_file(f)).collect(),
+            sources,
+            uniforms: renderer.painting_uniforms(resolution),
+            user_uniforms: renderer.user_uniforms.clone(),
+            resolution,
+            format,
+            easel_version: String::from(env!("CARGO_PKG_VERSION")),
+        }
+    }
+
+    /// Name of the software that wrote the painting, such as `Easel 1.0.7`.
+    pub fn software(&self) -> String {
+        format!("Easel {}", self.easel_version)
+    }
+
+    /// Describe the recipe as JSON.
+    /// User uniforms are stored under `"uniforms"` in the same form as a uniforms file,
+    /// so the recipe can be passed to [crate::uniforms::load_uniforms_from_json()] directly.
+    pub fn to_json(&self) -> json::JsonValue {
+        let mut painting = json::object! {
+            "width": self.resolution.x,
+            "height": self.resolution.y,
+            "format": self.format.name(),
+        };
+        if let PaintingFormat::Jpeg(quality) = self.format {
+            painting["quality"] = quality.into();
+        }
+        let mut postprocess = json::JsonValue::new_array();
+        for (file, hash) in self
+            .sources
+            .postprocess
+            .iter()
+            .zip(&self.postprocess_hashes)
+        {
+            postprocess
+                .push(json::object! { "file": file.as_str(), "sha256": hash.as_str() })
+                .unwrap();
+        }
+        let mut user_uniforms = json::JsonValue::new_object();
+        for uniform in &self.user_uniforms {
+            user_uniforms[uniform.name.as_str()] = uniform.to_json();
+        }
+        let textures: Vec<&str> = self.sources.textures.iter().map(|t| t.as_str()).collect();
+
+        json::object! {
+            "easel version": self.easel_version.as_str(),
+            "shader": json::object! {
+                "file": self.sources.shader.as_str(),
+                "sha256": self.shader_hash.as_str(),
+            },
+            "textures": textures,
+            "postprocess": postprocess,
+            "painting": painting,
+            "builtin uniforms": self.uniforms.to_json(),
+            "uniforms": user_uniforms,
+        }
+    }
+
+    /// Read a recipe written by [Self::to_json()], such as from the sidecar file of a painting.
+    /// Returns an error message if the shader file or painting resolution and format are missing.
+    pub fn from_json(data: &json::JsonValue) -> Result<PaintingRecipe, String> {
+        let shader = data["shader"]["file"]
+            .as_str()
+            .ok_or("Recipe is missing the shader file")?;
+        let painting = &data["painting"];
+        let (width, height) = match (painting["width"].as_u32(), painting["height"].as_u32()) {
+            (Some(width), Some(height)) => (width, height),
+            _ => return Err(String::from("Recipe is missing the painting resolution")),
+        };
+        let format_name = painting["format"].as_str().unwrap_or("");
+        let mut format = PaintingFormat::from_name(format_name).ok_or(format!(
+            "Recipe has unknown painting format {}",
+            format_name
+        ))?;
+        if let PaintingFormat::Jpeg(quality) = &mut format {
+            *quality = painting["quality"].as_u8().unwrap_or(*quality);
+        }
+
+        let strings = |v: &json::JsonValue| -> Vec<String> {
+            v.members()
+                .filter_map(|s| s.as_str().map(String::from))
+                .collect()
+        };
+        let postprocess = &data["postprocess"];
+        let user_uniforms = load_uniforms_from_json(data);
+
+        Ok(PaintingRecipe {
+            sources: ShaderSources {
+                shader: String::from(shader),
+                textures: strings(&data["textures"]),
+                postprocess: postprocess
+                    .members()
+                    .filter_map(|p| p["file"].as_str().map(String::from))
+                    .collect(),
+            },
+            shader_hash: String::from(data["shader"]["sha256"].as_str().unwrap_or("")),
+            postprocess_hashes: postprocess
+                .members()
+                .map(|p| String::from(p["sha256"].as_str().unwrap_or("")))
+                .collect(),
+            uniforms: Uniforms::from_json(&data["builtin uniforms"]),
+            user_uniforms,
+            resolution: UIntVector2::new(width, height),
+            format,
+            easel_version: String::from(data["easel version"].as_str().unwrap_or("")),
+        })
+    }
+
+    /// Whether the shader and post-processing files on disk still match the hashes in the recipe.
+    /// Logs a warning for each file that has changed.
+    pub fn sources_unchanged(&self) -> bool {
+        let mut unchanged = true;
+        let files = std::iter::once(&self.sources.shader).chain(&self.sources.postprocess);
+        let hashes = std::iter::once(&self.shader_hash).chain(&self.postprocess_hashes);
+        for (file, hash) in files.zip(hashes) {
+            if hash_file(file) != *hash {
+                warn!("{} has changed since the painting was rendered", file);
+                unchanged = false;
+            }
+        }
+        unchanged
+    }
+
+    /// Path of the sidecar JSON file written next to a painting, the painting path with a `.json` extension.
+    pub fn sidecar_path(painting: &str) -> PathBuf {
+        Path::new(painting).with_extension("json")
+    }
+}
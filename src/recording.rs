@@ -1,16 +1,100 @@
+use crate::animation::{self, AnimationFormat, AnimationOptions};
+use crate::encoder::EncoderProfile;
+use crate::painting::{AsyncPaintingWriter, PaintingFormat};
 use crate::{utils, vector::UIntVector2};
 use futures::executor::block_on;
-use log::info;
-use std::io::Write;
+use half::f16;
+use log::{error, info};
+use std::io::{Read, Write};
+use std::path::Path;
 use std::process::{Command, Stdio};
 use std::thread::JoinHandle;
 use wgpu::TextureFormat;
 
 pub static MOVIE_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+/// Format of high bit depth movie frames, rendered through the painting pipeline with the sRGB conversion applied.
+/// Piped to FFmpeg as 16-bit unsigned integers per component.
+pub static HDR_MOVIE_TEXTURE_FORMAT: wgpu::TextureFormat = crate::renderer::PAINTING_TEXTURE_FORMAT;
+
+/// The pixel formats frames are handed to a [Recorder] in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// 8-bit RGBA in sRGB, rendered by the movie pipeline in [MOVIE_TEXTURE_FORMAT].
+    Rgba8,
+    /// Half float RGBA, rendered by the painting pipeline. Contains whether the sRGB conversion is applied.
+    RgbaHalf(bool),
+}
+
+/// Pixels of a frame that has already been read back from the GPU, tightly packed.
+pub enum FrameData {
+    /// Pixels of [FrameFormat::Rgba8].
+    Rgba8(Vec<u8>),
+    /// Pixels of [FrameFormat::RgbaHalf].
+    RgbaHalf(Vec<f16>),
+}
+
+/// Where a [Recorder] writes its frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecorderBackend {
+    /// Encode a movie by piping frames to FFmpeg.
+    Ffmpeg,
+    /// Write each frame as a numbered image of the given format into a directory.
+    ImageSequence(PaintingFormat),
+    /// Collect the frames and write them as a looping animation once recording stops.
+    Animation(AnimationFormat),
+}
+
+impl RecorderBackend {
+    /// All backends, in the order they are listed in the GUI.
+    pub const ALL: [RecorderBackend; 11] = [
+        RecorderBackend::Ffmpeg,
+        RecorderBackend::Animation(AnimationFormat::Gif),
+        RecorderBackend::Animation(AnimationFormat::Apng),
+        RecorderBackend::ImageSequence(PaintingFormat::Png8),
+        RecorderBackend::ImageSequence(PaintingFormat::Png16),
+        RecorderBackend::ImageSequence(PaintingFormat::Tiff16),
+        RecorderBackend::ImageSequence(PaintingFormat::Tiff16Lzw),
+        RecorderBackend::ImageSequence(PaintingFormat::Tiff16Deflate),
+        RecorderBackend::ImageSequence(PaintingFormat::TiffFloat32),
+        RecorderBackend::ImageSequence(PaintingFormat::ExrHalf),
+        RecorderBackend::ImageSequence(PaintingFormat::ExrFloat),
+    ];
+
+    /// Short name used to select the backend on the command line.
+    /// Image sequences and animations use the name of their format.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RecorderBackend::Ffmpeg => "ffmpeg",
+            RecorderBackend::ImageSequence(format) => format.name(),
+            RecorderBackend::Animation(format) => format.name(),
+        }
+    }
+
+    /// Look up a backend by its [Self::name()].
+    pub fn from_name(name: &str) -> Option<RecorderBackend> {
+        RecorderBackend::ALL
+            .iter()
+            .copied()
+            .find(|backend| backend.name() == name)
+    }
+
+    /// Human-readable description of the backend, as shown in the GUI.
+    pub fn description(&self) -> String {
+        match self {
+            RecorderBackend::Ffmpeg => String::from("Movie (FFmpeg)"),
+            RecorderBackend::ImageSequence(format) => {
+                format!("Image Sequence, {}", format.description())
+            }
+            RecorderBackend::Animation(format) => String::from(format.description()),
+        }
+    }
+}
 
 enum RecorderToThreadSignal {
     Stop,
     Frame(wgpu::Buffer, UIntVector2),
+    /// A frame that has already been read back from the GPU.
+    FrameData(FrameData),
 }
 
 enum ThreadToRecorderSignal {
@@ -19,6 +103,8 @@ enum ThreadToRecorderSignal {
 }
 
 pub struct Recorder {
+    /// The format frames sent to this recorder must be rendered in.
+    pub frame_format: FrameFormat,
     join_handle: JoinHandle<()>,
     sender: std::sync::mpsc::Sender<RecorderToThreadSignal>,
     receiver: std::sync::mpsc::Receiver<ThreadToRecorderSignal>,
@@ -28,23 +114,39 @@ pub struct Recorder {
 }
 
 impl Recorder {
+    /// Construct a recorder that pipes frames to FFmpeg to encode a movie.
+    /// * `width` - Width of each frame.
+    /// * `height` - Height of each frame.
+    /// * `texture_format` - Format of the frames, [MOVIE_TEXTURE_FORMAT] or [HDR_MOVIE_TEXTURE_FORMAT].
+    ///   Usually [EncoderProfile::texture_format()].
+    /// * `framerate` - Frames per second of the movie.
+    /// * `profile` - FFmpeg output options to encode the movie with.
+    /// * `filename` - Movie file to write. The extension should match the container of the profile.
     pub fn new(
         width: u32,
         height: u32,
         texture_format: TextureFormat,
         framerate: u32,
+        profile: &EncoderProfile,
         filename: String,
     ) -> Recorder {
-        let pix_fmt = match texture_format{
-            TextureFormat::Rgba8UnormSrgb => "rgba",
-            _ => panic!("Unsupported texture format. Only the following texture formats are supported: Rgba8UnormSrgb")
+        // Half float frames are transcoded to 16-bit integers in native byte order.
+        let (pix_fmt, frame_format) = match texture_format {
+            TextureFormat::Rgba8UnormSrgb => ("rgba", FrameFormat::Rgba8),
+            TextureFormat::Rgba16Float if cfg!(target_endian = "little") => {
+                ("rgba64le", FrameFormat::RgbaHalf(true))
+            }
+            TextureFormat::Rgba16Float => ("rgba64be", FrameFormat::RgbaHalf(true)),
+            _ => panic!("Unsupported texture format. Only the following texture formats are supported: Rgba8UnormSrgb, Rgba16Float")
         };
         let resolution_string = format!("{}x{}", width.to_string(), height.to_string());
         let (our_sender, thread_receiver) = std::sync::mpsc::channel();
         let (thread_sender, our_receiver) = std::sync::mpsc::channel();
         let framerate_str = framerate.to_string();
+        let output_args = profile.output_args();
+        info!("Encoding movie with profile {}", profile.name);
         let join_handle = std::thread::spawn(move || {
-            let mut args = vec![
+            let mut args: Vec<String> = [
                 "-hide_banner",
                 "-y",
                 "-f",
@@ -55,49 +157,45 @@ impl Recorder {
                 &resolution_string,
                 "-pixel_format",
                 pix_fmt,
-            ];
-            if cfg!(target_os = "windows") {
-                args.extend_from_slice(&[
-                    "-hwaccel",
-                    "cuda",
-                    "-i",
-                    "-",
-                    "-c:v",
-                    "hevc_nvenc",
-                    "-preset",
-                    "2", // medium
-                    "-pix_fmt",
-                    "yuv420p",
-                    "-r",
-                    &framerate_str,
-                    &filename,
-                ]);
-            } else {
-                args.extend_from_slice(&[
-                    "-i",
-                    "-",
-                    "-c:v",
-                    "libx265",
-                    "-pix_fmt",
-                    "yuv420p",
-                    "-x265-params",
-                    "lossless=1",
-                    "-r",
-                    &framerate_str,
-                    &filename,
-                ]);
-            }
-            let mut ffmpeg_process = Command::new("ffmpeg")
+                "-i",
+                "-",
+            ]
+            .iter()
+            .map(|arg| String::from(*arg))
+            .collect();
+            args.extend(output_args);
+            args.extend_from_slice(&[String::from("-r"), framerate_str.clone(), filename]);
+            let mut ffmpeg_process = match Command::new("ffmpeg")
                 .args(&args)
                 .stdin(Stdio::piped())
+                .stderr(Stdio::piped())
                 .spawn()
-                .unwrap();
+            {
+                Ok(process) => process,
+                Err(e) => {
+                    error!("Error launching FFmpeg, is it installed? {}", e);
+                    thread_sender
+                        .send(ThreadToRecorderSignal::Finished)
+                        .unwrap();
+                    return;
+                }
+            };
+
+            // Drain FFmpeg's output as it runs so it never blocks writing to a full pipe.
+            let mut stderr = ffmpeg_process.stderr.take().unwrap();
+            let stderr_handle = std::thread::spawn(move || {
+                let mut output = String::new();
+                stderr.read_to_string(&mut output).ok();
+                output
+            });
 
             // Notify Recorder struct that we are ready to start receiving frames.
             thread_sender.send(ThreadToRecorderSignal::Ready).unwrap();
 
             let mut pixel_data = Vec::<u8>::new();
             let mut frame_count: usize = 0;
+            // Set if FFmpeg stops accepting frames, such as when it exits early on invalid options.
+            let mut write_error = None;
             loop {
                 let msg = thread_receiver.recv().unwrap();
                 match msg {
@@ -107,35 +205,257 @@ impl Recorder {
                     }
                     RecorderToThreadSignal::Frame(buffer, resolution) => {
                         let pipe_in = ffmpeg_process.stdin.as_mut().unwrap();
-                        block_on(utils::transcode_frame_data_for_movie(
+                        match frame_format {
+                            FrameFormat::Rgba8 => block_on(utils::transcode_frame_data_for_movie(
+                                buffer,
+                                resolution,
+                                &mut pixel_data,
+                            )),
+                            FrameFormat::RgbaHalf(_) => block_on(utils::transcode_painting_data(
+                                buffer,
+                                resolution,
+                                &mut pixel_data,
+                            )),
+                        }
+                        if let Err(e) = pipe_in.write_all(&pixel_data) {
+                            write_error = Some(e);
+                            break;
+                        }
+                        frame_count += 1;
+                        pixel_data.clear();
+                    }
+                    RecorderToThreadSignal::FrameData(frame_data) => {
+                        let pipe_in = ffmpeg_process.stdin.as_mut().unwrap();
+                        let result = match (frame_format, frame_data) {
+                            (FrameFormat::Rgba8, FrameData::Rgba8(frame_data)) => {
+                                pipe_in.write_all(&frame_data)
+                            }
+                            (FrameFormat::RgbaHalf(_), FrameData::RgbaHalf(components)) => {
+                                utils::transcode_painting_components(&components, &mut pixel_data);
+                                let result = pipe_in.write_all(&pixel_data);
+                                pixel_data.clear();
+                                result
+                            }
+                            _ => {
+                                error!("Frame does not match the format of the FFmpeg recorder, dropping frame.");
+                                continue;
+                            }
+                        };
+                        if let Err(e) = result {
+                            write_error = Some(e);
+                            break;
+                        }
+                        frame_count += 1;
+                    }
+                }
+            }
+
+            if write_error.is_none() {
+                write_error = ffmpeg_process.stdin.as_mut().unwrap().flush().err();
+            }
+            // Close the pipe so FFmpeg sees the end of the movie.
+            drop(ffmpeg_process.stdin.take());
+            let status = ffmpeg_process
+                .wait()
+                .expect("Failed to wait on FFmpeg process");
+            let output = stderr_handle.join().unwrap_or_default();
+
+            if let Some(e) = write_error {
+                error!(
+                    "FFmpeg stopped accepting frames after {} frames ({}) and finished with status: {}\n{}",
+                    frame_count, e, status, output
+                );
+            } else if !status.success() {
+                error!(
+                    "FFmpeg processed {} frames and failed with status: {}\n{}",
+                    frame_count, status, output
+                );
+            } else {
+                info!(
+                    "FFMpeg processed {} frames and finished with status: {}",
+                    frame_count, status
+                );
+            }
+            thread_sender
+                .send(ThreadToRecorderSignal::Finished)
+                .unwrap();
+        });
+
+        Recorder {
+            frame_format,
+            join_handle,
+            sender: our_sender,
+            receiver: our_receiver,
+            done: false,
+            ready: false,
+            stop_signal_received: false,
+        }
+    }
+
+    /// Construct a recorder that writes each frame as a numbered image into a directory,
+    /// such as `Muybridge/Muybridge_00000.png`. The directory is created if it does not exist.
+    /// Frames are rendered through the painting pipeline, with the sRGB conversion applied unless the format is linear.
+    /// * `width` - Width of each frame.
+    /// * `height` - Height of each frame.
+    /// * `format` - Format to encode each frame with.
+    /// * `directory` - Directory to write the frames to, relative to the working directory. Also used to name the frames.
+    pub fn image_sequence(
+        width: u32,
+        height: u32,
+        format: PaintingFormat,
+        directory: String,
+    ) -> Recorder {
+        let (our_sender, thread_receiver) = std::sync::mpsc::channel();
+        let (thread_sender, our_receiver) = std::sync::mpsc::channel();
+        let resolution = UIntVector2::new(width, height);
+        let join_handle = std::thread::spawn(move || {
+            if let Err(e) = std::fs::create_dir_all(&directory) {
+                error!(
+                    "Error creating image sequence directory {}: {}",
+                    directory, e
+                );
+                thread_sender
+                    .send(ThreadToRecorderSignal::Finished)
+                    .unwrap();
+                return;
+            }
+            let stem = match Path::new(&directory).file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => String::from("frame"),
+            };
+
+            // Notify Recorder struct that we are ready to start receiving frames.
+            thread_sender.send(ThreadToRecorderSignal::Ready).unwrap();
+
+            let mut frame_count: usize = 0;
+            loop {
+                let pixel_data = match thread_receiver.recv().unwrap() {
+                    RecorderToThreadSignal::Stop => {
+                        info!("Stop signal received.");
+                        break;
+                    }
+                    RecorderToThreadSignal::Frame(buffer, resolution) => {
+                        let mut pixel_data = Vec::new();
+                        block_on(utils::read_painting_data(
                             buffer,
                             resolution,
                             &mut pixel_data,
                         ));
-                        pipe_in.write_all(&pixel_data).unwrap();
-                        frame_count += 1;
-                        pixel_data.clear();
+                        pixel_data
+                    }
+                    RecorderToThreadSignal::FrameData(FrameData::RgbaHalf(pixel_data)) => {
+                        pixel_data
                     }
+                    RecorderToThreadSignal::FrameData(FrameData::Rgba8(_)) => {
+                        error!("Image sequence recorder only accepts half float frames, dropping frame.");
+                        continue;
+                    }
+                };
+                let filename = Path::new(&directory).join(format!(
+                    "{}_{:05}.{}",
+                    stem,
+                    frame_count,
+                    format.extension()
+                ));
+                if !AsyncPaintingWriter::write_frame(
+                    pixel_data,
+                    resolution,
+                    format,
+                    &filename.to_string_lossy(),
+                ) {
+                    error!("Frame {} is incomplete", filename.display());
                 }
+                frame_count += 1;
             }
 
-            ffmpeg_process.stdin.as_mut().unwrap().flush().unwrap();
-            let output = ffmpeg_process
-                .wait_with_output()
-                .expect("Failed to wait on FFmpeg process");
+            info!(
+                "Wrote {} frames to image sequence {}",
+                frame_count, directory
+            );
+            thread_sender
+                .send(ThreadToRecorderSignal::Finished)
+                .unwrap();
+        });
+
+        Recorder {
+            frame_format: FrameFormat::RgbaHalf(!format.is_linear()),
+            join_handle,
+            sender: our_sender,
+            receiver: our_receiver,
+            done: false,
+            ready: false,
+            stop_signal_received: false,
+        }
+    }
+
+    /// Construct a recorder that keeps every frame in memory and writes them as a looping animation
+    /// once stopped, such as an animated GIF for a short social media loop. Does not need FFmpeg.
+    /// Frames must be [FrameFormat::Rgba8].
+    /// * `width` - Width of each frame.
+    /// * `height` - Height of each frame.
+    /// * `format` - File format of the animation.
+    /// * `framerate` - Frames per second to play the animation at.
+    /// * `options` - Dithering and ping-pong playback.
+    /// * `filename` - File to write the animation to.
+    pub fn animation(
+        width: u32,
+        height: u32,
+        format: AnimationFormat,
+        framerate: u32,
+        options: AnimationOptions,
+        filename: String,
+    ) -> Recorder {
+        let (our_sender, thread_receiver) = std::sync::mpsc::channel();
+        let (thread_sender, our_receiver) = std::sync::mpsc::channel();
+        let resolution = UIntVector2::new(width, height);
+        let join_handle = std::thread::spawn(move || {
+            // Notify Recorder struct that we are ready to start receiving frames.
+            thread_sender.send(ThreadToRecorderSignal::Ready).unwrap();
+
+            let mut frames: Vec<Vec<u8>> = Vec::new();
+            loop {
+                match thread_receiver.recv().unwrap() {
+                    RecorderToThreadSignal::Stop => {
+                        info!("Stop signal received.");
+                        break;
+                    }
+                    RecorderToThreadSignal::Frame(buffer, resolution) => {
+                        let mut pixel_data = Vec::new();
+                        block_on(utils::transcode_frame_data_for_movie(
+                            buffer,
+                            resolution,
+                            &mut pixel_data,
+                        ));
+                        frames.push(pixel_data);
+                    }
+                    RecorderToThreadSignal::FrameData(FrameData::Rgba8(pixel_data)) => {
+                        frames.push(pixel_data);
+                    }
+                    RecorderToThreadSignal::FrameData(FrameData::RgbaHalf(_)) => {
+                        error!("Animation recorder only accepts 8-bit frames, dropping frame.");
+                    }
+                }
+            }
 
             info!(
-                "FFMpeg processed {} frames and finished with status: {}",
-                frame_count, output.status
+                "Writing {} frames to {} {}",
+                frames.len(),
+                format.description(),
+                filename
             );
+            match animation::write_animation(
+                &frames, resolution, framerate, format, options, &filename,
+            ) {
+                Ok(()) => info!("Finished writing {}", filename),
+                Err(e) => error!("Error writing animation {}: {}", filename, e),
+            }
             thread_sender
                 .send(ThreadToRecorderSignal::Finished)
                 .unwrap();
-            // std::io::stdout().write_all(&output.stdout).unwrap();
-            // std::io::stderr().write_all(&output.stderr).unwrap();
         });
 
         Recorder {
+            frame_format: FrameFormat::Rgba8,
             join_handle,
             sender: our_sender,
             receiver: our_receiver,
@@ -145,6 +465,42 @@ impl Recorder {
         }
     }
 
+    /// Construct a recorder for the given backend.
+    /// * `profile` - FFmpeg output options, only used by [RecorderBackend::Ffmpeg].
+    /// * `animation_options` - Only used by [RecorderBackend::Animation].
+    /// * `filename` - Movie or animation file to write, or directory to write the frames of an image sequence to.
+    pub fn with_backend(
+        backend: RecorderBackend,
+        profile: &EncoderProfile,
+        animation_options: AnimationOptions,
+        width: u32,
+        height: u32,
+        framerate: u32,
+        filename: String,
+    ) -> Recorder {
+        match backend {
+            RecorderBackend::Ffmpeg => Recorder::new(
+                width,
+                height,
+                profile.texture_format(),
+                framerate,
+                profile,
+                filename,
+            ),
+            RecorderBackend::ImageSequence(format) => {
+                Recorder::image_sequence(width, height, format, filename)
+            }
+            RecorderBackend::Animation(format) => Recorder::animation(
+                width,
+                height,
+                format,
+                framerate,
+                animation_options,
+                filename,
+            ),
+        }
+    }
+
     /// Whether this recorder has finished processing all frames.
     pub fn poll(&mut self) -> bool {
         let msg_result = self.receiver.try_recv();
@@ -164,9 +520,26 @@ impl Recorder {
         resolution: UIntVector2,
         _timestamp: std::time::Instant,
     ) {
-        self.sender
+        if self
+            .sender
             .send(RecorderToThreadSignal::Frame(buffer, resolution))
-            .unwrap();
+            .is_err()
+        {
+            error!("Recorder has already finished, dropping frame.");
+        }
+    }
+
+    /// Add a frame that has already been read back from the GPU, such as by [crate::renderer::Renderer::render_movie_frame()].
+    /// Frames are encoded in the order they are added.
+    /// Frames must be in the [Self::frame_format] of this recorder.
+    pub fn add_frame_data(&self, frame_data: FrameData) {
+        if self
+            .sender
+            .send(RecorderToThreadSignal::FrameData(frame_data))
+            .is_err()
+        {
+            error!("Recorder has already finished, dropping frame.");
+        }
     }
 
     pub fn stop(&mut self) {
@@ -174,7 +547,8 @@ impl Recorder {
             panic!("Attempting to request stop on recorder that has already stopped!");
         }
         info!("Sending stop signal to FFMpeg.");
-        self.sender.send(RecorderToThreadSignal::Stop).unwrap();
+        // The recorder may have finished on its own, such as when FFmpeg exits early.
+        self.sender.send(RecorderToThreadSignal::Stop).ok();
         self.stop_signal_received = true;
     }
 
@@ -0,0 +1,897 @@
+use crate::postprocessing::{PipelineType, PostProcess};
+use crate::recording::{FrameData, FrameFormat, MOVIE_TEXTURE_FORMAT};
+use crate::texture::{default_color_sampler, AssetTexture};
+use crate::uniforms::{same_std140_layout, std140_bytes, Uniforms, UserUniform};
+use crate::utils;
+use crate::vector::{UIntVector2, Vector4};
+use futures::FutureExt;
+use std::future::Future;
+use std::num::{NonZeroU32, NonZeroU64};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupEntry, BindGroupLayoutEntry, BindingResource, BufferBinding, Extent3d, LoadOp,
+    Operations, Origin3d, PowerPreference, RequestAdapterOptions,
+};
+
+/// Pre-compile vertex shader that renders a full-screen quad.
+pub static VS_MODULE_BYTES: &[u8] = include_bytes!("../shaders/vert.spv");
+/// The [wgpu::TextureFormat] used when rendering to screen.
+/// We render to linear color as so that post-process ops are correctly applied in linear space.
+/// A final render pass is done before presenting to screen to convert to sRGB.
+pub static RENDER_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+/// The [wgpu::TextureFormat] used when rendering off-screen painting to write to disk.
+pub static PAINTING_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// Built-in shader used as a post-processing effect to apply gamma sRGB conversion for painting.
+/// This is needed as the [PAINTING_TEXTURE_FORMAT] does not perform automatic sRGB conversion for us.
+static POST_PROCESS_SRGB_SHADER_BYTES: &[u8] = include_bytes!("../shaders/post-process-srgb.spv");
+
+/// Request an adapter and device suitable for running Easel shaders.
+/// Attempts to use the highest performance GPU on the system.
+/// * `instance` - The [wgpu::Instance] to request the adapter from.
+/// * `compatible_surface` - Optional surface the adapter must be able to present to. Use `None` when rendering off-screen.
+pub async fn request_device(
+    instance: &wgpu::Instance,
+    compatible_surface: Option<&wgpu::Surface>,
+) -> (wgpu::Adapter, wgpu::Device, wgpu::Queue) {
+    let adapter = instance
+        .request_adapter(&RequestAdapterOptions {
+            compatible_surface,
+            power_preference: PowerPreference::HighPerformance,
+        })
+        .await
+        .expect("Unable to find a suitable GPU adapter.");
+    // From: https://docs.rs/wgpu/0.6.2/wgpu/struct.Limits.html#structfield.max_push_constant_size
+    let max_push_constant_size = match wgpu::BackendBit::PRIMARY {
+        wgpu::BackendBit::VULKAN => 256,
+        wgpu::BackendBit::DX12 => 256,
+        wgpu::BackendBit::METAL => 4096,
+        _ => 128,
+    };
+    let limits = wgpu::Limits {
+        max_push_constant_size,
+        ..Default::default()
+    };
+    let device_desc = wgpu::DeviceDescriptor {
+        label: None,
+        features: adapter.features(),
+        limits,
+    };
+
+    let (device, queue) = adapter.request_device(&device_desc, None).await.unwrap();
+    (adapter, device, queue)
+}
+
+/// Off-screen renderer for Easel shaders.
+/// Owns the render, painting and movie pipelines built from a fragment shader, the uniforms bound to it,
+/// any input textures, and the chain of post-processing effects.
+/// Independent of any windowing or GUI system, so it can be used to render paintings and movie frames
+/// into pixel buffers from any program.
+pub struct Renderer {
+    /// Handle to WebGPU Device.
+    pub device: wgpu::Device,
+    /// Handle to command dispatch queue on device.
+    pub queue: wgpu::Queue,
+    /// Render pipeline used for on-screen rendering.
+    render_pipeline: wgpu::RenderPipeline,
+    /// Render pipeline used for off-screen rendering. Will always include sRGB conversion post-processing effect.
+    painting_pipeline: wgpu::RenderPipeline,
+    /// Render pipeline use for off-screen rendering of movie frames.
+    movie_pipeline: wgpu::RenderPipeline,
+    /// Color with which to [wgpu::LoadOp::Clear] attachments to render passes.
+    pub clear_color: wgpu::Color,
+    /// Uniforms provided by Easel to all shaders.
+    /// Copied to the device on [Self::write_uniforms()].
+    pub uniforms: Uniforms,
+    /// Handle to device buffer where [Self::uniforms] are copied over.
+    uniforms_device_buffer: wgpu::Buffer,
+    /// Optional device buffer of user-provided uniforms.
+    user_uniforms_buffer: Option<wgpu::Buffer>,
+    /// Optional size of device buffer holding user-provided uniforms.
+    user_uniforms_buffer_size: Option<usize>,
+    /// List of user-provided uniforms, in the order they are bound. Copied to the device on [Self::write_uniforms()].
+    /// Values may change freely, but the types and order must not, see [Self::set_user_uniforms()].
+    pub user_uniforms: Vec<UserUniform>,
+    bind_groups: [wgpu::BindGroup; 2],
+    bind_group_layouts: [wgpu::BindGroupLayout; 2],
+    /// List of texture handles and their destination binding locations in the shader.
+    #[allow(dead_code)]
+    textures: Vec<AssetTexture>,
+    /// List of post-processing shaders.
+    postprocess_ops: Vec<PostProcess>,
+    /// Shader to apply sRGB Gamma for paintings.
+    srgb_postprocess: PostProcess,
+}
+
+impl Renderer {
+    /// Construct a new Renderer on its own device, without any window or surface.
+    /// * `fs_spirv_data` - Binary data of compiled fragment shader
+    /// * `images` - Optional array of images to bind to shader. Images are bound in the same order as specified here.
+    /// * `user_uniforms` - Optional array of user-specified uniforms to bind in shader, laid out as a std140 block in order.
+    /// * `resolution` - Reference resolution reported to shaders, in pixels.
+    pub async fn new(
+        fs_spirv_data: &[u8],
+        images: Option<Vec<image::DynamicImage>>,
+        user_uniforms: Option<Vec<UserUniform>>,
+        resolution: UIntVector2,
+    ) -> Self {
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let (_adapter, device, queue) = request_device(&instance, None).await;
+        Self::with_device(
+            device,
+            queue,
+            fs_spirv_data,
+            images,
+            user_uniforms,
+            resolution,
+        )
+    }
+
+    /// Construct a new Renderer on an existing device.
+    /// Use this when the device must also be able to present to a window surface.
+    /// See [Self::new()] for the remaining arguments.
+    pub fn with_device(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        fs_spirv_data: &[u8],
+        images: Option<Vec<image::DynamicImage>>,
+        user_uniforms: Option<Vec<UserUniform>>,
+        resolution: UIntVector2,
+    ) -> Self {
+        //------------------------------------------------------------------------------------------
+        // Create uniforms, device buffer, and bindings.
+        let mut uniforms = Uniforms::new();
+        uniforms.resolution = Vector4::new(resolution.x as f32, resolution.y as f32, 0.0, 0.0);
+        uniforms.num_textures = match &images {
+            Some(vec) => vec.len() as u32,
+            None => 0,
+        };
+        let descriptor = BufferInitDescriptor {
+            label: Some("Uniforms Buffer"),
+            contents: bytemuck::bytes_of(&uniforms),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        };
+        let u_buffer = device.create_buffer_init(&descriptor);
+
+        //------------------------------------------------------------------------------------------
+        // Bind custom uniforms, if provided
+        let mut custom_uniforms_buffer = None;
+        let mut custom_uniforms_buffer_size = 0;
+        if let Some(dem_uniforms) = &user_uniforms {
+            let bytes = std140_bytes(dem_uniforms);
+            custom_uniforms_buffer_size = bytes.len();
+
+            let desc = BufferInitDescriptor {
+                label: Some("Custom Uniforms Buffer"),
+                contents: &bytes,
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            };
+
+            custom_uniforms_buffer = Some(device.create_buffer_init(&desc));
+        }
+
+        //------------------------------------------------------------------------------------------
+        // Load textures.
+        let mut asset_textures = Vec::<AssetTexture>::new();
+        if let Some(vec) = images {
+            for an_image in &vec {
+                asset_textures.push(AssetTexture::new_with_image(an_image, &device, &queue));
+            }
+        }
+
+        //------------------------------------------------------------------------------------------
+        // Create the bind group layout and entries.
+        // Uniforms and our generated textures are set 0
+        let primary_bind_group_layout: wgpu::BindGroupLayout;
+        {
+            let mut bind_group_layout_entries = Vec::<wgpu::BindGroupLayoutEntry>::new();
+            // Uniforms are first.
+            bind_group_layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            });
+            if custom_uniforms_buffer.is_some() {
+                bind_group_layout_entries.push(wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                });
+            }
+            primary_bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: None,
+                    entries: &bind_group_layout_entries,
+                });
+        }
+
+        // In set 1, bind provided textures.
+        let secondary_bind_group_layout: wgpu::BindGroupLayout;
+        {
+            let mut bind_group_layout_entries = Vec::<wgpu::BindGroupLayoutEntry>::new();
+            // For now, we only have 1 sampler per set
+            bind_group_layout_entries.push(BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Sampler {
+                    filtering: true,
+                    comparison: false,
+                },
+                count: None,
+            });
+            for i in 1..=asset_textures.len() {
+                bind_group_layout_entries.push(BindGroupLayoutEntry {
+                    binding: i as u32,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                });
+            }
+            // Create the Bind Group Layout.
+            secondary_bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: None,
+                    entries: &bind_group_layout_entries,
+                });
+        }
+
+        //------------------------------------------------------------------------------------------
+        // Create Bind Groups from layouts.
+        let primary_bind_group: wgpu::BindGroup;
+        {
+            let mut primary_bind_group_entries: Vec<BindGroupEntry> = Vec::new();
+            // Provided Uniforms first.
+            primary_bind_group_entries.push(wgpu::BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Buffer(BufferBinding {
+                    buffer: &u_buffer,
+                    offset: 0,
+                    size: Some(NonZeroU64::new(std::mem::size_of_val(&uniforms) as u64).unwrap()),
+                }),
+            });
+            // Custom Uniforms next, if enabled.
+            if let Some(cu_buffer) = &custom_uniforms_buffer {
+                primary_bind_group_entries.push(wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: cu_buffer,
+                        offset: 0,
+                        size: Some(NonZeroU64::new(custom_uniforms_buffer_size as u64).unwrap()),
+                    }),
+                });
+            }
+
+            // Finally create the bind group.
+            primary_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Primary Bind Group"),
+                layout: &primary_bind_group_layout,
+                entries: &primary_bind_group_entries,
+            });
+        }
+
+        let secondary_bind_group: wgpu::BindGroup;
+        {
+            let mut secondary_bind_group_entries: Vec<BindGroupEntry> = Vec::new();
+            let default_sampler = default_color_sampler(&device);
+            secondary_bind_group_entries.push(BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Sampler(&default_sampler),
+            });
+            // Create texture views.
+            let mut tex_views = Vec::<wgpu::TextureView>::new();
+            for tex in &asset_textures {
+                let texture_view = tex.get_view(0);
+                tex_views.push(texture_view);
+            }
+            // Add texture view bindings.
+            for tex_bind_idx in 1..=tex_views.len() {
+                secondary_bind_group_entries.push(BindGroupEntry {
+                    binding: tex_bind_idx as u32,
+                    resource: BindingResource::TextureView(&tex_views[tex_bind_idx - 1]),
+                });
+            }
+            secondary_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Secondary Bind Group"),
+                layout: &secondary_bind_group_layout,
+                entries: &secondary_bind_group_entries,
+            });
+        }
+
+        //------------------------------------------------------------------------------------------
+        // Create render pipelines.
+        let bind_group_layouts = [primary_bind_group_layout, secondary_bind_group_layout];
+        let (render_pipeline, painting_pipeline, movie_pipeline) =
+            Self::build_pipelines(&device, &bind_group_layouts, fs_spirv_data);
+        let mut custom_size = None;
+        if custom_uniforms_buffer_size > 0 {
+            custom_size = Some(custom_uniforms_buffer_size);
+        }
+
+        Self {
+            srgb_postprocess: PostProcess::new(
+                &device,
+                Vec::from(POST_PROCESS_SRGB_SHADER_BYTES),
+                custom_uniforms_buffer.is_some(),
+            ),
+            device,
+            queue,
+            render_pipeline,
+            painting_pipeline,
+            movie_pipeline,
+            clear_color: wgpu::Color {
+                r: 0.1,
+                g: 0.2,
+                b: 0.3,
+                a: 1.0,
+            },
+            uniforms,
+            user_uniforms_buffer: custom_uniforms_buffer,
+            user_uniforms_buffer_size: custom_size,
+            user_uniforms: user_uniforms.unwrap_or_default(),
+            uniforms_device_buffer: u_buffer,
+            bind_groups: [primary_bind_group, secondary_bind_group],
+            bind_group_layouts,
+            textures: asset_textures,
+            postprocess_ops: vec![],
+        }
+    }
+
+    /// Private helper that builds the render, painting and movie pipelines for a fragment shader.
+    fn build_pipelines(
+        device: &wgpu::Device,
+        bind_group_layouts: &[wgpu::BindGroupLayout; 2],
+        fs_spirv_data: &[u8],
+    ) -> (
+        wgpu::RenderPipeline,
+        wgpu::RenderPipeline,
+        wgpu::RenderPipeline,
+    ) {
+        let vs_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Vertex Shader"),
+            source: wgpu::util::make_spirv(VS_MODULE_BYTES),
+            flags: wgpu::ShaderFlags::VALIDATION,
+        });
+        let fs_module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Painting Fragment Shader"),
+            source: wgpu::util::make_spirv(fs_spirv_data),
+            flags: wgpu::ShaderFlags::VALIDATION,
+        });
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Canvas Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layouts[0], &bind_group_layouts[1]],
+                push_constant_ranges: &[],
+            });
+        utils::create_pipelines(
+            device,
+            &render_pipeline_layout,
+            &vs_module,
+            &fs_module,
+            (
+                RENDER_TEXTURE_FORMAT,
+                PAINTING_TEXTURE_FORMAT,
+                MOVIE_TEXTURE_FORMAT,
+            ),
+        )
+    }
+
+    /// Replace the fragment shader and rebuild all render pipelines.
+    /// The bindings of the new shader must match those of the original.
+    pub fn update_shader(&mut self, fs_spirv_data: &[u8]) {
+        let (render_pipeline, painting_pipeline, movie_pipeline) =
+            Self::build_pipelines(&self.device, &self.bind_group_layouts, fs_spirv_data);
+        self.render_pipeline = render_pipeline;
+        self.painting_pipeline = painting_pipeline;
+        self.movie_pipeline = movie_pipeline;
+    }
+
+    /// Append a post-processing shader to run after the main fragment shader.
+    /// Post-processing shaders are run in the order they are added.
+    pub fn add_post_processing_shader(&mut self, shader_data: Vec<u8>) {
+        let postprocess = PostProcess::new(
+            &self.device,
+            shader_data,
+            self.user_uniforms_buffer.is_some(),
+        );
+        // The sRGB conversion stage for paintings is kept separately in `srgb_postprocess`
+        // and always runs last, so user-provided stages are simply appended.
+        self.postprocess_ops.push(postprocess);
+    }
+
+    /// Replace the user-provided uniforms, such as after their file changed on disk.
+    /// Returns an error message, keeping the current uniforms, if the types or order differ from the bound uniforms,
+    /// as the uniform buffer and bind groups are built for that layout.
+    pub fn set_user_uniforms(&mut self, user_uniforms: Vec<UserUniform>) -> Result<(), String> {
+        if !same_std140_layout(&self.user_uniforms, &user_uniforms) {
+            return Err(String::from(
+                "Custom uniforms can't be added, removed, reordered or change type while running. Restart Easel to rebind them.",
+            ));
+        }
+        self.user_uniforms = user_uniforms;
+        Ok(())
+    }
+
+    /// Copy [Self::uniforms] and [Self::user_uniforms] to their device buffers.
+    pub fn write_uniforms(&self) {
+        // Referesh user uniforms buffer
+        if let Some(buffer) = &self.user_uniforms_buffer {
+            let bytes = std140_bytes(&self.user_uniforms);
+            assert_eq!(
+                Some(bytes.len()),
+                self.user_uniforms_buffer_size,
+                "User uniforms no longer match the std140 layout of their buffer"
+            );
+            self.queue.write_buffer(buffer, 0, &bytes);
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Update Uniforms Encoder"),
+            });
+        // Copy uniforms from CPU to staging buffer, then copy from staging buffer to main buf.
+        let descriptor = BufferInitDescriptor {
+            label: Some("Uniforms Buffer"),
+            contents: bytemuck::bytes_of(&self.uniforms),
+            usage: wgpu::BufferUsage::COPY_SRC,
+        };
+        let staging_buffer = self.device.create_buffer_init(&descriptor);
+
+        encoder.copy_buffer_to_buffer(
+            &staging_buffer,
+            0,
+            &self.uniforms_device_buffer,
+            0,
+            std::mem::size_of::<Uniforms>() as u64,
+        );
+        let command_buffer = encoder.finish();
+        self.queue.submit(Some(command_buffer));
+    }
+
+    /// Private helper that encodes the main shader followed by all post-processing effects.
+    /// Returns the texture holding the final output.
+    /// * `encoder` - [wgpu::CommandEncoder] on which to encode the draw calls.
+    /// * `resolution` - Size of the textures to render to.
+    /// * `format` - Format of the textures to render to. Must match the pipeline type.
+    /// * `label` - Debug label of the textures.
+    /// * `pipeline_type` - Which of the pipelines to render with.
+    /// * `srgb` - Whether to finish with the sRGB conversion. Only needed for paintings, as the other texture formats convert automatically.
+    fn encode_pipeline(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        resolution: UIntVector2,
+        format: wgpu::TextureFormat,
+        label: &str,
+        pipeline_type: PipelineType,
+        srgb: bool,
+    ) -> wgpu::Texture {
+        let tex_desc = wgpu::TextureDescriptor {
+            size: Extent3d {
+                width: resolution.x,
+                height: resolution.y,
+                depth_or_array_layers: 1,
+            },
+            format,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT
+                | wgpu::TextureUsage::COPY_SRC
+                | wgpu::TextureUsage::SAMPLED,
+            label: Some(label),
+            dimension: wgpu::TextureDimension::D2,
+            mip_level_count: 1,
+            sample_count: 1,
+        };
+
+        // Texture to render to.
+        let render_tex = self.device.create_texture(&tex_desc);
+        // We can't read and write the same texture, so post-processing ping-pongs with a second one.
+        let post_process_tex = self.device.create_texture(&tex_desc);
+
+        // First, render using the shader.
+        {
+            let render_tex_view = render_tex.create_view(&wgpu::TextureViewDescriptor::default());
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: &render_tex_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(self.clear_color),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+
+            for (i, bind_group) in self.bind_groups.iter().enumerate() {
+                render_pass.set_bind_group(i as u32, bind_group, &[]);
+            }
+            match pipeline_type {
+                PipelineType::Render => render_pass.set_pipeline(&self.render_pipeline),
+                PipelineType::Painting => render_pass.set_pipeline(&self.painting_pipeline),
+                PipelineType::Movie => render_pass.set_pipeline(&self.movie_pipeline),
+            }
+            render_pass.draw(0..3, 0..1);
+        }
+
+        // Then run all post-processing steps, in order.
+        let mut stage_in = render_tex;
+        let mut stage_out = post_process_tex;
+        // If user has provided custom uniforms, pass them to the post-processing stage as well.
+        let mut custom_data = None;
+        if let Some(custom_buffer) = self.user_uniforms_buffer.as_ref() {
+            custom_data = Some((custom_buffer, self.user_uniforms_buffer_size.unwrap()));
+        }
+        let srgb_postprocess = match srgb {
+            true => Some(&self.srgb_postprocess),
+            false => None,
+        };
+        for postprocess_op in self.postprocess_ops.iter().chain(srgb_postprocess) {
+            let input_view = stage_in.create_view(&wgpu::TextureViewDescriptor::default());
+            let output_view = stage_out.create_view(&wgpu::TextureViewDescriptor::default());
+            postprocess_op.post_process(
+                &input_view,
+                &output_view,
+                (
+                    &self.uniforms_device_buffer,
+                    std::mem::size_of_val(&self.uniforms),
+                ),
+                custom_data,
+                &self.device,
+                encoder,
+                self.clear_color,
+                pipeline_type,
+            );
+            // Swap input and output textures handles
+            std::mem::swap(&mut stage_in, &mut stage_out);
+        }
+
+        // After the last swap, the final output is the input of the next (non-existent) stage.
+        stage_in
+    }
+
+    /// Encode the on-screen render pipeline, including post-processing effects.
+    /// Returns a texture of [RENDER_TEXTURE_FORMAT] holding the output, ready to be presented.
+    pub fn encode_render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        resolution: UIntVector2,
+    ) -> wgpu::Texture {
+        self.encode_pipeline(
+            encoder,
+            resolution,
+            RENDER_TEXTURE_FORMAT,
+            "Canvas Render",
+            PipelineType::Render,
+            false,
+        )
+    }
+
+    /// The [Uniforms] a painting of the given resolution is rendered with.
+    /// The mouse position is scaled from the current resolution to the painting resolution.
+    pub fn painting_uniforms(&self, resolution: UIntVector2) -> Uniforms {
+        let mut painting_uniforms = self.uniforms;
+        let width_ratio = resolution.x as f32 / self.uniforms.resolution.x;
+        let height_ratio = resolution.y as f32 / self.uniforms.resolution.y;
+        painting_uniforms.mouse_position.x *= width_ratio;
+        painting_uniforms.mouse_position.z *= width_ratio;
+        painting_uniforms.mouse_position.y *= height_ratio;
+        painting_uniforms.mouse_position.w *= height_ratio;
+        painting_uniforms.resolution.x = resolution.x as f32;
+        painting_uniforms.resolution.y = resolution.y as f32;
+        painting_uniforms
+    }
+
+    /// Render a painting to a very high bit-depth texture and submit a copy of it to a staging buffer.
+    /// Returns the staging buffer, which will contain the painting data of [PAINTING_TEXTURE_FORMAT] once the GPU finishes.
+    /// Mouse coordinates in [Self::uniforms] are scaled from the current resolution to the painting resolution.
+    /// * `resolution` - Resolution of the painting.
+    /// * `srgb` - Whether to apply the sRGB conversion. Disable to keep linear color for float formats, see [crate::painting::PaintingFormat::is_linear()].
+    pub fn encode_painting(&self, resolution: UIntVector2, srgb: bool) -> wgpu::Buffer {
+        self.encode_painting_tile(resolution, UIntVector2::zero(), resolution, srgb)
+    }
+
+    /// Render one tile of a painting and submit a copy of it to a staging buffer.
+    /// Shaders see the full painting resolution and the offset of the tile in [Uniforms::tile_offset],
+    /// so that each tile renders its own region of the painting.
+    /// Returns the staging buffer, which will contain the tile data of [PAINTING_TEXTURE_FORMAT] once the GPU finishes.
+    /// * `resolution` - Resolution of the full painting.
+    /// * `tile_offset` - Pixel offset of the top-left corner of the tile within the painting.
+    /// * `tile_size` - Size of the tile to render, in pixels.
+    /// * `srgb` - Whether to apply the sRGB conversion. See [Self::encode_painting()].
+    pub fn encode_painting_tile(
+        &self,
+        resolution: UIntVector2,
+        tile_offset: UIntVector2,
+        tile_size: UIntVector2,
+        srgb: bool,
+    ) -> wgpu::Buffer {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Painting Encoder"),
+            });
+
+        // Modify Uniforms as necessary for painting render
+        let mut painting_uniforms = self.painting_uniforms(resolution);
+        painting_uniforms.tile_offset.x = tile_offset.x as f32;
+        painting_uniforms.tile_offset.y = tile_offset.y as f32;
+        self.encode_uniforms_copy(&mut encoder, &painting_uniforms);
+
+        let painting = self.encode_pipeline(
+            &mut encoder,
+            tile_size,
+            PAINTING_TEXTURE_FORMAT,
+            "Painting",
+            PipelineType::Painting,
+            srgb,
+        );
+        let buffer = self.encode_copy_to_buffer(
+            &mut encoder,
+            &painting,
+            tile_size,
+            std::mem::size_of::<half::f16>() * 4,
+            "Painting Staging Buffer",
+        );
+
+        let command_buffer = encoder.finish();
+        self.queue.submit(Some(command_buffer));
+        buffer
+    }
+
+    /// Render a movie frame and submit a copy of it to a staging buffer.
+    /// Returns the staging buffer, which will contain the frame data of [MOVIE_TEXTURE_FORMAT] once the GPU finishes.
+    pub fn encode_movie_frame(&self, resolution: UIntVector2) -> wgpu::Buffer {
+        self.encode_movie_frame_with_uniforms(resolution, None)
+    }
+
+    /// Private helper that renders a movie frame, optionally with different [Uniforms] than [Self::uniforms].
+    /// Other uniforms are only used for this frame: [Self::uniforms] are copied back once it is encoded.
+    fn encode_movie_frame_with_uniforms(
+        &self,
+        resolution: UIntVector2,
+        uniforms: Option<Uniforms>,
+    ) -> wgpu::Buffer {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Movie Frame Encoder"),
+            });
+
+        if let Some(uniforms) = &uniforms {
+            self.encode_uniforms_copy(&mut encoder, uniforms);
+        }
+        let movie_frame = self.encode_pipeline(
+            &mut encoder,
+            resolution,
+            MOVIE_TEXTURE_FORMAT,
+            "Movie Frame",
+            PipelineType::Movie,
+            false,
+        );
+        let buffer = self.encode_copy_to_buffer(
+            &mut encoder,
+            &movie_frame,
+            resolution,
+            std::mem::size_of::<u8>() * 4,
+            "Movie Frame Staging Buffer",
+        );
+        if uniforms.is_some() {
+            self.encode_uniforms_copy(&mut encoder, &self.uniforms);
+        }
+
+        let command_buffer = encoder.finish();
+        self.queue.submit(Some(command_buffer));
+        buffer
+    }
+
+    /// Private helper that encodes a copy of uniforms from the CPU to the uniforms buffer the shaders read.
+    fn encode_uniforms_copy(&self, encoder: &mut wgpu::CommandEncoder, uniforms: &Uniforms) {
+        // Copy uniforms from CPU to staging buffer, then copy from staging buffer to main buf.
+        let descriptor = BufferInitDescriptor {
+            label: Some("Uniforms Buffer"),
+            contents: bytemuck::bytes_of(uniforms),
+            usage: wgpu::BufferUsage::COPY_SRC,
+        };
+        let staging_buffer = self.device.create_buffer_init(&descriptor);
+
+        encoder.copy_buffer_to_buffer(
+            &staging_buffer,
+            0,
+            &self.uniforms_device_buffer,
+            0,
+            std::mem::size_of::<Uniforms>() as u64,
+        );
+    }
+
+    /// Private helper that encodes a copy of a texture into a new mappable buffer.
+    /// Rows in the buffer are padded as per [utils::padded_bytes_per_row()].
+    fn encode_copy_to_buffer(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        resolution: UIntVector2,
+        bytes_per_pixel: usize,
+        label: &str,
+    ) -> wgpu::Buffer {
+        let bytes_per_row = utils::padded_bytes_per_row(resolution.x, bytes_per_pixel);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            size: (bytes_per_row * resolution.y as usize) as u64,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                texture,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    bytes_per_row: NonZeroU32::new(bytes_per_row as u32),
+                    offset: 0,
+                    rows_per_image: NonZeroU32::new(resolution.y),
+                },
+            },
+            Extent3d {
+                width: resolution.x,
+                height: resolution.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        buffer
+    }
+
+    /// Drive a future that waits on this renderer's device to completion, polling the device as needed.
+    pub fn wait_for<F: Future>(&self, future: F) -> F::Output {
+        futures::pin_mut!(future);
+        loop {
+            if let Some(output) = future.as_mut().now_or_never() {
+                return output;
+            }
+            self.device.poll(wgpu::Maintain::Wait);
+        }
+    }
+
+    /// Render a painting and read it back from the GPU.
+    /// Returns the pixels as tightly packed RGBA, 16-bit unsigned integer per component, in native byte order.
+    pub fn render_painting(&self, resolution: UIntVector2) -> Vec<u8> {
+        let buffer = self.encode_painting(resolution, true);
+        let mut pixel_data = Vec::new();
+        self.wait_for(utils::transcode_painting_data(
+            buffer,
+            resolution,
+            &mut pixel_data,
+        ));
+        pixel_data
+    }
+
+    /// Render a painting in linear color and read it back from the GPU, without clamping.
+    /// Returns the pixels as tightly packed RGBA, half float per component.
+    pub fn render_painting_hdr(&self, resolution: UIntVector2) -> Vec<half::f16> {
+        let buffer = self.encode_painting(resolution, false);
+        let mut pixel_data = Vec::new();
+        self.wait_for(utils::read_painting_data(
+            buffer,
+            resolution,
+            &mut pixel_data,
+        ));
+        pixel_data
+    }
+
+    /// Render a painting in square tiles, so that paintings larger than what fits in GPU memory can be created.
+    /// Tiles are rendered one row at a time. Each finished row is handed to `band_rendered` as a horizontal band
+    /// of the painting, top to bottom, in the same pixel format as [Self::render_painting_hdr()].
+    /// Every band is `tile_size` rows tall, except for the last one which holds the remaining rows.
+    /// **Note:** Post-processing effects that sample neighbouring pixels will show seams at tile edges.
+    /// * `resolution` - Resolution of the full painting.
+    /// * `tile_size` - Width and height of each tile, in pixels. Tiles on the right and bottom edges may be smaller.
+    /// * `srgb` - Whether to apply the sRGB conversion. See [Self::encode_painting()].
+    /// * `band_rendered` - Called with each band of the painting as soon as it has been read back.
+    pub fn render_painting_tiled<F: FnMut(Vec<half::f16>)>(
+        &self,
+        resolution: UIntVector2,
+        tile_size: u32,
+        srgb: bool,
+        mut band_rendered: F,
+    ) {
+        let tile_size = tile_size.max(1);
+        let components_per_row = resolution.x as usize * 4;
+        let mut tile_data = Vec::new();
+        for band_y in (0..resolution.y).step_by(tile_size as usize) {
+            let band_height = tile_size.min(resolution.y - band_y);
+            let mut band = vec![half::f16::ZERO; components_per_row * band_height as usize];
+            for tile_x in (0..resolution.x).step_by(tile_size as usize) {
+                let tile = UIntVector2::new(tile_size.min(resolution.x - tile_x), band_height);
+                let buffer = self.encode_painting_tile(
+                    resolution,
+                    UIntVector2::new(tile_x, band_y),
+                    tile,
+                    srgb,
+                );
+                tile_data.clear();
+                self.wait_for(utils::read_painting_data(buffer, tile, &mut tile_data));
+
+                // Place each row of the tile at its horizontal offset within the band.
+                let tile_row_components = tile.x as usize * 4;
+                let band_x = tile_x as usize * 4;
+                for row in 0..band_height as usize {
+                    let src = row * tile_row_components;
+                    let dst = row * components_per_row + band_x;
+                    band[dst..dst + tile_row_components]
+                        .copy_from_slice(&tile_data[src..src + tile_row_components]);
+                }
+            }
+            band_rendered(band);
+        }
+    }
+
+    /// Render a movie frame and read it back from the GPU.
+    /// Returns the pixels as tightly packed RGBA, 8-bit sRGB per component.
+    pub fn render_movie_frame(&self, resolution: UIntVector2) -> Vec<u8> {
+        let buffer = self.encode_movie_frame(resolution);
+        let mut pixel_data = Vec::new();
+        self.wait_for(utils::transcode_frame_data_for_movie(
+            buffer,
+            resolution,
+            &mut pixel_data,
+        ));
+        pixel_data
+    }
+
+    /// Render a small preview of the canvas, such as a gallery thumbnail, and read it back from the GPU.
+    /// Unlike [Self::render_movie_frame()], shaders see the preview resolution and a mouse position scaled to it,
+    /// as for [Self::painting_uniforms()], so the preview shows the whole canvas.
+    /// Returns the pixels as tightly packed RGBA, 8-bit sRGB per component.
+    pub fn render_thumbnail(&self, resolution: UIntVector2) -> Vec<u8> {
+        let buffer = self
+            .encode_movie_frame_with_uniforms(resolution, Some(self.painting_uniforms(resolution)));
+        let mut pixel_data = Vec::new();
+        self.wait_for(utils::transcode_frame_data_for_movie(
+            buffer,
+            resolution,
+            &mut pixel_data,
+        ));
+        pixel_data
+    }
+
+    /// Encode a frame for a [crate::recording::Recorder] in the given format.
+    /// Uses the movie pipeline for [FrameFormat::Rgba8] and the painting pipeline for [FrameFormat::RgbaHalf].
+    pub fn encode_frame(&self, resolution: UIntVector2, frame_format: FrameFormat) -> wgpu::Buffer {
+        match frame_format {
+            FrameFormat::Rgba8 => self.encode_movie_frame(resolution),
+            FrameFormat::RgbaHalf(srgb) => self.encode_painting(resolution, srgb),
+        }
+    }
+
+    /// Render a frame for a [crate::recording::Recorder] in the given format and read it back from the GPU.
+    pub fn render_frame(&self, resolution: UIntVector2, frame_format: FrameFormat) -> FrameData {
+        match frame_format {
+            FrameFormat::Rgba8 => FrameData::Rgba8(self.render_movie_frame(resolution)),
+            FrameFormat::RgbaHalf(srgb) => {
+                let buffer = self.encode_painting(resolution, srgb);
+                let mut pixel_data = Vec::new();
+                self.wait_for(utils::read_painting_data(
+                    buffer,
+                    resolution,
+                    &mut pixel_data,
+                ));
+                FrameData::RgbaHalf(pixel_data)
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::uniforms::{std140_layout, UserUniform, UserUniformType};
+
 /// Source string of the skeleton of a typical Easel shader.
 pub static SHADER_SKELETON: &str = r#"
 #version 450
@@ -11,8 +13,11 @@ layout(set = 0, binding = 0) uniform Uniforms {
     float u_time_delta;
     uint u_frame_num;
     uint u_num_textures;
+    vec4 u_tile_offset;
 };
 
+// Custom uniforms, matching the uniforms file. To generate this block from the file instead, replace it with
+// #include "easel_uniforms.glsl"
 layout(set = 0, binding = 1) uniform CustomUniforms { bool vertical_wipe; };
 
 layout(location = 0) in vec2 tex_coords;
@@ -22,8 +27,10 @@ void main() {
     float window_wiper = sin(0.5 * u_time);
     window_wiper *= window_wiper;
 
-    bool color_condition = (vertical_wipe) ? tex_coords.y < window_wiper
-                                           : tex_coords.x < window_wiper;
+    // Pixel coordinates within the whole painting, so tiled paintings line up.
+    vec2 uv = (gl_FragCoord.xy + u_tile_offset.xy) / u_resolution.xy;
+    bool color_condition = (vertical_wipe) ? uv.y < window_wiper
+                                           : uv.x < window_wiper;
 
     if (color_condition) {
         f_color = vec4(0.f, 0.5f, 0.5f, 1.f);
@@ -33,3 +40,61 @@ void main() {
 }
 
 "#;
+
+/// Name of the virtual include file that [crate::utils::load_shader()] resolves to [uniforms_header()].
+/// Use it with `#include "easel_uniforms.glsl"`. A real file of the same name next to the shader takes precedence.
+pub static UNIFORMS_INCLUDE: &str = "easel_uniforms.glsl";
+
+/// Private helper that turns a uniform name into a valid GLSL identifier.
+/// Characters other than ASCII letters, digits and underscores become underscores, and names starting with a digit get a leading underscore.
+fn glsl_identifier(name: &str) -> String {
+    let mut identifier: String = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    if identifier.is_empty() || identifier.as_bytes()[0].is_ascii_digit() {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+
+/// GLSL source declaring user uniforms as the `CustomUniforms` block at set 0, binding 1, matching how they are bound.
+/// Members are declared in the same order as the uniforms, with std140-compatible types and their byte offsets in comments.
+/// Member names follow the uniform names, with any characters not allowed in GLSL replaced by underscores.
+/// If there are no uniforms there is nothing to bind, and the source only contains a comment.
+pub fn uniforms_header(user_uniforms: &[UserUniform]) -> String {
+    let mut header =
+        String::from("// Custom uniforms, generated by Easel from the uniforms file.\n");
+    if user_uniforms.is_empty() {
+        header.push_str("// No custom uniforms are declared.\n");
+        return header;
+    }
+    let needs_int64 = user_uniforms.iter().any(|u| {
+        matches!(
+            u.inherent_type,
+            UserUniformType::UInt64 | UserUniformType::Int64
+        )
+    });
+    if needs_int64 {
+        header.push_str("#extension GL_ARB_gpu_shader_int64 : require\n");
+    }
+    let (offsets, size) = std140_layout(user_uniforms);
+    header.push_str(&format!(
+        "// The members must stay in this order. The block is {} bytes.\n",
+        size
+    ));
+    header.push_str("layout(std140, set = 0, binding = 1) uniform CustomUniforms {\n");
+    for (uniform, offset) in user_uniforms.iter().zip(offsets) {
+        header.push_str(&format!(
+            "    {} {}; // offset {}\n",
+            uniform.inherent_type.glsl_type(),
+            glsl_identifier(&uniform.name),
+            offset
+        ));
+    }
+    header.push_str("};\n");
+    header
+}
@@ -1,10 +1,8 @@
-use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
 
-use crate::utils::{convert_bytes_to_value, convert_value_to_bytes};
+use crate::utils::{convert_bytes_to_value, convert_value_to_bytes, f32_to_json};
 use crate::vector::{IntVector4, Vector4};
 use bytemuck::{Pod, Zeroable};
-use imgui::ImString;
 use log::{debug, error};
 
 #[repr(C)]
@@ -29,6 +27,9 @@ pub struct Uniforms {
     pub frame_num: u32,
     /// Number of textures bound.
     pub num_textures: u32,
+    /// Pixel offset of the tile being rendered within the full painting.
+    /// xy: offset, zw is unused. Always zero unless rendering a painting in tiles.
+    pub tile_offset: Vector4,
 }
 
 impl Uniforms {
@@ -47,10 +48,51 @@ impl Uniforms {
             mouse_button: IntVector4::zero(),
             num_textures: 0,
             date: IntVector4::zero(),
+            tile_offset: Vector4::zero(),
+        }
+    }
+
+    /// Describe every field of the struct as a JSON object, such as for painting recipes.
+    /// Vectors are stored as arrays of 4 numbers.
+    pub fn to_json(&self) -> json::JsonValue {
+        let vec4 = |v: &Vector4| json::array![v.x, v.y, v.z, v.w];
+        let ivec4 = |v: &IntVector4| json::array![v.x, v.y, v.z, v.w];
+        json::object! {
+            "resolution": vec4(&self.resolution),
+            "mouse position": vec4(&self.mouse_position),
+            "mouse button": ivec4(&self.mouse_button),
+            "date": ivec4(&self.date),
+            "time": self.time,
+            "time delta": self.time_delta,
+            "frame": self.frame_num,
+            "textures": self.num_textures,
+            "tile offset": vec4(&self.tile_offset),
+        }
+    }
+
+    /// Read uniforms written by [Self::to_json()]. Missing fields are zero.
+    pub fn from_json(data: &json::JsonValue) -> Uniforms {
+        let float = |v: &json::JsonValue| v.as_f32().unwrap_or(0.0);
+        let int = |v: &json::JsonValue| v.as_i32().unwrap_or(0);
+        let vec4 = |v: &json::JsonValue| {
+            Vector4::new(float(&v[0]), float(&v[1]), float(&v[2]), float(&v[3]))
+        };
+        let ivec4 =
+            |v: &json::JsonValue| IntVector4::new(int(&v[0]), int(&v[1]), int(&v[2]), int(&v[3]));
+        Uniforms {
+            resolution: vec4(&data["resolution"]),
+            mouse_position: vec4(&data["mouse position"]),
+            mouse_button: ivec4(&data["mouse button"]),
+            date: ivec4(&data["date"]),
+            time: float(&data["time"]),
+            time_delta: float(&data["time delta"]),
+            frame_num: data["frame"].as_u32().unwrap_or(0),
+            num_textures: data["textures"].as_u32().unwrap_or(0),
+            tile_offset: vec4(&data["tile offset"]),
         }
     }
 }
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum UserUniformType {
     Float32,
     Float64,
@@ -59,6 +101,355 @@ pub enum UserUniformType {
     Int32,
     Int64,
     Bool,
+    Vec2,
+    Vec3,
+    Vec4,
+    IVec2,
+    IVec3,
+    IVec4,
+    UVec2,
+    UVec3,
+    UVec4,
+    /// 3x3 float matrix, stored column by column as in GLSL.
+    Mat3,
+    /// 4x4 float matrix, stored column by column as in GLSL.
+    Mat4,
+    /// RGB colour, bound as a `vec3` and edited with a colour picker.
+    Color3,
+    /// RGBA colour, bound as a `vec4` and edited with a colour picker.
+    Color4,
+}
+
+/// Private type of the components of vector, matrix and colour uniforms.
+enum Component {
+    Float,
+    Int,
+    UInt,
+}
+
+impl UserUniformType {
+    /// Whether values of this type are a single number rather than a vector, matrix or colour.
+    pub fn is_scalar(&self) -> bool {
+        matches!(
+            self,
+            UserUniformType::Float32
+                | UserUniformType::Float64
+                | UserUniformType::UInt32
+                | UserUniformType::UInt64
+                | UserUniformType::Int32
+                | UserUniformType::Int64
+                | UserUniformType::Bool
+        )
+    }
+
+    /// Number of components in vectors, matrices and colours, or 1 for scalars.
+    pub fn component_count(&self) -> usize {
+        match self {
+            UserUniformType::Vec2 | UserUniformType::IVec2 | UserUniformType::UVec2 => 2,
+            UserUniformType::Vec3
+            | UserUniformType::IVec3
+            | UserUniformType::UVec3
+            | UserUniformType::Color3 => 3,
+            UserUniformType::Vec4
+            | UserUniformType::IVec4
+            | UserUniformType::UVec4
+            | UserUniformType::Color4 => 4,
+            UserUniformType::Mat3 => 9,
+            UserUniformType::Mat4 => 16,
+            _ => 1,
+        }
+    }
+
+    /// Name of the type in the uniforms file, such as `f32` or `vec3`. Both colour types are `color`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            UserUniformType::Float32 => "f32",
+            UserUniformType::Float64 => "f64",
+            UserUniformType::UInt32 => "u32",
+            UserUniformType::UInt64 => "u64",
+            UserUniformType::Int32 => "i32",
+            UserUniformType::Int64 => "i64",
+            UserUniformType::Bool => "bool",
+            UserUniformType::Vec2 => "vec2",
+            UserUniformType::Vec3 => "vec3",
+            UserUniformType::Vec4 => "vec4",
+            UserUniformType::IVec2 => "ivec2",
+            UserUniformType::IVec3 => "ivec3",
+            UserUniformType::IVec4 => "ivec4",
+            UserUniformType::UVec2 => "uvec2",
+            UserUniformType::UVec3 => "uvec3",
+            UserUniformType::UVec4 => "uvec4",
+            UserUniformType::Mat3 => "mat3",
+            UserUniformType::Mat4 => "mat4",
+            UserUniformType::Color3 | UserUniformType::Color4 => "color",
+        }
+    }
+
+    /// Alignment of the type in a std140 uniform block, in bytes.
+    /// Scalars align to their size, two component vectors to twice that,
+    /// and three and four component vectors, colours and matrix columns to 16.
+    pub fn std140_alignment(&self) -> usize {
+        match self {
+            UserUniformType::Float64 | UserUniformType::UInt64 | UserUniformType::Int64 => 8,
+            UserUniformType::Vec2 | UserUniformType::IVec2 | UserUniformType::UVec2 => 8,
+            _ if self.is_scalar() => 4,
+            _ => 16,
+        }
+    }
+
+    /// Size of the type in a std140 uniform block, in bytes.
+    /// Each column of a matrix is padded to a `vec4`, so a `mat3` takes 48 bytes.
+    pub fn std140_size(&self) -> usize {
+        match self {
+            UserUniformType::Mat3 => 3 * 16,
+            UserUniformType::Float64 | UserUniformType::UInt64 | UserUniformType::Int64 => 8,
+            _ => 4 * self.component_count(),
+        }
+    }
+
+    /// Name of the matching GLSL type, for declaring the uniform in a std140 block.
+    /// Colours are plain vectors, and bools are 4 bytes in std140 so bind as GLSL `bool`.
+    /// The 64-bit integer types need the `GL_ARB_gpu_shader_int64` extension.
+    pub fn glsl_type(&self) -> &'static str {
+        match self {
+            UserUniformType::Float32 => "float",
+            UserUniformType::Float64 => "double",
+            UserUniformType::UInt32 => "uint",
+            UserUniformType::UInt64 => "uint64_t",
+            UserUniformType::Int32 => "int",
+            UserUniformType::Int64 => "int64_t",
+            UserUniformType::Bool => "bool",
+            UserUniformType::Vec2 => "vec2",
+            UserUniformType::Vec3 | UserUniformType::Color3 => "vec3",
+            UserUniformType::Vec4 | UserUniformType::Color4 => "vec4",
+            UserUniformType::IVec2 => "ivec2",
+            UserUniformType::IVec3 => "ivec3",
+            UserUniformType::IVec4 => "ivec4",
+            UserUniformType::UVec2 => "uvec2",
+            UserUniformType::UVec3 => "uvec3",
+            UserUniformType::UVec4 => "uvec4",
+            UserUniformType::Mat3 => "mat3",
+            UserUniformType::Mat4 => "mat4",
+        }
+    }
+
+    /// Private helper that returns the component type of vectors, matrices and colours, or None for scalars.
+    fn component(&self) -> Option<Component> {
+        match self {
+            UserUniformType::IVec2 | UserUniformType::IVec3 | UserUniformType::IVec4 => {
+                Some(Component::Int)
+            }
+            UserUniformType::UVec2 | UserUniformType::UVec3 | UserUniformType::UVec4 => {
+                Some(Component::UInt)
+            }
+            _ if self.is_scalar() => None,
+            _ => Some(Component::Float),
+        }
+    }
+}
+
+/// How a uniform is edited in the Dashboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UniformWidget {
+    /// A text field, with step buttons if the uniform has a `step`.
+    Input,
+    /// A slider from `min` to `max`.
+    Slider,
+    /// A field that changes by dragging left and right, and within `min` and `max` if given.
+    Drag,
+    /// A rotary knob from `min` to `max`, turned by dragging up and down.
+    Knob,
+    /// A drop-down of named values.
+    Combo,
+}
+
+// Written out, as deriving Default for an enum needs a newer compiler than Easel requires.
+#[allow(clippy::derivable_impls)]
+impl Default for UniformWidget {
+    fn default() -> Self {
+        UniformWidget::Input
+    }
+}
+
+impl UniformWidget {
+    /// Every widget.
+    pub const ALL: [UniformWidget; 5] = [
+        UniformWidget::Input,
+        UniformWidget::Slider,
+        UniformWidget::Drag,
+        UniformWidget::Knob,
+        UniformWidget::Combo,
+    ];
+
+    /// Name used in the uniforms file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            UniformWidget::Input => "input",
+            UniformWidget::Slider => "slider",
+            UniformWidget::Drag => "drag",
+            UniformWidget::Knob => "knob",
+            UniformWidget::Combo => "combo",
+        }
+    }
+
+    /// Look up a widget by its [Self::name()].
+    pub fn from_name(name: &str) -> Option<UniformWidget> {
+        UniformWidget::ALL
+            .iter()
+            .copied()
+            .find(|w| w.name() == name)
+    }
+}
+
+/// Optional description of how a user uniform is presented and edited in the Dashboard. Has no effect on the shader.
+/// See [load_uniforms_from_json()] for how it is written in the uniforms file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UniformMetadata {
+    /// Lowest value the uniform can be set to in the Dashboard. Applies to every component of vector sliders and drags.
+    pub min: Option<f64>,
+    /// Highest value the uniform can be set to in the Dashboard. Applies to every component of vector sliders and drags.
+    pub max: Option<f64>,
+    /// Increment of the step buttons, the value sliders and knobs snap to, and the change per pixel when dragging.
+    pub step: Option<f64>,
+    /// How the uniform is edited.
+    pub widget: UniformWidget,
+    /// Names and values of the choices of a [UniformWidget::Combo], in the order shown.
+    pub options: Vec<(String, f64)>,
+    /// Text shown instead of the uniform name.
+    pub label: Option<String>,
+    /// Text shown when hovering over the uniform.
+    pub tooltip: Option<String>,
+    /// Name of the collapsible group the uniform is shown in. Uniforms without a group are shown first.
+    pub group: Option<String>,
+}
+
+impl UniformMetadata {
+    /// The `min` and `max` of the uniform, if it has both.
+    pub fn range(&self) -> Option<(f64, f64)> {
+        Some((self.min?, self.max?))
+    }
+
+    /// Clamp a value to `min` and `max`, where given.
+    pub fn clamp(&self, value: f64) -> f64 {
+        let value = self.min.map_or(value, |min| value.max(min));
+        self.max.map_or(value, |max| value.min(max))
+    }
+
+    /// Round a value to the nearest multiple of `step` from `min`, or from 0 without a `min`. Unchanged without a `step`.
+    pub fn snap(&self, value: f64) -> f64 {
+        match self.step.filter(|step| *step > 0.0) {
+            Some(step) => {
+                let origin = self.min.unwrap_or(0.0);
+                self.clamp(origin + ((value - origin) / step).round() * step)
+            }
+            None => value,
+        }
+    }
+
+    /// Read metadata from the object form of a uniforms file entry, see [load_uniforms_from_json()].
+    /// Returns an error message if the metadata is malformed or its widget can't edit the uniform's type.
+    /// * `name` - Name of the uniform.
+    /// * `inherent_type` - Type of the uniform.
+    /// * `data` - JSON object describing the uniform.
+    pub fn from_json(
+        name: &str,
+        inherent_type: UserUniformType,
+        data: &json::JsonValue,
+    ) -> Result<UniformMetadata, String> {
+        let number = |key: &str| match &data[key] {
+            json::JsonValue::Null => Ok(None),
+            value => value
+                .as_f64()
+                .map(Some)
+                .ok_or(format!("Uniform {} has an invalid {}", name, key)),
+        };
+        let text = |key: &str| match &data[key] {
+            json::JsonValue::Null => Ok(None),
+            value => value
+                .as_str()
+                .map(|s| Some(String::from(s)))
+                .ok_or(format!("Uniform {} has an invalid {}", name, key)),
+        };
+        let mut metadata = UniformMetadata {
+            min: number("min")?,
+            max: number("max")?,
+            step: number("step")?,
+            label: text("label")?,
+            tooltip: text("tooltip")?,
+            group: text("group")?,
+            ..Default::default()
+        };
+        if let Some((min, max)) = metadata.range() {
+            if min > max {
+                return Err(format!("Uniform {} has a min above its max", name));
+            }
+        }
+
+        // Options are an array of names for the values 0, 1, 2 and so on, or an object of names and values.
+        let options = &data["options"];
+        if options.is_array() {
+            for (i, option) in options.members().enumerate() {
+                let option = option
+                    .as_str()
+                    .ok_or(format!("Uniform {} has an invalid option {}", name, option))?;
+                metadata.options.push((String::from(option), i as f64));
+            }
+        } else {
+            for (option, value) in options.entries() {
+                let value = value.as_f64().ok_or(format!(
+                    "Uniform {} has an invalid value for option {}",
+                    name, option
+                ))?;
+                metadata.options.push((String::from(option), value));
+            }
+        }
+
+        // Without a widget, pick one from the other settings.
+        metadata.widget = match data["widget"].as_str() {
+            Some(widget) => UniformWidget::from_name(widget)
+                .ok_or(format!("Uniform {} has unknown widget {}", name, widget))?,
+            None if !metadata.options.is_empty() => UniformWidget::Combo,
+            None if metadata.range().is_some() => UniformWidget::Slider,
+            None => UniformWidget::Input,
+        };
+        let numeric_scalar =
+            inherent_type.is_scalar() && !matches!(inherent_type, UserUniformType::Bool);
+        // Vectors can be sliders and drags, but colours and matrices have their own editors.
+        let numeric_vector = inherent_type.component().is_some()
+            && !matches!(
+                inherent_type,
+                UserUniformType::Mat3
+                    | UserUniformType::Mat4
+                    | UserUniformType::Color3
+                    | UserUniformType::Color4
+            );
+        let supported = match metadata.widget {
+            UniformWidget::Input => true,
+            UniformWidget::Slider | UniformWidget::Drag => numeric_scalar || numeric_vector,
+            UniformWidget::Knob | UniformWidget::Combo => numeric_scalar,
+        };
+        if !supported {
+            return Err(format!(
+                "Uniform {} of type {} can't use a {} widget",
+                name,
+                inherent_type.name(),
+                metadata.widget.name()
+            ));
+        }
+        match metadata.widget {
+            UniformWidget::Slider | UniformWidget::Knob if metadata.range().is_none() => {
+                Err(format!(
+                    "Uniform {} needs a min and max for a {} widget",
+                    name,
+                    metadata.widget.name()
+                ))
+            }
+            UniformWidget::Combo if metadata.options.is_empty() => {
+                Err(format!("Uniform {} needs options for a combo widget", name))
+            }
+            _ => Ok(metadata),
+        }
+    }
 }
 
 #[repr(C)]
@@ -66,12 +457,158 @@ pub struct UserUniform {
     pub bytes: Vec<u8>,
     pub name: String,
     pub inherent_type: UserUniformType,
+    /// How the uniform is presented in the Dashboard.
+    pub metadata: UniformMetadata,
 }
 
 impl UserUniform {
     pub fn get_value<T: Copy>(&self) -> Result<T, &str> {
         convert_bytes_to_value(&self.bytes)
     }
+
+    /// The value of this uniform as an f64, whatever its type. Booleans are 0 or 1.
+    /// Vectors, matrices and colours return their first component.
+    pub fn as_f64(&self) -> f64 {
+        if let Some(component) = self.inherent_type.component() {
+            let first = &self.bytes[..4];
+            return match component {
+                Component::Float => convert_bytes_to_value::<f32>(first).unwrap() as f64,
+                Component::Int => convert_bytes_to_value::<i32>(first).unwrap() as f64,
+                Component::UInt => convert_bytes_to_value::<u32>(first).unwrap() as f64,
+            };
+        }
+        match self.inherent_type {
+            UserUniformType::Float32 => self.get_value::<f32>().unwrap() as f64,
+            UserUniformType::Float64 => self.get_value::<f64>().unwrap(),
+            UserUniformType::UInt32 => self.get_value::<u32>().unwrap() as f64,
+            UserUniformType::UInt64 => self.get_value::<u64>().unwrap() as f64,
+            UserUniformType::Int32 => self.get_value::<i32>().unwrap() as f64,
+            UserUniformType::Int64 => self.get_value::<i64>().unwrap() as f64,
+            UserUniformType::Bool => self.get_value::<u32>().unwrap() as f64,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Set the value of this uniform from an f64, converting it to the uniform's type.
+    /// Integers are rounded to the nearest value in range, and booleans are true from 0.5 up.
+    /// Vectors, matrices and colours set every component to the value, like GLSL's `vec3(value)`.
+    pub fn set_from_f64(&mut self, value: f64) {
+        if let Some(component) = self.inherent_type.component() {
+            let bytes = match component {
+                Component::Float => convert_value_to_bytes(value as f32),
+                Component::Int => convert_value_to_bytes(value.round() as i32),
+                Component::UInt => convert_value_to_bytes(value.round() as u32),
+            };
+            self.bytes = bytes.repeat(self.inherent_type.component_count());
+            return;
+        }
+        self.bytes = match self.inherent_type {
+            UserUniformType::Float32 => convert_value_to_bytes(value as f32),
+            UserUniformType::Float64 => convert_value_to_bytes(value),
+            UserUniformType::UInt32 => convert_value_to_bytes(value.round() as u32),
+            UserUniformType::UInt64 => convert_value_to_bytes(value.round() as u64),
+            UserUniformType::Int32 => convert_value_to_bytes(value.round() as i32),
+            UserUniformType::Int64 => convert_value_to_bytes(value.round() as i64),
+            UserUniformType::Bool => convert_value_to_bytes((value >= 0.5) as u32),
+            _ => unreachable!(),
+        };
+    }
+
+    /// Every component of the value of this uniform as an f64, or just the value for single numbers.
+    /// Matrices are in column-major order and booleans are 0 or 1.
+    pub fn components(&self) -> Vec<f64> {
+        match self.inherent_type.component() {
+            Some(component) => self
+                .bytes
+                .chunks_exact(4)
+                .map(|bytes| match component {
+                    Component::Float => convert_bytes_to_value::<f32>(bytes).unwrap() as f64,
+                    Component::Int => convert_bytes_to_value::<i32>(bytes).unwrap() as f64,
+                    Component::UInt => convert_bytes_to_value::<u32>(bytes).unwrap() as f64,
+                })
+                .collect(),
+            None => vec![self.as_f64()],
+        }
+    }
+
+    /// Set every component of the value of this uniform from f64s in the order of [Self::components()],
+    /// converting them to the uniform's type as [Self::set_from_f64()] does.
+    pub fn set_components(&mut self, components: &[f64]) {
+        assert_eq!(components.len(), self.inherent_type.component_count());
+        match self.inherent_type.component() {
+            Some(component) => {
+                self.bytes = components
+                    .iter()
+                    .flat_map(|value| match component {
+                        Component::Float => convert_value_to_bytes(*value as f32),
+                        Component::Int => convert_value_to_bytes(value.round() as i32),
+                        Component::UInt => convert_value_to_bytes(value.round() as u32),
+                    })
+                    .collect()
+            }
+            None => self.set_from_f64(components[0]),
+        }
+    }
+
+    /// Interpolate every component between the value of this uniform and another of the same type.
+    /// Integers are rounded and booleans switch halfway. Returns a copy of this uniform if the types differ.
+    /// * `other` - The uniform to interpolate towards.
+    /// * `t` - Progress from this uniform at 0 to `other` at 1.
+    pub fn lerp(&self, other: &UserUniform, t: f64) -> UserUniform {
+        let mut uniform = self.clone();
+        if other.inherent_type == self.inherent_type {
+            let components: Vec<f64> = self
+                .components()
+                .iter()
+                .zip(other.components())
+                .map(|(a, b)| a + (b - a) * t)
+                .collect();
+            uniform.set_components(&components);
+        }
+        uniform
+    }
+
+    /// The type and value of this uniform, in the same `["f32", 4.0]` form read by [load_uniforms_from_json].
+    /// Matrices are written as an array of columns.
+    pub fn to_json(&self) -> json::JsonValue {
+        if let Some(component) = self.inherent_type.component() {
+            let components: Vec<json::JsonValue> = self
+                .bytes
+                .chunks_exact(4)
+                .map(|bytes| match component {
+                    Component::Float => f32_to_json(convert_bytes_to_value::<f32>(bytes).unwrap()),
+                    Component::Int => convert_bytes_to_value::<i32>(bytes).unwrap().into(),
+                    Component::UInt => convert_bytes_to_value::<u32>(bytes).unwrap().into(),
+                })
+                .collect();
+            let value = match self.inherent_type {
+                UserUniformType::Mat3 | UserUniformType::Mat4 => {
+                    let rows = (components.len() as f32).sqrt() as usize;
+                    json::JsonValue::Array(
+                        components
+                            .chunks(rows)
+                            .map(|column| json::JsonValue::Array(column.to_vec()))
+                            .collect(),
+                    )
+                }
+                _ => json::JsonValue::Array(components),
+            };
+            return json::array![self.inherent_type.name(), value];
+        }
+        match self.inherent_type {
+            UserUniformType::Float32 => {
+                json::array!["f32", f32_to_json(self.get_value::<f32>().unwrap())]
+            }
+            UserUniformType::Float64 => json::array!["f64", self.get_value::<f64>().unwrap()],
+            UserUniformType::UInt32 => json::array!["u32", self.get_value::<u32>().unwrap()],
+            UserUniformType::UInt64 => json::array!["u64", self.get_value::<u64>().unwrap()],
+            UserUniformType::Int32 => json::array!["i32", self.get_value::<i32>().unwrap()],
+            UserUniformType::Int64 => json::array!["i64", self.get_value::<i64>().unwrap()],
+            // Booleans are bound as u32
+            UserUniformType::Bool => json::array!["bool", self.get_value::<u32>().unwrap() != 0],
+            _ => unreachable!(),
+        }
+    }
 }
 
 impl Clone for UserUniform {
@@ -80,6 +617,7 @@ impl Clone for UserUniform {
             bytes: self.bytes.clone(),
             name: self.name.clone(),
             inherent_type: self.inherent_type,
+            metadata: self.metadata.clone(),
         }
     }
 }
@@ -97,6 +635,140 @@ impl Hash for UserUniform {
     }
 }
 
+/// Replace the value of a user uniform, keeping its place in the list and its metadata from the uniforms file.
+/// Returns false, leaving the list unchanged, if there is no uniform with the same name and type,
+/// such as when the uniforms file was reloaded while the change was in flight.
+/// * `user_uniforms` - Uniforms in the order they are bound.
+/// * `uniform` - The uniform with its new value.
+pub fn replace_user_uniform(user_uniforms: &mut [UserUniform], uniform: UserUniform) -> bool {
+    match user_uniforms
+        .iter_mut()
+        .find(|u| u.name == uniform.name && u.inherent_type == uniform.inherent_type)
+    {
+        Some(existing) => {
+            existing.bytes = uniform.bytes;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Offset of every user uniform in a std140 uniform block, in the order they are declared,
+/// and the size of the whole block in bytes.
+/// The block size is rounded up to a multiple of 16, the alignment of a std140 block.
+pub fn std140_layout(user_uniforms: &[UserUniform]) -> (Vec<usize>, usize) {
+    let mut offsets = Vec::with_capacity(user_uniforms.len());
+    let mut end = 0;
+    for uniform in user_uniforms {
+        let alignment = uniform.inherent_type.std140_alignment();
+        let offset = end + (alignment - end % alignment) % alignment;
+        offsets.push(offset);
+        end = offset + uniform.inherent_type.std140_size();
+    }
+    (offsets, end + (16 - end % 16) % 16)
+}
+
+/// Whether two lists of user uniforms have the same types in the same order, and so the same std140 layout.
+pub fn same_std140_layout(a: &[UserUniform], b: &[UserUniform]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(a, b)| a.inherent_type == b.inherent_type)
+}
+
+/// Pack user uniforms into the bytes of a std140 uniform block, in the order they are declared.
+/// Matches a GLSL block declaring the same members in the same order, such as
+/// `layout(set = 0, binding = 1) uniform MyUniforms { float zoom; vec3 tint; mat3 warp; };`.
+pub fn std140_bytes(user_uniforms: &[UserUniform]) -> Vec<u8> {
+    let (offsets, size) = std140_layout(user_uniforms);
+    let mut bytes = vec![0; size];
+    for (uniform, offset) in user_uniforms.iter().zip(offsets) {
+        match uniform.inherent_type {
+            // The columns of a mat3 are padded from 12 to 16 bytes.
+            UserUniformType::Mat3 => {
+                for (i, column) in uniform.bytes.chunks_exact(12).enumerate() {
+                    let start = offset + i * 16;
+                    bytes[start..start + 12].copy_from_slice(column);
+                }
+            }
+            _ => bytes[offset..offset + uniform.bytes.len()].copy_from_slice(&uniform.bytes),
+        }
+    }
+    bytes
+}
+
+/// Name of the optional `f32` user uniform driven from 0 to 1 over a seamless loop recording.
+/// Declare it in the uniforms file to use it, such as `"loop_phase": ["f32", 0.0]`.
+pub static LOOP_PHASE_UNIFORM: &str = "loop_phase";
+
+/// Private helper that reads a vector, matrix or colour uniform from its type name and value.
+/// Returns an error message if the type is unknown or the value has the wrong number of components.
+fn load_vector_uniform(
+    name: &str,
+    type_str: &str,
+    value: &json::JsonValue,
+) -> Result<UserUniform, String> {
+    let inherent_type = match type_str {
+        "vec2" => UserUniformType::Vec2,
+        "vec3" => UserUniformType::Vec3,
+        "vec4" => UserUniformType::Vec4,
+        "ivec2" => UserUniformType::IVec2,
+        "ivec3" => UserUniformType::IVec3,
+        "ivec4" => UserUniformType::IVec4,
+        "uvec2" => UserUniformType::UVec2,
+        "uvec3" => UserUniformType::UVec3,
+        "uvec4" => UserUniformType::UVec4,
+        "mat3" => UserUniformType::Mat3,
+        "mat4" => UserUniformType::Mat4,
+        "color" => match value.len() {
+            3 => UserUniformType::Color3,
+            4 => UserUniformType::Color4,
+            _ => {
+                return Err(format!(
+                    "Color uniform {} must have 3 or 4 components",
+                    name
+                ))
+            }
+        },
+        _ => return Err(format!("Uniform with invalid type {} found", type_str)),
+    };
+    // Matrices may be given as an array of columns, or as a single array in column-major order.
+    let mut components = Vec::new();
+    for member in value.members() {
+        match member.is_array() {
+            true => components.extend(member.members()),
+            false => components.push(member),
+        }
+    }
+    if components.len() != inherent_type.component_count() {
+        return Err(format!(
+            "Uniform {} of type {} needs {} components, found {}",
+            name,
+            type_str,
+            inherent_type.component_count(),
+            components.len()
+        ));
+    }
+    let mut bytes = Vec::with_capacity(components.len() * 4);
+    for component in components {
+        let component_bytes = match inherent_type.component().unwrap() {
+            Component::Float => component.as_f32().map(convert_value_to_bytes),
+            Component::Int => component.as_i32().map(convert_value_to_bytes),
+            Component::UInt => component.as_u32().map(convert_value_to_bytes),
+        };
+        bytes.extend(component_bytes.ok_or(format!(
+            "Uniform {} has invalid component {} for type {}",
+            name, component, type_str
+        ))?);
+    }
+    Ok(UserUniform {
+        bytes,
+        name: String::from(name),
+        inherent_type,
+        metadata: UniformMetadata::default(),
+    })
+}
+
 /// Loads user-specified uniforms from a given JSON file on disk.
 /// Currently, the following data formats are supported:
 ///   - f32
@@ -106,134 +778,244 @@ impl Hash for UserUniform {
 ///   - i32
 ///   - i64
 ///   - bool (bound as u32 in shader)
+///   - vec2, vec3, vec4, ivec2, ivec3, ivec4, uvec2, uvec3, uvec4
+///   - mat3, mat4, as an array of columns or a single array in column-major order
+///   - color, an RGB or RGBA colour bound as a vec3 or vec4
 ///
 /// The JSON file must follow a specific format, where each uniform is given a name followed by the type and value.
 /// Example valid format:
 /// ```text
 /// "uniforms": {
 ///     "dynamic": ["bool", false],
-///     "ground_truth": ["f32", 4.0]
+///     "ground_truth": ["f32", 4.0],
+///     "offset": ["vec2", [0.5, -0.25]],
+///     "tint": ["color", [1.0, 0.4, 0.2]]
 /// }
 /// ```
-/// Returns a vector of [UserUniform] objects that provided everything needed to bind to a shader.
-pub fn load_uniforms_from_json(data: &json::JsonValue) -> HashSet<UserUniform> {
-    let mut uniforms = HashSet::new();
+/// Uniforms can also be given as an object with a `type` and `value`, plus any of the [UniformMetadata] settings:
+/// `min`, `max`, `step`, `label`, `tooltip`, `group` and `widget`, one of `"input"`, `"slider"`, `"drag"`, `"knob"` or `"combo"`.
+/// Combos pick from `options`, an array of names for the values 0, 1, 2 and so on, or an object of names and values.
+/// Without a `widget`, uniforms with `options` are combos, uniforms with a `min` and `max` are sliders, and others are inputs.
+/// Malformed metadata is logged and ignored.
+/// ```text
+/// "uniforms": {
+///     "zoom": { "type": "f32", "value": 4.0, "min": 1.0, "max": 10.0, "step": 0.5, "group": "Camera" },
+///     "blend": { "type": "u32", "value": 0, "options": ["add", "multiply", "screen"], "tooltip": "How layers combine." }
+/// }
+/// ```
+/// Returns a vector of [UserUniform] objects that provided everything needed to bind to a shader,
+/// in the order they are declared in the file. See [std140_bytes()] for how they are laid out.
+pub fn load_uniforms_from_json(data: &json::JsonValue) -> Vec<UserUniform> {
+    let mut uniforms = Vec::new();
     let uniforms_json = &data["uniforms"];
     if !uniforms_json.is_null() {
         let entries = uniforms_json.entries();
         for entry in entries {
             let name = entry.0;
-            let mut array_itr = entry.1.members();
-            let type_str = array_itr.next().unwrap().as_str().unwrap();
-            let value = array_itr.next().unwrap();
-            if type_str == "f32" {
-                uniforms.insert(UserUniform {
-                    bytes: convert_value_to_bytes(value.as_f32().unwrap()),
-                    name: String::from(name),
-                    inherent_type: UserUniformType::Float32,
-                });
-            } else if type_str == "f64" {
-                uniforms.insert(UserUniform {
-                    bytes: convert_value_to_bytes(value.as_f64().unwrap()),
-                    name: String::from(name),
-                    inherent_type: UserUniformType::Float64,
-                });
-            } else if type_str == "u32" {
-                uniforms.insert(UserUniform {
-                    bytes: convert_value_to_bytes(value.as_u32().unwrap()),
-                    name: String::from(name),
-                    inherent_type: UserUniformType::UInt32,
-                });
-            } else if type_str == "u64" {
-                uniforms.insert(UserUniform {
-                    bytes: convert_value_to_bytes(value.as_u64().unwrap()),
-                    name: String::from(name),
-                    inherent_type: UserUniformType::UInt64,
-                });
-            } else if type_str == "i32" {
-                uniforms.insert(UserUniform {
-                    bytes: convert_value_to_bytes(value.as_i32().unwrap()),
-                    name: String::from(name),
-                    inherent_type: UserUniformType::Int32,
-                });
-            } else if type_str == "i64" {
-                uniforms.insert(UserUniform {
-                    bytes: convert_value_to_bytes(value.as_i64().unwrap()),
-                    name: String::from(name),
-                    inherent_type: UserUniformType::Int64,
-                });
-            } else if type_str == "bool" {
+            let (type_str, value) = match entry.1.is_object() {
+                true => match entry.1["type"].as_str() {
+                    Some(type_str) => (type_str, &entry.1["value"]),
+                    None => {
+                        error!("Uniform {} has no type, ignoring.", name);
+                        continue;
+                    }
+                },
+                false => {
+                    let mut array_itr = entry.1.members();
+                    let type_str = match array_itr.next().and_then(|v| v.as_str()) {
+                        Some(type_str) => type_str,
+                        None => {
+                            error!("Uniform {} has no type, ignoring.", name);
+                            continue;
+                        }
+                    };
+                    match array_itr.next() {
+                        Some(value) => (type_str, value),
+                        None => {
+                            error!("Uniform {} has no valid value, ignoring.", name);
+                            continue;
+                        }
+                    }
+                }
+            };
+            // Scalar types give their bytes, or None if the value is missing or of the wrong JSON type.
+            let scalar = match type_str {
+                "f32" => Some((
+                    value.as_f32().map(convert_value_to_bytes),
+                    UserUniformType::Float32,
+                )),
+                "f64" => Some((
+                    value.as_f64().map(convert_value_to_bytes),
+                    UserUniformType::Float64,
+                )),
+                "u32" => Some((
+                    value.as_u32().map(convert_value_to_bytes),
+                    UserUniformType::UInt32,
+                )),
+                "u64" => Some((
+                    value.as_u64().map(convert_value_to_bytes),
+                    UserUniformType::UInt64,
+                )),
+                "i32" => Some((
+                    value.as_i32().map(convert_value_to_bytes),
+                    UserUniformType::Int32,
+                )),
+                "i64" => Some((
+                    value.as_i64().map(convert_value_to_bytes),
+                    UserUniformType::Int64,
+                )),
                 // Note we bind booleans as u32
-                let uint_value: u32 = match value.as_bool().unwrap() {
-                    true => 1,
-                    false => 0,
-                };
-                uniforms.insert(UserUniform {
-                    bytes: convert_value_to_bytes(uint_value),
+                "bool" => Some((
+                    value
+                        .as_bool()
+                        .map(|value| convert_value_to_bytes(value as u32)),
+                    UserUniformType::Bool,
+                )),
+                _ => None,
+            };
+            let mut uniform = match scalar {
+                Some((Some(bytes), inherent_type)) => UserUniform {
+                    bytes,
                     name: String::from(name),
-                    inherent_type: UserUniformType::Bool,
-                });
-            } else {
-                error!("Uniform with invalid type {} found, ignoring.", type_str);
+                    inherent_type,
+                    metadata: UniformMetadata::default(),
+                },
+                Some((None, _)) => {
+                    error!("Uniform {} has no valid value, ignoring.", name);
+                    continue;
+                }
+                None => match load_vector_uniform(name, type_str, value) {
+                    Ok(uniform) => uniform,
+                    Err(e) => {
+                        error!("{}, ignoring.", e);
+                        continue;
+                    }
+                },
+            };
+            if entry.1.is_object() {
+                match UniformMetadata::from_json(name, uniform.inherent_type, entry.1) {
+                    Ok(metadata) => uniform.metadata = metadata,
+                    Err(e) => error!("{}, ignoring its metadata.", e),
+                }
             }
+            uniforms.push(uniform);
         }
     }
 
     uniforms
 }
 
-/// Builds the UI element for the given uniform and updates it with the latest value.
-///
-/// * `ui` - Reference to [imgui::Ui] object.
-/// * `uniform` - The [UserUniform] object to visualise and update.
-pub fn update_user_uniform_ui(ui: &imgui::Ui, uniform: &mut UserUniform) {
-    match uniform.inherent_type {
-        // 32 bit types
-        UserUniformType::Float32 => {
-            let mut value = uniform.get_value::<f32>().unwrap();
-            ui.input_float(&ImString::from(uniform.name.clone()), &mut value)
-                .build();
-            uniform.bytes = convert_value_to_bytes(value);
-        }
-        UserUniformType::Int32 => {
-            let mut value = uniform.get_value::<i32>().unwrap();
-            ui.input_int(&ImString::from(uniform.name.clone()), &mut value)
-                .build();
-            uniform.bytes = convert_value_to_bytes(value);
-        }
-        UserUniformType::UInt32 => {
-            let value = uniform.get_value::<u32>().unwrap();
-            let mut value_i32 = value as i32;
-            ui.input_int(&ImString::from(uniform.name.clone()), &mut value_i32)
-                .build();
-            uniform.bytes = convert_value_to_bytes(value);
-        }
-        // 64 bit types
-        UserUniformType::Float64 => {
-            let mut value = uniform.get_value::<f32>().unwrap();
-            ui.input_float(&ImString::from(uniform.name.clone()), &mut value)
-                .build();
-            uniform.bytes = convert_value_to_bytes(value as f64);
-        }
-        UserUniformType::Int64 => {
-            let mut value = uniform.get_value::<i32>().unwrap();
-            ui.input_int(&ImString::from(uniform.name.clone()), &mut value)
-                .build();
-            uniform.bytes = convert_value_to_bytes(value as i64);
-        }
-        UserUniformType::UInt64 => {
-            let value = uniform.get_value::<u32>().unwrap();
-            let mut value_i32 = value as i32;
-            ui.input_int(&ImString::from(uniform.name.clone()), &mut value_i32)
-                .build();
-            uniform.bytes = convert_value_to_bytes(value_i32 as u64);
-        }
-        // Bool is a special case
-        UserUniformType::Bool => {
-            let value = uniform.get_value::<u32>().unwrap();
-            let mut value_bool = value != 0;
-            ui.checkbox(&ImString::from(uniform.name.clone()), &mut value_bool);
-            uniform.bytes = convert_value_to_bytes(value_bool as u32);
+/// Store the values of user uniforms in the JSON of a uniforms file, keeping everything else in it,
+/// such as uniform metadata and keys Easel doesn't use. See [load_uniforms_from_json()] for the format.
+/// Uniforms in the object form have their `value` replaced, and the rest are written in the `["f32", 4.0]` form.
+/// Uniforms missing from the file are added after the others.
+pub fn store_uniforms_in_json(data: &mut json::JsonValue, user_uniforms: &[UserUniform]) {
+    if !data["uniforms"].is_object() {
+        data["uniforms"] = json::JsonValue::new_object();
+    }
+    let uniforms_json = &mut data["uniforms"];
+    for uniform in user_uniforms {
+        let entry = &mut uniforms_json[uniform.name.as_str()];
+        match entry.is_object() {
+            true => entry["value"] = uniform.to_json()[1].clone(),
+            false => *entry = uniform.to_json(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(text: &str) -> Vec<UserUniform> {
+        load_uniforms_from_json(&json::parse(text).unwrap())
+    }
+
+    fn mixed_uniforms() -> Vec<UserUniform> {
+        load(
+            r#"{"uniforms": {
+                "zoom": ["f32", 2.0],
+                "tint": ["vec3", [0.1, 0.2, 0.3]],
+                "gain": ["f32", 0.5],
+                "warp": ["mat3", [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]]],
+                "dynamic": ["bool", true],
+                "seed": ["u64", 5],
+                "offset": ["vec2", [-1.0, 1.0]]
+            }}"#,
+        )
+    }
+
+    #[test]
+    fn std140_layout_of_mixed_declaration() {
+        let uniforms = mixed_uniforms();
+        let (offsets, size) = std140_layout(&uniforms);
+        // A float packs into the padding after a vec3, a mat3 takes three 16-byte columns,
+        // and a u64 after a bool is aligned to 8 bytes.
+        assert_eq!(offsets, vec![0, 16, 28, 32, 80, 88, 96]);
+        assert_eq!(size, 112);
+    }
+
+    #[test]
+    fn std140_bytes_pads_mat3_columns() {
+        let uniforms = mixed_uniforms();
+        let bytes = std140_bytes(&uniforms);
+        assert_eq!(bytes.len(), 112);
+        let f32_at =
+            |offset: usize| convert_bytes_to_value::<f32>(&bytes[offset..offset + 4]).unwrap();
+        assert_eq!(f32_at(0), 2.0);
+        assert_eq!(f32_at(28), 0.5);
+        for (column, offset) in [32, 48, 64].iter().enumerate() {
+            for row in 0..3 {
+                assert_eq!(f32_at(offset + row * 4), (column * 3 + row + 1) as f32);
+            }
+            assert_eq!(f32_at(offset + 12), 0.0);
+        }
+        assert_eq!(convert_bytes_to_value::<u32>(&bytes[80..84]).unwrap(), 1);
+        assert_eq!(convert_bytes_to_value::<u64>(&bytes[88..96]).unwrap(), 5);
+        assert_eq!(f32_at(96), -1.0);
+        assert_eq!(f32_at(100), 1.0);
+    }
+
+    #[test]
+    fn same_std140_layout_compares_types_in_order() {
+        let uniforms = mixed_uniforms();
+        let mut reordered = uniforms.clone();
+        reordered.swap(0, 1);
+        assert!(same_std140_layout(&uniforms, &uniforms.clone()));
+        assert!(!same_std140_layout(&uniforms, &reordered));
+        assert!(!same_std140_layout(&uniforms, &uniforms[1..]));
+    }
+
+    #[test]
+    fn stored_uniforms_load_in_declared_order() {
+        let uniforms = mixed_uniforms();
+        let mut data = json::parse(
+            r#"{"uniforms": {"gain": {"type": "f32", "value": 1.0, "min": 0.0, "max": 1.0}}}"#,
+        )
+        .unwrap();
+        store_uniforms_in_json(&mut data, &uniforms);
+        let loaded = load_uniforms_from_json(&data);
+        let names = |uniforms: &[UserUniform]| -> Vec<String> {
+            uniforms
+                .iter()
+                .map(|uniform| uniform.name.clone())
+                .collect()
+        };
+        // Uniforms already in the file keep their place, the rest follow in declared order.
+        assert_eq!(
+            names(&loaded),
+            vec!["gain", "zoom", "tint", "warp", "dynamic", "seed", "offset"]
+        );
+        for uniform in &uniforms {
+            let loaded = loaded.iter().find(|l| l.name == uniform.name).unwrap();
+            assert_eq!(loaded.inherent_type, uniform.inherent_type);
+            assert_eq!(loaded.bytes, uniform.bytes);
+        }
+        // Metadata in the object form is kept.
+        assert_eq!(loaded[0].metadata.range(), Some((0.0, 1.0)));
+
+        let mut data = json::JsonValue::new_object();
+        store_uniforms_in_json(&mut data, &uniforms);
+        assert_eq!(names(&load_uniforms_from_json(&data)), names(&uniforms));
+    }
+}
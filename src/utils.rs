@@ -1,14 +1,9 @@
+use crate::skeletons;
+use crate::uniforms::UserUniform;
 use crate::vector::UIntVector2;
 use byteorder::{NativeEndian, WriteBytesExt};
-use futures::executor::block_on;
 use half::prelude::*;
-use image::ImageEncoder;
-use image::{codecs::png::PngEncoder, tiff::TiffEncoder};
-use log::info;
-use std::fs::File;
-use std::io::BufWriter;
 use std::path::Path;
-use std::sync::mpsc::{channel, Receiver};
 use std::vec::Vec;
 use wgpu::{BindGroupLayoutDescriptor, BindGroupLayoutEntry, BlendState};
 
@@ -33,7 +28,13 @@ fn load_shader_source(
 /// Loads a shader from the given file. Can be either text source or compiled SPIR-V blob.
 /// Returns a Result with the binary data of the loaded/compiled shader or an error from ShaderC
 /// if unable to compile.
-pub fn load_shader(shader_file: &str) -> Result<Vec<u8>, shaderc::Error> {
+/// Text shaders may `#include "easel_uniforms.glsl"` to declare the user uniforms, see [crate::skeletons::uniforms_header()].
+/// * `shader_file` - Path to the `.frag` source or `.spv` blob.
+/// * `user_uniforms` - The user uniforms that will be bound to the shader, in order.
+pub fn load_shader(
+    shader_file: &str,
+    user_uniforms: &[UserUniform],
+) -> Result<Vec<u8>, shaderc::Error> {
     // Determine if shader text file provided or SPIR-V binary blob.
     let tokens = shader_file.split(".").collect::<Vec<&str>>();
     assert!(
@@ -58,6 +59,13 @@ pub fn load_shader(shader_file: &str) -> Result<Vec<u8>, shaderc::Error> {
                 }
                 // Read text data from include file.
                 let path_to_file = shader_dir.join(Path::new(source_name));
+                // Generate the user uniforms block, unless there's a real file by that name.
+                if source_name == skeletons::UNIFORMS_INCLUDE && !path_to_file.exists() {
+                    return Ok(shaderc::ResolvedInclude {
+                        resolved_name: source_name.to_string(),
+                        content: skeletons::uniforms_header(user_uniforms),
+                    });
+                }
                 let include_src = std::fs::read_to_string(path_to_file.to_str().unwrap()).expect("Unable to find include file.");
                 // Return info.
                 Ok(shaderc::ResolvedInclude{
@@ -84,145 +92,90 @@ pub fn load_shader(shader_file: &str) -> Result<Vec<u8>, shaderc::Error> {
     Result::Ok(fs_spv_data)
 }
 
+/// Number of bytes in each row of a texture copied to a buffer.
+/// wgpu requires rows to be a multiple of [wgpu::COPY_BYTES_PER_ROW_ALIGNMENT] bytes, so rows are padded at the end as needed.
+/// * `width` - Width of the texture, in pixels.
+/// * `bytes_per_pixel` - Size of one pixel of the texture format, in bytes.
+pub fn padded_bytes_per_row(width: u32, bytes_per_pixel: usize) -> usize {
+    let unpadded_bytes_per_row = width as usize * bytes_per_pixel;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+    unpadded_bytes_per_row.next_multiple_of(align)
+}
+
+/// Read back a movie frame of [crate::recording::MOVIE_TEXTURE_FORMAT] and append its pixels to `pixel_data`,
+/// as tightly packed 8-bit RGBA with the row padding removed.
+/// * `painting` - Staging buffer the frame was copied to, with rows padded as per [padded_bytes_per_row()].
+/// * `resolution` - The width and height of the frame.
+/// * `pixel_data` - Destination for the pixels.
 pub async fn transcode_frame_data_for_movie(
     painting: wgpu::Buffer,
     resolution: UIntVector2,
     pixel_data: &mut Vec<u8>,
 ) {
     let (width, height) = (resolution.x, resolution.y);
+    let padded_row_bytes = padded_bytes_per_row(width, 4);
+    let row_bytes = width as usize * 4;
     let slice = painting.slice(0..);
     slice.map_async(wgpu::MapMode::Read).await.unwrap();
     let buf_view = slice.get_mapped_range();
     pixel_data.reserve((width * height * 4) as usize);
-    for i in 0..(width * height) {
-        // This puts us the beginning of the pixel
-        let pixel_idx = (i * 4) as usize;
-        // Load each component, excluding alpha
-        for component_idx in 0..4 {
-            // Load the bytes of each component.
-            let component_data = (*buf_view)[pixel_idx + component_idx];
-            pixel_data.push(component_data);
-        }
+    for row in 0..height as usize {
+        // Copy the pixels of each row, skipping the padding at its end.
+        let row_idx = row * padded_row_bytes;
+        pixel_data.extend_from_slice(&(*buf_view)[row_idx..row_idx + row_bytes]);
     }
 }
 
-pub async fn transcode_painting_data(
+/// Read back a painting of [crate::renderer::PAINTING_TEXTURE_FORMAT] and append its components to `pixel_data`,
+/// as tightly packed RGBA half floats with the row padding removed.
+/// * `painting` - Staging buffer the painting was copied to, with rows padded as per [padded_bytes_per_row()].
+/// * `resolution` - The width and height of the painting.
+/// * `pixel_data` - Destination for the pixel components.
+pub async fn read_painting_data(
     painting: wgpu::Buffer,
     resolution: UIntVector2,
-    pixel_data: &mut Vec<u8>,
+    pixel_data: &mut Vec<f16>,
 ) {
     let (width, height) = (resolution.x, resolution.y);
+    let padded_row_bytes = padded_bytes_per_row(width, 8);
+    let row_bytes = width as usize * 8;
     let slice = painting.slice(0..);
     slice.map_async(wgpu::MapMode::Read).await.unwrap();
     let buf_view = slice.get_mapped_range();
-    pixel_data.reserve((width * height * 4) as usize * std::mem::size_of::<u16>());
-    for i in 0..(width * height) {
-        // This puts us the beginning of the pixel
-        let pixel_idx = (i * 8) as usize;
-
-        let mut bytes_workarea = Vec::with_capacity(2);
-        // Load each component
-        for component_idx in 0..4 {
-            // Load the bytes of each component.
-            let component_data = [
-                (*buf_view)[pixel_idx + (2 * component_idx) + 0],
-                (*buf_view)[pixel_idx + (2 * component_idx) + 1],
-            ];
-
-            // Convert bytes to f16.
-            let component_f16 = unsafe { std::mem::transmute::<[u8; 2], f16>(component_data) };
-            // Convert to 16 bit uint and write.
-            let component_u16 = (component_f16.to_f32() * 65535.0) as u16;
-            bytes_workarea.clear();
-            bytes_workarea
-                .write_u16::<NativeEndian>(component_u16)
-                .unwrap();
-            pixel_data.extend_from_slice(&bytes_workarea);
+    pixel_data.reserve((width * height * 4) as usize);
+    for row in 0..height as usize {
+        // Load each component of the row, skipping the padding at its end.
+        let row_idx = row * padded_row_bytes;
+        for component_data in (*buf_view)[row_idx..row_idx + row_bytes].chunks_exact(2) {
+            pixel_data.push(f16::from_ne_bytes([component_data[0], component_data[1]]));
         }
     }
 }
 
-#[allow(dead_code)]
-pub fn encode_image_buffer_to_png(
-    pixel_data: &Vec<u8>,
+/// Read back a painting of [crate::renderer::PAINTING_TEXTURE_FORMAT] and append its pixels to `pixel_data`,
+/// as tightly packed RGBA, 16-bit unsigned integer per component, in native byte order.
+/// Components are clamped to [0, 1] before conversion.
+/// * `painting` - Staging buffer the painting was copied to, with rows padded as per [padded_bytes_per_row()].
+/// * `resolution` - The width and height of the painting.
+/// * `pixel_data` - Destination for the pixels.
+pub async fn transcode_painting_data(
+    painting: wgpu::Buffer,
     resolution: UIntVector2,
-    output_file: File,
+    pixel_data: &mut Vec<u8>,
 ) {
-    let encoder = PngEncoder::new(output_file);
-    encoder
-        .encode(
-            pixel_data,
-            resolution.x,
-            resolution.y,
-            image::ColorType::Rgba8,
-        )
-        .unwrap();
-}
-
-/// An enum used by the [AsyncTiffWriter] class to signify a write operation has finished.
-pub enum WriteFinished {
-    Finished,
+    let mut components = Vec::new();
+    read_painting_data(painting, resolution, &mut components).await;
+    transcode_painting_components(&components, pixel_data);
 }
 
-/// A struct used to write a painting to disk after rendering.
-pub struct AsyncTiffWriter {}
-
-impl AsyncTiffWriter {
-    /// Private helper method called by [AsyncTiffWriter::write]
-    async fn write_painting_to_disk(
-        painting: wgpu::Buffer,
-        resolution: UIntVector2,
-        filename: &str,
-        _open_external_app: bool,
-    ) {
-        let width = resolution.x;
-        let height = resolution.y;
-        let mut pixel_data = Vec::<u8>::new();
-        transcode_painting_data(painting, resolution, &mut pixel_data).await;
-
-        {
-            let file = File::create(Path::new(filename)).unwrap();
-            let buf_writer = BufWriter::new(file);
-            TiffEncoder::new(buf_writer)
-                .write_image(&pixel_data, width, height, image::ColorType::Rgba16)
-                .unwrap();
-        }
-        // Once writing has finished, open in external app if specified.
-        #[cfg(target_os = "macos")]
-        if _open_external_app {
-            std::process::Command::new("open")
-                .arg(filename)
-                .spawn()
-                .expect("Error launching external app to display painting.");
-        }
-    }
-
-    /// Given a painting present in GPU memory, copy to CPU, construct a TIFF painting and write to disk.
-    /// Paintings are written with uncompressed 16-bit uint TIFF encoding.
-    /// **Note:** This function launches an async task and returns immediately.
-    /// Use the returned [std::sync::mpsc::Receiver] object which can be used to poll for status updates.
-    /// * `painting` - WGPU buffer holding the image data.
-    /// * `resolution` - The width and height of the image.
-    /// * `filename` - File will be written relative to working directory and with .tiff extension.
-    /// * `open_external_app` - Optionally launch external program to view the image. Only supported on macOS and Windows.
-    pub fn write(
-        buffer: wgpu::Buffer,
-        resolution: UIntVector2,
-        filename: String,
-        open_external_app: bool,
-    ) -> Receiver<WriteFinished> {
-        let (tx, rx) = channel();
-        std::thread::spawn(move || {
-            block_on(AsyncTiffWriter::write_painting_to_disk(
-                buffer,
-                resolution,
-                &filename,
-                open_external_app,
-            ));
-            info!("Wrote painting {} to disk", filename);
-            tx.send(WriteFinished::Finished).unwrap();
-        });
-        rx
+/// Convert half float components, as read by [read_painting_data()], to 16-bit unsigned integers in
+/// native byte order and append them to `pixel_data`. Components are clamped to [0, 1] before conversion.
+pub fn transcode_painting_components(components: &[f16], pixel_data: &mut Vec<u8>) {
+    pixel_data.reserve(components.len() * std::mem::size_of::<u16>());
+    for component_f16 in components {
+        // Convert to 16 bit uint and write.
+        let component_u16 = (component_f16.to_f32().clamp(0.0, 1.0) * 65535.0).round() as u16;
+        pixel_data.write_u16::<NativeEndian>(component_u16).unwrap();
     }
 }
 
@@ -431,3 +384,9 @@ pub fn convert_value_to_bytes<'a, T>(value: T) -> Vec<u8> {
     bytes.extend_from_slice(&bs);
     bytes
 }
+
+/// Describe an f32 as a JSON number with the fewest digits that read back as the same f32, such as `0.4`.
+/// Converting the f32 straight to JSON widens it to an f64 first, which writes `0.4000000059604645`.
+pub fn f32_to_json(value: f32) -> json::JsonValue {
+    value.to_string().parse::<f64>().unwrap().into()
+}